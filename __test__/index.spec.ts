@@ -1,5 +1,11 @@
 import test from "ava"
-import { getCurrentSession, getSessions, getSessionById } from "../binding"
+import {
+  getCurrentSession,
+  getSessions,
+  getSessionById,
+  getSessionByKey,
+  getSessionsByAppId,
+} from "../binding"
 
 test("getCurrentSession() should return MediaInfo | null", (t) => {
   const session = getCurrentSession()
@@ -14,3 +20,11 @@ test("getSessions() should return MediaInfo[]", (t) => {
 test("getSessionById() should return null", (t) => {
   t.is(getSessionById("nonexistent"), null)
 })
+
+test("getSessionByKey() should return null", (t) => {
+  t.is(getSessionByKey("nonexistent#1"), null)
+})
+
+test("getSessionsByAppId() should return an empty array", (t) => {
+  t.deepEqual(getSessionsByAppId("nonexistent"), [])
+})
@@ -3,28 +3,175 @@
 
 /* auto-generated by NAPI-RS */
 
-export declare function getCurrentSession(): MediaInfo | null
-export declare function getSessions(): Array<MediaInfo>
-export declare function getSessionById(sourceAppId: string): MediaInfo | null
+export declare function getCurrentSession(options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): MediaInfo | null
+export declare function getSessions(options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): Array<MediaInfo>
+export declare function getSessionById(sourceAppId: string, options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): MediaInfo | null
+export declare function getSessionByKey(sessionKey: string, options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): MediaInfo | null
+export declare function getSessionsByAppId(sourceAppId: string, options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): Array<MediaInfo>
+export declare function getCurrentSessionAsync(options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): Promise<MediaInfo | null>
+export declare function getSessionsAsync(options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): Promise<MediaInfo[]>
+export declare function getSessionByIdAsync(sourceAppId: string, options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): Promise<MediaInfo | null>
+export declare function getSessionByKeyAsync(sessionKey: string, options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): Promise<MediaInfo | null>
+export declare function getSessionsByAppIdAsync(sourceAppId: string, options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): Promise<MediaInfo[]>
+export declare function getThumbnail(sessionKey: string, options?: ThumbnailOptions | undefined | null, timeoutMs?: number | undefined | null): Promise<Buffer | null>
+export declare function getThumbnailByHash(hash: string): Buffer | null
+export declare function play(sourceAppId?: string | undefined | null, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function pause(sourceAppId?: string | undefined | null, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function togglePlayPause(sourceAppId?: string | undefined | null, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function stop(sourceAppId?: string | undefined | null, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function skipNext(sourceAppId?: string | undefined | null, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function skipPrevious(sourceAppId?: string | undefined | null, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function setShuffle(sourceAppId: string | undefined | null, active: boolean, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function setRepeatMode(sourceAppId: string | undefined | null, mode: RepeatMode, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function setPlaybackRate(sourceAppId: string | undefined | null, rate: number, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function seekTo(sourceAppId: string | undefined | null, seconds: number, timeoutMs?: number | undefined | null): Promise<boolean>
+export declare function seekBy(sourceAppId: string | undefined | null, offsetSeconds: number, timeoutMs?: number | undefined | null): Promise<boolean>
+export interface SessionCallbackData {
+  sourceAppId: string
+  sessionKey: string
+}
 export interface MediaPropsCallbackData {
   sourceAppId: string
+  sessionKey: string
   mediaProps: MediaProps
 }
 export interface PlaybackInfoCallbackData {
   sourceAppId: string
+  sessionKey: string
   playbackInfo: PlaybackInfo
 }
 export interface TimelinePropsCallbackData {
   sourceAppId: string
+  sessionKey: string
   timelineProps: TimelineProps
 }
+export interface SessionUpdatedCallbackData {
+  sourceAppId: string
+  sessionKey: string
+  mediaInfo: MediaInfo
+  changedFields: Array<string>
+}
+export interface MonitorState {
+  sessions: Array<MediaInfo>
+  currentSessionKey?: string
+}
+export interface MonitorOptions {
+  queueSize?: number
+  policy?: 'blocking' | 'drop-oldest' | 'coalesce-latest'
+  thumbnail?: ThumbnailOptions
+  timeoutMs?: number
+  omitUnchangedThumbnail?: boolean
+}
+export interface ThumbnailPalette {
+  dominant: string
+  vibrant?: string
+  muted?: string
+  blurhash: string
+}
+export interface ReplayOptions {
+  speed?: number
+}
+export interface SessionManagerMetrics {
+  createdCount: number
+  reusedCount: number
+  resetCount: number
+  lastCreatedTime?: number
+}
+export declare function resetSessionManager(): void
+export declare function getSessionManagerMetrics(): SessionManagerMetrics
+export interface SimulatedMediaProps {
+  title?: string
+  artist?: string
+  albumTitle?: string
+  albumArtist?: string
+  genres?: Array<string>
+  albumTrackCount?: number
+  trackNumber?: number
+  thumbnail?: Buffer | undefined
+}
+export interface SimulatedPlaybackInfo {
+  playbackStatus?: PlaybackStatus
+  playbackType?: PlaybackType
+  isShuffleActive?: boolean
+  autoRepeatMode?: RepeatMode
+  playbackRate?: number
+  controls?: PlaybackControls
+}
+export interface SimulatedTimelineProps {
+  position?: number
+  duration?: number
+  startTime?: number
+  minSeekTime?: number
+  maxSeekTime?: number
+}
+export interface SimulatedSessionInit {
+  media?: SimulatedMediaProps
+  playback?: SimulatedPlaybackInfo
+  timeline?: SimulatedTimelineProps
+}
+export interface ThumbnailOptions {
+  includeThumbnail?: boolean
+  maxBytes?: number
+  format?: 'png' | 'jpeg' | 'webp'
+  maxDimension?: number
+  quality?: number
+  palette?: boolean
+}
 export interface TimelineProps {
   position: number
   duration: number
+  startTime: number
+  minSeekTime: number
+  maxSeekTime: number
+  lastUpdatedTime?: number
+  estimatedPosition?: number
+}
+export const enum PlaybackStatus {
+  CLOSED = 0,
+  OPENED = 1,
+  CHANGING = 2,
+  STOPPED = 3,
+  PLAYING = 4,
+  PAUSED = 5,
+  UNRECOGNIZED = -1
+}
+export const enum PlaybackType {
+  UNKNOWN = 0,
+  MUSIC = 1,
+  VIDEO = 2,
+  IMAGE = 3,
+  UNRECOGNIZED = -1
+}
+export const enum RepeatMode {
+  NONE = 0,
+  TRACK = 1,
+  LIST = 2,
+  UNRECOGNIZED = -1
 }
 export interface PlaybackInfo {
-  playbackStatus: number
-  playbackType: number
+  playbackStatus: PlaybackStatus
+  playbackType: PlaybackType
+  isShuffleActive?: boolean
+  autoRepeatMode?: RepeatMode
+  playbackRate?: number
+  controls: PlaybackControls
+}
+export interface PlaybackControls {
+  play: boolean
+  pause: boolean
+  stop: boolean
+  record: boolean
+  fastForward: boolean
+  rewind: boolean
+  next: boolean
+  previous: boolean
+  channelUp: boolean
+  channelDown: boolean
+  playPauseToggle: boolean
+  shuffle: boolean
+  repeat: boolean
+  playbackRate: boolean
+  seek: boolean
 }
 export interface MediaProps {
   title: string
@@ -35,22 +182,68 @@ export interface MediaProps {
   albumTrackCount: number
   trackNumber: number
   thumbnail?: Buffer | undefined
+  thumbnailTruncated: boolean
+  thumbnailHash?: string
+  thumbnailMimeType?: string
+  thumbnailWidth?: number
+  thumbnailHeight?: number
+  thumbnailPalette?: ThumbnailPalette
 }
 export interface MediaInfo {
   sourceAppId: string
+  sessionKey: string
   media: MediaProps
   playback: PlaybackInfo
   timeline: TimelineProps
   lastUpdatedTime: number
+  error?: string
 }
 export declare class SMTCMonitor {
-  constructor()
+  constructor(options?: MonitorOptions | undefined | null)
   initialize(): void
-  onSessionAdded(callback: (error:unknown, media: MediaInfo) => void): void
-  onSessionRemoved(callback: (error:unknown, sourceAppId: string) => void): void
-  onMediaPropertiesChanged(callback: (error:unknown, data: {sourceAppId: string, mediaProps: MediaProps}) => void): void
-  onPlaybackInfoChanged(callback: (error:unknown, data: {sourceAppId: string, playbackInfo: PlaybackInfo}) => void): void
-  onTimelinePropertiesChanged(callback: (error:unknown, data: {sourceAppId: string, timelineProps: TimelineProps}) => void): void
-  onCurrentSessionChanged(callback: (error:unknown, sourceAppId: string) => void): void
+  attachSimulator(simulator: MediaSimulator): void
+  onSessionAdded(callback: (error:unknown, media: MediaInfo) => void): number
+  onSessionRemoved(callback: (error:unknown, data: {sourceAppId: string, sessionKey: string}) => void): number
+  onMediaPropertiesChanged(callback: (error:unknown, data: {sourceAppId: string, sessionKey: string, mediaProps: MediaProps}) => void): number
+  onPlaybackInfoChanged(callback: (error:unknown, data: {sourceAppId: string, sessionKey: string, playbackInfo: PlaybackInfo}) => void): number
+  onTimelinePropertiesChanged(callback: (error:unknown, data: {sourceAppId: string, sessionKey: string, timelineProps: TimelineProps}) => void): number
+  onCurrentSessionChanged(callback: (error:unknown, data: {sourceAppId: string, sessionKey: string}) => void): number
+  onSessionUpdated(callback: (error:unknown, data: {sourceAppId: string, sessionKey: string, mediaInfo: MediaInfo, changedFields: string[]}) => void): number
+  getState(): MonitorState
+  play(sourceAppId?: string | undefined | null): Promise<boolean>
+  pause(sourceAppId?: string | undefined | null): Promise<boolean>
+  togglePlayPause(sourceAppId?: string | undefined | null): Promise<boolean>
+  stop(sourceAppId?: string | undefined | null): Promise<boolean>
+  skipNext(sourceAppId?: string | undefined | null): Promise<boolean>
+  skipPrevious(sourceAppId?: string | undefined | null): Promise<boolean>
+  setShuffle(sourceAppId: string | undefined | null, active: boolean): Promise<boolean>
+  setRepeatMode(sourceAppId: string | undefined | null, mode: RepeatMode): Promise<boolean>
+  setPlaybackRate(sourceAppId: string | undefined | null, rate: number): Promise<boolean>
+  seekTo(sourceAppId: string | undefined | null, seconds: number): Promise<boolean>
+  seekBy(sourceAppId: string | undefined | null, offsetSeconds: number): Promise<boolean>
+  getEstimatedPosition(sourceAppId?: string | undefined | null): number | null
+  getDroppedEventCount(): number
+  startRecording(path: string): void
+  stopRecording(): boolean
+  off(subscriptionId: number): boolean
+  removeListener(subscriptionId: number): boolean
   destroy(): void
 }
+export declare class EventReplay {
+  constructor(path: string, options?: ReplayOptions | undefined | null)
+  simulator(): MediaSimulator
+  getEventCount(): number
+  start(): Promise<number>
+  stop(): void
+}
+export declare class MediaSimulator {
+  constructor()
+  addSession(sourceAppId: string, init?: SimulatedSessionInit | undefined | null): string
+  removeSession(sessionKey: string): boolean
+  setCurrentSession(sessionKey?: string | undefined | null): void
+  updateMediaProps(sessionKey: string, media: SimulatedMediaProps): void
+  updatePlaybackInfo(sessionKey: string, playback: SimulatedPlaybackInfo): void
+  updateTimelineProps(sessionKey: string, timeline: SimulatedTimelineProps): void
+  getSessions(options?: ThumbnailOptions | undefined | null): Array<MediaInfo>
+  getCurrentSession(options?: ThumbnailOptions | undefined | null): MediaInfo | null
+}
@@ -310,9 +310,36 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { getCurrentSession, getSessions, getSessionById, SMTCMonitor } = nativeBinding
+const { getCurrentSession, getSessions, getSessionById, getSessionByKey, getSessionsByAppId, getCurrentSessionAsync, getSessionsAsync, getSessionByIdAsync, getSessionByKeyAsync, getSessionsByAppIdAsync, getThumbnail, getThumbnailByHash, play, pause, togglePlayPause, stop, skipNext, skipPrevious, setShuffle, setRepeatMode, setPlaybackRate, seekTo, seekBy, resetSessionManager, getSessionManagerMetrics, PlaybackStatus, PlaybackType, RepeatMode, SMTCMonitor, EventReplay, MediaSimulator } = nativeBinding
 
 module.exports.getCurrentSession = getCurrentSession
 module.exports.getSessions = getSessions
 module.exports.getSessionById = getSessionById
+module.exports.getSessionByKey = getSessionByKey
+module.exports.getSessionsByAppId = getSessionsByAppId
+module.exports.getCurrentSessionAsync = getCurrentSessionAsync
+module.exports.getSessionsAsync = getSessionsAsync
+module.exports.getSessionByIdAsync = getSessionByIdAsync
+module.exports.getSessionByKeyAsync = getSessionByKeyAsync
+module.exports.getSessionsByAppIdAsync = getSessionsByAppIdAsync
+module.exports.getThumbnail = getThumbnail
+module.exports.getThumbnailByHash = getThumbnailByHash
+module.exports.play = play
+module.exports.pause = pause
+module.exports.togglePlayPause = togglePlayPause
+module.exports.stop = stop
+module.exports.skipNext = skipNext
+module.exports.skipPrevious = skipPrevious
+module.exports.setShuffle = setShuffle
+module.exports.setRepeatMode = setRepeatMode
+module.exports.setPlaybackRate = setPlaybackRate
+module.exports.seekTo = seekTo
+module.exports.seekBy = seekBy
+module.exports.resetSessionManager = resetSessionManager
+module.exports.getSessionManagerMetrics = getSessionManagerMetrics
+module.exports.PlaybackStatus = PlaybackStatus
+module.exports.PlaybackType = PlaybackType
+module.exports.RepeatMode = RepeatMode
 module.exports.SMTCMonitor = SMTCMonitor
+module.exports.EventReplay = EventReplay
+module.exports.MediaSimulator = MediaSimulator
@@ -1,12 +1,8 @@
-const PlaybackStatus = {
-  CLOSED: 0,
-  OPENED: 1,
-  CHANGING: 2,
-  STOPPED: 3,
-  PLAYING: 4,
-  PAUSED: 5,
-}
+// 播放状态、类型与循环模式由 Rust 端生成，避免与原生取值不一致
+const { PlaybackStatus, PlaybackType, RepeatMode } = require("./binding")
 
 module.exports = {
   PlaybackStatus,
+  PlaybackType,
+  RepeatMode,
 }
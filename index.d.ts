@@ -1,55 +1,96 @@
 import { EventEmitter } from "events"
-import { SMTCMonitor as SMTC } from "./binding"
+import { SMTCMonitor as SMTC, MediaSimulator, EventReplay, PlaybackStatus, PlaybackType, RepeatMode } from "./binding"
 import type {
   MediaInfo,
   MediaProps,
   PlaybackInfo,
+  PlaybackControls,
   TimelineProps,
   MediaPropsCallbackData,
   PlaybackInfoCallbackData,
   TimelinePropsCallbackData,
+  SessionCallbackData,
+  SessionUpdatedCallbackData,
+  MonitorState,
+  MonitorOptions,
+  ThumbnailOptions,
+  ThumbnailPalette,
+  SessionManagerMetrics,
+  SimulatedSessionInit,
+  SimulatedMediaProps,
+  SimulatedPlaybackInfo,
+  SimulatedTimelineProps,
+  ReplayOptions,
 } from "./binding"
 
-export enum PlaybackStatus {
-  CLOSED = 0,
-  OPENED = 1,
-  CHANGING = 2,
-  STOPPED = 3,
-  PLAYING = 4,
-  PAUSED = 5,
-}
-
 declare class SMTCMonitor extends EventEmitter {
-  constructor()
+  constructor(options?: MonitorOptions, simulator?: MediaSimulator)
 
   private smtc: SMTC
-  private _mediaSessions: Map<string, MediaInfo>
+  readonly simulator: MediaSimulator | null
+  readonly replay?: EventReplay
+  private _sessionKeys: Set<string>
+  private _subscriptions: number[]
 
   private _initialize(): void
   private _preloadSessions(): void
   private _bindEvents(): void
+  private _subscribe(method: string, handler: (data: any) => void): void
 
   private _onMediaPropertiesChanged(data: MediaPropsCallbackData): void
   private _onTimelinePropertiesChanged(data: TimelinePropsCallbackData): void
   private _onPlaybackInfoChanged(data: PlaybackInfoCallbackData): void
   private _onSessionAdded(media: MediaInfo): void
-  private _onSessionRemoved(sourceAppId: string): void
-  private _onCurrentSessionChanged(sourceAppId: string): void
+  private _onSessionRemoved(data: SessionCallbackData): void
+  private _onCurrentSessionChanged(data: SessionCallbackData): void
+  private _onSessionUpdated(data: SessionUpdatedCallbackData): void
 
-  static getMediaSessions(): MediaInfo[]
-  static getCurrentMediaSession(): MediaInfo | null
-  static getMediaSessionByAppId(sourceAppId: string): MediaInfo | null
+  static getMediaSessions(options?: ThumbnailOptions, timeoutMs?: number): MediaInfo[]
+  static getCurrentMediaSession(options?: ThumbnailOptions, timeoutMs?: number): MediaInfo | null
+  static getMediaSessionByAppId(sourceAppId: string, options?: ThumbnailOptions, timeoutMs?: number): MediaInfo | null
+  static getMediaSessionByKey(sessionKey: string, options?: ThumbnailOptions, timeoutMs?: number): MediaInfo | null
+  static getMediaSessionsByAppId(sourceAppId: string, options?: ThumbnailOptions, timeoutMs?: number): MediaInfo[]
+  static getMediaSessionsAsync(options?: ThumbnailOptions, timeoutMs?: number): Promise<MediaInfo[]>
+  static getCurrentMediaSessionAsync(options?: ThumbnailOptions, timeoutMs?: number): Promise<MediaInfo | null>
+  static getMediaSessionByAppIdAsync(sourceAppId: string, options?: ThumbnailOptions, timeoutMs?: number): Promise<MediaInfo | null>
+  static getMediaSessionByKeyAsync(sessionKey: string, options?: ThumbnailOptions, timeoutMs?: number): Promise<MediaInfo | null>
+  static getMediaSessionsByAppIdAsync(sourceAppId: string, options?: ThumbnailOptions, timeoutMs?: number): Promise<MediaInfo[]>
+  static getThumbnail(sessionKey: string, options?: ThumbnailOptions, timeoutMs?: number): Promise<Buffer | null>
+  static getThumbnailByHash(hash: string): Buffer | null
+  static resetSessionManager(): void
+  static getSessionManagerMetrics(): SessionManagerMetrics
 
+  getState(): MonitorState
   get sessions(): MediaInfo[]
+  get droppedEventCount(): number
 
-  on(event: "session-media-changed", listener: (sourceAppId: string, mediaProps: MediaProps) => void): this
-  on(event: "session-timeline-changed", listener: (sourceAppId: string, timelineProps: TimelineProps) => void): this
-  on(event: "session-playback-changed", listener: (sourceAppId: string, playbackInfo: PlaybackInfo) => void): this
-  on(event: "session-added", listener: (sourceAppId: string, media: MediaInfo) => void): this
-  on(event: "session-removed", listener: (sourceAppId: string) => void): this
-  on(event: "current-session-changed", listener: (sourceAppId: string) => void): this
+  play(sourceAppId?: string | null): Promise<boolean>
+  pause(sourceAppId?: string | null): Promise<boolean>
+  togglePlayPause(sourceAppId?: string | null): Promise<boolean>
+  stop(sourceAppId?: string | null): Promise<boolean>
+  skipNext(sourceAppId?: string | null): Promise<boolean>
+  skipPrevious(sourceAppId?: string | null): Promise<boolean>
+  setShuffle(sourceAppId: string | null | undefined, active: boolean): Promise<boolean>
+  setRepeatMode(sourceAppId: string | null | undefined, mode: RepeatMode): Promise<boolean>
+  setPlaybackRate(sourceAppId: string | null | undefined, rate: number): Promise<boolean>
+  seekTo(sourceAppId: string | null | undefined, seconds: number): Promise<boolean>
+  seekBy(sourceAppId: string | null | undefined, offsetSeconds: number): Promise<boolean>
+  getEstimatedPosition(sourceAppId?: string | null): number | null
+  startRecording(path: string): void
+  stopRecording(): boolean
+
+  on(event: "session-media-changed", listener: (sourceAppId: string, mediaProps: MediaProps, sessionKey: string) => void): this
+  on(event: "session-timeline-changed", listener: (sourceAppId: string, timelineProps: TimelineProps, sessionKey: string) => void): this
+  on(event: "session-playback-changed", listener: (sourceAppId: string, playbackInfo: PlaybackInfo, sessionKey: string) => void): this
+  on(event: "session-added", listener: (sourceAppId: string, media: MediaInfo, sessionKey: string) => void): this
+  on(event: "session-removed", listener: (sourceAppId: string, sessionKey: string) => void): this
+  on(event: "current-session-changed", listener: (sourceAppId: string, sessionKey: string) => void): this
+  on(event: "session-updated", listener: (sourceAppId: string, mediaInfo: MediaInfo, changedFields: string[], sessionKey: string) => void): this
 
   destroy(): void
 }
 
-export { SMTCMonitor, MediaInfo, MediaProps, PlaybackInfo, TimelineProps }
+declare function createSimulatedMonitor(options?: MonitorOptions): SMTCMonitor & { readonly simulator: MediaSimulator }
+declare function createReplayMonitor(path: string, replayOptions?: ReplayOptions, options?: MonitorOptions): SMTCMonitor & { readonly simulator: MediaSimulator; readonly replay: EventReplay }
+
+export { SMTCMonitor, MediaSimulator, EventReplay, createSimulatedMonitor, createReplayMonitor, PlaybackStatus, PlaybackType, RepeatMode, MediaInfo, MediaProps, PlaybackInfo, PlaybackControls, TimelineProps, MonitorOptions, ThumbnailOptions, ThumbnailPalette, SessionManagerMetrics, MonitorState, SimulatedSessionInit, SimulatedMediaProps, SimulatedPlaybackInfo, SimulatedTimelineProps, ReplayOptions }
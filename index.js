@@ -1,124 +1,266 @@
 const os = require("os")
 const { EventEmitter } = require("events")
-const { PlaybackStatus } = require("./constant")
+const { PlaybackStatus, PlaybackType, RepeatMode } = require("./constant")
 const {
   SMTCMonitor: SMTC,
+  MediaSimulator,
+  EventReplay,
   getCurrentSession,
   getSessions,
   getSessionById,
+  getSessionByKey,
+  getSessionsByAppId,
+  getCurrentSessionAsync,
+  getSessionsAsync,
+  getSessionByIdAsync,
+  getSessionByKeyAsync,
+  getSessionsByAppIdAsync,
+  getThumbnail,
+  getThumbnailByHash,
+  resetSessionManager,
+  getSessionManagerMetrics,
 } = require("./binding")
 
 class SMTCMonitor extends EventEmitter {
-  constructor() {
+  constructor(options, simulator) {
     super()
-    this.smtc = new SMTC()
-    this._mediaSessions = new Map()
+    this.smtc = new SMTC(options)
+    this.simulator = simulator || null
+    this._sessionKeys = new Set()
+    this._subscriptions = []
     this._bindEvents()
     this._initialize()
     this._preloadSessions()
   }
 
   _initialize() {
-    this.smtc.initialize()
+    if (this.simulator) {
+      this.smtc.attachSimulator(this.simulator)
+    } else {
+      this.smtc.initialize()
+    }
   }
+  // 会话状态由 Rust 侧的快照维护，这里只记录已知的会话键用于过滤事件
   _preloadSessions() {
-    SMTCMonitor.getMediaSessions().forEach((session) => {
-      this._mediaSessions.set(session.sourceAppId, session)
+    this.smtc.getState().sessions.forEach((session) => {
+      this._sessionKeys.add(session.sessionKey)
     })
   }
 
   _bindEvents() {
-    this.smtc.onMediaPropertiesChanged((error, data) => {
-      !error && this._onMediaPropertiesChanged(data)
+    this._subscribe("onMediaPropertiesChanged", (data) => {
+      this._onMediaPropertiesChanged(data)
+    })
+
+    this._subscribe("onTimelinePropertiesChanged", (data) => {
+      this._onTimelinePropertiesChanged(data)
     })
 
-    this.smtc.onTimelinePropertiesChanged((error, data) => {
-      !error && this._onTimelinePropertiesChanged(data)
+    this._subscribe("onPlaybackInfoChanged", (data) => {
+      this._onPlaybackInfoChanged(data)
     })
 
-    this.smtc.onPlaybackInfoChanged((error, data) => {
-      !error && this._onPlaybackInfoChanged(data)
+    this._subscribe("onSessionAdded", (data) => {
+      this._onSessionAdded(data)
     })
 
-    this.smtc.onSessionAdded((error, data) => {
-      !error && this._onSessionAdded(data)
+    this._subscribe("onSessionRemoved", (data) => {
+      this._onSessionRemoved(data)
     })
 
-    this.smtc.onSessionRemoved((error, sourceAppId) => {
-      !error && this._onSessionRemoved(sourceAppId)
+    this._subscribe("onCurrentSessionChanged", (data) => {
+      this._onCurrentSessionChanged(data)
     })
 
-    this.smtc.onCurrentSessionChanged((error, sourceAppId) => {
-      !error && this._onCurrentSessionChanged(sourceAppId)
+    this._subscribe("onSessionUpdated", (data) => {
+      this._onSessionUpdated(data)
     })
   }
 
+  _subscribe(method, handler) {
+    // destroy() 之后仍可能收到已排队的事件，此时直接忽略
+    const id = this.smtc[method]((error, data) => {
+      !error && this._sessionKeys && handler(data)
+    })
+    this._subscriptions.push(id)
+  }
+
   _onMediaPropertiesChanged(data) {
-    const { sourceAppId, mediaProps } = data
-    const session = this._mediaSessions.get(sourceAppId)
-    if (!session) {
+    const { sourceAppId, sessionKey, mediaProps } = data
+    if (!this._sessionKeys.has(sessionKey)) {
       return
     }
 
-    session.media = mediaProps
-    this.emit("session-media-changed", sourceAppId, mediaProps)
+    this.emit("session-media-changed", sourceAppId, mediaProps, sessionKey)
   }
 
   _onTimelinePropertiesChanged(data) {
-    const { sourceAppId, timelineProps } = data
-    const session = this._mediaSessions.get(sourceAppId)
-    if (!session) {
+    const { sourceAppId, sessionKey, timelineProps } = data
+    if (!this._sessionKeys.has(sessionKey)) {
       return
     }
 
-    session.timeline = timelineProps
-    this.emit("session-timeline-changed", sourceAppId, timelineProps)
+    this.emit("session-timeline-changed", sourceAppId, timelineProps, sessionKey)
   }
 
   _onPlaybackInfoChanged(data) {
-    const { sourceAppId, playbackInfo } = data
-    const session = this._mediaSessions.get(sourceAppId)
-    if (!session) {
+    const { sourceAppId, sessionKey, playbackInfo } = data
+    if (!this._sessionKeys.has(sessionKey)) {
       return
     }
 
-    session.playback = playbackInfo
-    this.emit("session-playback-changed", sourceAppId, playbackInfo)
+    this.emit("session-playback-changed", sourceAppId, playbackInfo, sessionKey)
   }
 
   _onSessionAdded(data) {
-    const { sourceAppId } = data
-    this._mediaSessions.set(sourceAppId, data)
-    this.emit("session-added", sourceAppId, data)
+    const { sourceAppId, sessionKey } = data
+    this._sessionKeys.add(sessionKey)
+    this.emit("session-added", sourceAppId, data, sessionKey)
+  }
+
+  _onSessionRemoved(data) {
+    const { sourceAppId, sessionKey } = data
+    if (this._sessionKeys.delete(sessionKey)) {
+      this.emit("session-removed", sourceAppId, sessionKey)
+    }
   }
 
-  _onSessionRemoved(sourceAppId) {
-    if (this._mediaSessions.has(sourceAppId)) {
-      this._mediaSessions.delete(sourceAppId)
-      this.emit("session-removed", sourceAppId)
+  _onCurrentSessionChanged(data) {
+    const { sourceAppId, sessionKey } = data
+    if (this._sessionKeys.has(sessionKey)) {
+      this.emit("current-session-changed", sourceAppId, sessionKey)
     }
   }
 
-  _onCurrentSessionChanged(sourceAppId) {
-    if (this._mediaSessions.has(sourceAppId)) {
-      this.emit("current-session-changed", sourceAppId)
+  _onSessionUpdated(data) {
+    const { sourceAppId, sessionKey, mediaInfo, changedFields } = data
+    if (this._sessionKeys.has(sessionKey)) {
+      this.emit("session-updated", sourceAppId, mediaInfo, changedFields, sessionKey)
     }
   }
 
+  getState() {
+    return this.smtc.getState()
+  }
+
   get sessions() {
-    return Array.from(this._mediaSessions.values())
+    return this.smtc.getState().sessions
+  }
+
+  get droppedEventCount() {
+    return this.smtc.getDroppedEventCount()
+  }
+
+  play(sourceAppId) {
+    return this.smtc.play(sourceAppId)
+  }
+
+  pause(sourceAppId) {
+    return this.smtc.pause(sourceAppId)
+  }
+
+  togglePlayPause(sourceAppId) {
+    return this.smtc.togglePlayPause(sourceAppId)
+  }
+
+  stop(sourceAppId) {
+    return this.smtc.stop(sourceAppId)
+  }
+
+  skipNext(sourceAppId) {
+    return this.smtc.skipNext(sourceAppId)
+  }
+
+  skipPrevious(sourceAppId) {
+    return this.smtc.skipPrevious(sourceAppId)
+  }
+
+  setShuffle(sourceAppId, active) {
+    return this.smtc.setShuffle(sourceAppId, active)
   }
 
-  static getMediaSessions() {
-    return getSessions()
+  setRepeatMode(sourceAppId, mode) {
+    return this.smtc.setRepeatMode(sourceAppId, mode)
   }
 
-  static getCurrentMediaSession() {
-    return getCurrentSession()
+  setPlaybackRate(sourceAppId, rate) {
+    return this.smtc.setPlaybackRate(sourceAppId, rate)
   }
 
-  static getMediaSessionByAppId(sourceAppId) {
-    return getSessionById(sourceAppId)
+  seekTo(sourceAppId, seconds) {
+    return this.smtc.seekTo(sourceAppId, seconds)
+  }
+
+  seekBy(sourceAppId, offsetSeconds) {
+    return this.smtc.seekBy(sourceAppId, offsetSeconds)
+  }
+
+  getEstimatedPosition(sourceAppId) {
+    return this.smtc.getEstimatedPosition(sourceAppId)
+  }
+
+  startRecording(path) {
+    return this.smtc.startRecording(path)
+  }
+
+  stopRecording() {
+    return this.smtc.stopRecording()
+  }
+
+  static getMediaSessions(options, timeoutMs) {
+    return getSessions(options, timeoutMs)
+  }
+
+  static getCurrentMediaSession(options, timeoutMs) {
+    return getCurrentSession(options, timeoutMs)
+  }
+
+  static getMediaSessionByAppId(sourceAppId, options, timeoutMs) {
+    return getSessionById(sourceAppId, options, timeoutMs)
+  }
+
+  static getMediaSessionByKey(sessionKey, options, timeoutMs) {
+    return getSessionByKey(sessionKey, options, timeoutMs)
+  }
+
+  static getMediaSessionsByAppId(sourceAppId, options, timeoutMs) {
+    return getSessionsByAppId(sourceAppId, options, timeoutMs)
+  }
+
+  static getMediaSessionsAsync(options, timeoutMs) {
+    return getSessionsAsync(options, timeoutMs)
+  }
+
+  static getCurrentMediaSessionAsync(options, timeoutMs) {
+    return getCurrentSessionAsync(options, timeoutMs)
+  }
+
+  static getMediaSessionByAppIdAsync(sourceAppId, options, timeoutMs) {
+    return getSessionByIdAsync(sourceAppId, options, timeoutMs)
+  }
+
+  static getMediaSessionByKeyAsync(sessionKey, options, timeoutMs) {
+    return getSessionByKeyAsync(sessionKey, options, timeoutMs)
+  }
+
+  static getMediaSessionsByAppIdAsync(sourceAppId, options, timeoutMs) {
+    return getSessionsByAppIdAsync(sourceAppId, options, timeoutMs)
+  }
+
+  static getThumbnail(sessionKey, options, timeoutMs) {
+    return getThumbnail(sessionKey, options, timeoutMs)
+  }
+
+  static getThumbnailByHash(hash) {
+    return getThumbnailByHash(hash)
+  }
+
+  static resetSessionManager() {
+    return resetSessionManager()
+  }
+
+  static getSessionManagerMetrics() {
+    return getSessionManagerMetrics()
   }
 
   destroy() {
@@ -126,13 +268,21 @@ class SMTCMonitor extends EventEmitter {
       this.removeAllListeners()
 
       if (this.smtc) {
+        this._subscriptions.forEach((id) => this.smtc.off(id))
+        this._subscriptions = []
         this.smtc.destroy()
         this.smtc = null
+        this.simulator = null
       }
 
-      if (this._mediaSessions) {
-        this._mediaSessions.clear()
-        this._mediaSessions = null
+      if (this.replay) {
+        this.replay.stop()
+        this.replay = null
+      }
+
+      if (this._sessionKeys) {
+        this._sessionKeys.clear()
+        this._sessionKeys = null
       }
     } catch (e) {
       console.error("Error during SMTCMonitor destroy:", e)
@@ -140,17 +290,35 @@ class SMTCMonitor extends EventEmitter {
   }
 }
 
+// 连接到模拟器的监听器，通过 monitor.simulator 增删会话、修改状态
+function createSimulatedMonitor(options) {
+  return new SMTCMonitor(options, new MediaSimulator())
+}
+
+// 回放录制文件的监听器，调用 monitor.replay.start() 开始投递录制的事件
+function createReplayMonitor(path, replayOptions, options) {
+  const replay = new EventReplay(path, replayOptions)
+  const monitor = new SMTCMonitor(options, replay.simulator())
+  monitor.replay = replay
+  return monitor
+}
+
 function _checkCompatibility() {
   const version = os.release()
   const globalWarning = `SMTCMonitor is designed to work with Windows.Media.Control namespace, which requires GlobalSystemMediaTransportControlsSessionManager feature.`
   let warnings = []
 
+  // Linux 上通过 MPRIS 读取媒体会话
+  if (process.platform === "linux" && process.arch === "x64") {
+    return
+  }
+
   if (
     process.platform !== "win32" ||
     !["ia32", "x64", "arm64"].includes(process.arch)
   ) {
     warnings.push(
-      `SMTC Feature is not supported on this platform. Please use Windows 10 or later with x64 / ia32 / arm64 architecture.`
+      `SMTC Feature is not supported on this platform. Please use Windows 10 or later with x64 / ia32 / arm64 architecture, or Linux x64 with an MPRIS-compatible player.`
     )
   } else if (!version || _compareVersions(version, "10.0.17763") < 0) {
     warnings.push(`Please use Windows 10 version 1809 (10.0.17763) or later.`)
@@ -184,5 +352,11 @@ _checkCompatibility()
 
 module.exports = {
   SMTCMonitor,
+  MediaSimulator,
+  EventReplay,
+  createSimulatedMonitor,
+  createReplayMonitor,
   PlaybackStatus,
+  PlaybackType,
+  RepeatMode,
 }
@@ -1,3 +1,3 @@
 import { createRequire } from "module"
 const require = createRequire(import.meta.url)
-export const { SMTCMonitor, PlaybackStatus } = require("./index.js")
+export const { SMTCMonitor, MediaSimulator, EventReplay, createSimulatedMonitor, createReplayMonitor, PlaybackStatus, PlaybackType, RepeatMode } = require("./index.js")
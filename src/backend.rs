@@ -0,0 +1,305 @@
+use napi::{Error, Result, Status};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+#[cfg(windows)]
+use windows::Foundation::{EventRegistrationToken, TypedEventHandler};
+#[cfg(windows)]
+use windows::Media::Control::GlobalSystemMediaTransportControlsSessionManager;
+
+#[cfg(windows)]
+use crate::media_control;
+use crate::session::MediaSession;
+#[cfg(any(windows, target_os = "linux"))]
+use crate::shared_manager;
+use crate::transport;
+#[cfg(windows)]
+use crate::utils::win_to_napi_err;
+
+pub type SharedSession = Arc<dyn MediaSession + Send + Sync>;
+pub type SharedBackend = Arc<dyn MediaBackend>;
+
+// 事件本身不携带数据，处理器需要时自行从会话读取最新状态
+pub type EventHandler = Arc<dyn Fn() + Send + Sync>;
+
+#[allow(clippy::enum_variant_names)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BackendEvent {
+  SessionsChanged,
+  CurrentSessionChanged,
+  MediaPropsChanged,
+  PlaybackInfoChanged,
+  TimelinePropsChanged,
+}
+
+// 注册事件后返回的令牌，记录事件类型以便移除时找到对应的注销方法
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct EventToken {
+  pub event: BackendEvent,
+  pub value: i64,
+}
+
+// 媒体会话的来源，SessionManager 与 SMTCMonitor 只依赖此 trait
+pub trait MediaBackend: Send + Sync {
+  fn sessions(&self) -> Vec<SharedSession>;
+  fn current_session(&self) -> Option<SharedSession>;
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken>;
+  fn unsubscribe(&self, token: EventToken);
+}
+
+// 事件处理器列表，触发时先复制再释放锁，处理器中可以再次修改状态
+#[derive(Default)]
+pub struct Handlers {
+  entries: Mutex<Vec<(EventToken, EventHandler)>>,
+  next_token: AtomicI64,
+}
+
+impl Handlers {
+  pub fn add(&self, event: BackendEvent, handler: EventHandler) -> EventToken {
+    let token = EventToken {
+      event,
+      value: self.next_token.fetch_add(1, Ordering::Relaxed) + 1,
+    };
+    self.lock().push((token, handler));
+    token
+  }
+
+  pub fn remove(&self, token: EventToken) {
+    self.lock().retain(|(entry, _)| *entry != token);
+  }
+
+  pub fn emit(&self, event: BackendEvent) {
+    let handlers: Vec<_> = self
+      .lock()
+      .iter()
+      .filter(|(token, _)| token.event == event)
+      .map(|(_, handler)| handler.clone())
+      .collect();
+
+    for handler in handlers {
+      handler();
+    }
+  }
+
+  #[cfg(test)]
+  pub fn count(&self) -> usize {
+    self.lock().len()
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, Vec<(EventToken, EventHandler)>> {
+    self.entries.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+pub fn unsupported_event(event: BackendEvent) -> Error {
+  Error::new(
+    Status::InvalidArg,
+    format!("Event {:?} is not supported here", event),
+  )
+}
+
+// 未指定后端时使用进程内共享的 SMTC 会话管理器
+pub fn resolve(backend: Option<&SharedBackend>, timeout: Duration) -> Result<SharedBackend> {
+  match backend {
+    Some(backend) => Ok(backend.clone()),
+    None => system_backend(timeout),
+  }
+}
+
+#[cfg(windows)]
+fn system_backend(timeout: Duration) -> Result<SharedBackend> {
+  Ok(Arc::new(shared_manager::get(timeout)?))
+}
+
+#[cfg(target_os = "linux")]
+fn system_backend(timeout: Duration) -> Result<SharedBackend> {
+  Ok(shared_manager::get(timeout)?)
+}
+
+// 其他平台没有系统级的媒体会话来源，只能显式传入后端
+#[cfg(not(any(windows, target_os = "linux")))]
+fn system_backend(_timeout: Duration) -> Result<SharedBackend> {
+  Err(Error::new(
+    Status::GenericFailure,
+    "No system media sessions are available on this platform",
+  ))
+}
+
+// 按会话键或 sourceAppId 查找会话，未指定时返回当前会话
+pub fn find_session(
+  backend: &dyn MediaBackend,
+  source_app_id: Option<&str>,
+) -> Option<SharedSession> {
+  match source_app_id {
+    Some(_) => transport::select_session(backend.sessions(), None, source_app_id),
+    None => backend.current_session(),
+  }
+}
+
+#[cfg(windows)]
+impl MediaBackend for GlobalSystemMediaTransportControlsSessionManager {
+  // 监听器每次同步会话都经过这里，collect_sessions 会同时清理已消失会话的会话键
+  fn sessions(&self) -> Vec<SharedSession> {
+    media_control::collect_sessions(self)
+      .into_iter()
+      .map(|session| Arc::new(session) as SharedSession)
+      .collect()
+  }
+
+  fn current_session(&self) -> Option<SharedSession> {
+    self
+      .GetCurrentSession()
+      .ok()
+      .map(|session| Arc::new(session) as SharedSession)
+  }
+
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+    let token = match event {
+      BackendEvent::SessionsChanged => {
+        self.SessionsChanged(&TypedEventHandler::new(move |_, _| {
+          handler();
+          Ok(())
+        }))
+      }
+      BackendEvent::CurrentSessionChanged => {
+        self.CurrentSessionChanged(&TypedEventHandler::new(move |_, _| {
+          handler();
+          Ok(())
+        }))
+      }
+      _ => return Err(unsupported_event(event)),
+    };
+
+    win_to_napi_err(token).map(|token| EventToken {
+      event,
+      value: token.Value,
+    })
+  }
+
+  fn unsubscribe(&self, token: EventToken) {
+    let value = EventRegistrationToken { Value: token.value };
+    let _ = match token.event {
+      BackendEvent::SessionsChanged => self.RemoveSessionsChanged(value),
+      BackendEvent::CurrentSessionChanged => self.RemoveCurrentSessionChanged(value),
+      _ => Ok(()),
+    };
+  }
+}
+
+#[cfg(test)]
+pub mod fake {
+  use std::sync::Mutex;
+
+  use super::*;
+  use crate::session::fake::FakeSession;
+
+  // 内存中的会话列表，测试通过 add/remove 模拟播放器启动与退出
+  #[derive(Default)]
+  pub struct FakeBackend {
+    pub sessions: Mutex<Vec<FakeSession>>,
+    pub current: Mutex<Option<String>>,
+    handlers: Handlers,
+  }
+
+  impl FakeBackend {
+    pub fn add(&self, session: FakeSession) {
+      self.sessions.lock().unwrap().push(session);
+      self.emit(BackendEvent::SessionsChanged);
+    }
+
+    pub fn remove(&self, key: &str) {
+      self.sessions.lock().unwrap().retain(|s| s.key != key);
+      self.emit(BackendEvent::SessionsChanged);
+    }
+
+    pub fn set_current(&self, key: &str) {
+      *self.current.lock().unwrap() = Some(key.to_string());
+      self.emit(BackendEvent::CurrentSessionChanged);
+    }
+
+    pub fn handler_count(&self) -> usize {
+      self.handlers.count()
+    }
+
+    pub fn emit(&self, event: BackendEvent) {
+      self.handlers.emit(event);
+    }
+  }
+
+  impl MediaBackend for FakeBackend {
+    fn sessions(&self) -> Vec<SharedSession> {
+      self
+        .sessions
+        .lock()
+        .unwrap()
+        .iter()
+        .map(|session| Arc::new(session.clone()) as SharedSession)
+        .collect()
+    }
+
+    fn current_session(&self) -> Option<SharedSession> {
+      let current = self.current.lock().unwrap().clone()?;
+      self
+        .sessions()
+        .into_iter()
+        .find(|session| matches!(session.session_key(), Ok(key) if key == current))
+    }
+
+    fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+      if !matches!(
+        event,
+        BackendEvent::SessionsChanged | BackendEvent::CurrentSessionChanged
+      ) {
+        return Err(unsupported_event(event));
+      }
+
+      Ok(self.handlers.add(event, handler))
+    }
+
+    fn unsubscribe(&self, token: EventToken) {
+      self.handlers.remove(token);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::fake::FakeBackend;
+  use super::*;
+  use crate::session::fake::FakeSession;
+
+  #[test]
+  fn finds_sessions_by_key_app_id_or_current() {
+    let backend = FakeBackend::default();
+    backend.add(FakeSession::new("a.exe"));
+    backend.add(FakeSession::new("b.exe"));
+
+    let key = |session: Option<SharedSession>| session.map(|s| s.session_key().unwrap());
+    assert_eq!(
+      key(find_session(&backend, Some("b.exe#1"))).as_deref(),
+      Some("b.exe#1")
+    );
+    assert_eq!(
+      key(find_session(&backend, Some("a.exe"))).as_deref(),
+      Some("a.exe#1")
+    );
+    assert_eq!(key(find_session(&backend, Some("c.exe"))), None);
+    assert_eq!(key(find_session(&backend, None)), None);
+
+    backend.set_current("b.exe#1");
+    assert_eq!(
+      key(find_session(&backend, None)).as_deref(),
+      Some("b.exe#1")
+    );
+  }
+
+  #[test]
+  fn rejects_session_events_on_the_backend() {
+    let backend = FakeBackend::default();
+    let err = backend
+      .subscribe(BackendEvent::PlaybackInfoChanged, Arc::new(|| {}))
+      .unwrap_err();
+
+    assert_eq!(err.status, Status::InvalidArg);
+  }
+}
@@ -0,0 +1,154 @@
+#[cfg(windows)]
+use windows::Media::Control::GlobalSystemMediaTransportControlsSessionPlaybackControls;
+
+use crate::types::PlaybackControls;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Capability {
+  Play,
+  Pause,
+  Stop,
+  Record,
+  FastForward,
+  Rewind,
+  Next,
+  Previous,
+  ChannelUp,
+  ChannelDown,
+  PlayPauseToggle,
+  Shuffle,
+  Repeat,
+  PlaybackRate,
+  Seek,
+}
+
+impl Capability {
+  pub const ALL: [Capability; 15] = [
+    Capability::Play,
+    Capability::Pause,
+    Capability::Stop,
+    Capability::Record,
+    Capability::FastForward,
+    Capability::Rewind,
+    Capability::Next,
+    Capability::Previous,
+    Capability::ChannelUp,
+    Capability::ChannelDown,
+    Capability::PlayPauseToggle,
+    Capability::Shuffle,
+    Capability::Repeat,
+    Capability::PlaybackRate,
+    Capability::Seek,
+  ];
+}
+
+impl PlaybackControls {
+  // 按能力逐项查询，保证每个字段都对应到正确的能力
+  pub fn from_fn<F>(is_enabled: F) -> Self
+  where
+    F: Fn(Capability) -> bool,
+  {
+    Self {
+      play: is_enabled(Capability::Play),
+      pause: is_enabled(Capability::Pause),
+      stop: is_enabled(Capability::Stop),
+      record: is_enabled(Capability::Record),
+      fast_forward: is_enabled(Capability::FastForward),
+      rewind: is_enabled(Capability::Rewind),
+      next: is_enabled(Capability::Next),
+      previous: is_enabled(Capability::Previous),
+      channel_up: is_enabled(Capability::ChannelUp),
+      channel_down: is_enabled(Capability::ChannelDown),
+      play_pause_toggle: is_enabled(Capability::PlayPauseToggle),
+      shuffle: is_enabled(Capability::Shuffle),
+      repeat: is_enabled(Capability::Repeat),
+      playback_rate: is_enabled(Capability::PlaybackRate),
+      seek: is_enabled(Capability::Seek),
+    }
+  }
+}
+
+#[cfg(windows)]
+pub fn read_playback_controls(
+  controls: &GlobalSystemMediaTransportControlsSessionPlaybackControls,
+) -> PlaybackControls {
+  PlaybackControls::from_fn(|capability| {
+    let enabled = match capability {
+      Capability::Play => controls.IsPlayEnabled(),
+      Capability::Pause => controls.IsPauseEnabled(),
+      Capability::Stop => controls.IsStopEnabled(),
+      Capability::Record => controls.IsRecordEnabled(),
+      Capability::FastForward => controls.IsFastForwardEnabled(),
+      Capability::Rewind => controls.IsRewindEnabled(),
+      Capability::Next => controls.IsNextEnabled(),
+      Capability::Previous => controls.IsPreviousEnabled(),
+      Capability::ChannelUp => controls.IsChannelUpEnabled(),
+      Capability::ChannelDown => controls.IsChannelDownEnabled(),
+      Capability::PlayPauseToggle => controls.IsPlayPauseToggleEnabled(),
+      Capability::Shuffle => controls.IsShuffleEnabled(),
+      Capability::Repeat => controls.IsRepeatEnabled(),
+      Capability::PlaybackRate => controls.IsPlaybackRateEnabled(),
+      Capability::Seek => controls.IsPlaybackPositionEnabled(),
+    };
+
+    // 查询失败时视为不可用
+    enabled.unwrap_or(false)
+  })
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn maps_each_capability_to_its_own_field() {
+    let controls = PlaybackControls::from_fn(|capability| {
+      matches!(
+        capability,
+        Capability::Play | Capability::Next | Capability::Seek | Capability::Shuffle
+      )
+    });
+
+    assert_eq!(
+      controls,
+      PlaybackControls {
+        play: true,
+        next: true,
+        seek: true,
+        shuffle: true,
+        ..Default::default()
+      }
+    );
+  }
+
+  #[test]
+  fn every_capability_sets_exactly_one_field() {
+    for capability in Capability::ALL {
+      let controls = PlaybackControls::from_fn(|c| c == capability);
+      let enabled = [
+        controls.play,
+        controls.pause,
+        controls.stop,
+        controls.record,
+        controls.fast_forward,
+        controls.rewind,
+        controls.next,
+        controls.previous,
+        controls.channel_up,
+        controls.channel_down,
+        controls.play_pause_toggle,
+        controls.shuffle,
+        controls.repeat,
+        controls.playback_rate,
+        controls.seek,
+      ];
+
+      assert_eq!(
+        enabled.iter().filter(|&&on| on).count(),
+        1,
+        "{:?}",
+        capability
+      );
+    }
+  }
+}
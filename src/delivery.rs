@@ -0,0 +1,454 @@
+use napi::bindgen_prelude::ToNapiValue;
+use napi::threadsafe_function::{
+  ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode,
+};
+use napi::{Error, JsFunction, Result, Status};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::monitor::{
+  MediaPropsCallbackData, MonitorOptions, PlaybackInfoCallbackData, SessionCallbackData,
+  SessionUpdatedCallbackData, TimelinePropsCallbackData,
+};
+use crate::session_manager::EventCallback;
+use crate::types::MediaInfo;
+
+// 丢弃或合并事件的策略未指定队列大小时使用
+const DEFAULT_QUEUE_SIZE: usize = 64;
+// 线程安全函数的队列大小为 0 时不设上限
+const UNBOUNDED: usize = 0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DeliveryPolicy {
+  // 队列满时阻塞 WinRT 事件线程，直到 JS 线程取走事件；未指定队列大小时不设上限
+  Blocking,
+  // 队列满时丢弃最早的事件
+  DropOldest,
+  // 同一会话尚未投递的事件只保留最新的一个
+  CoalesceLatest,
+}
+
+impl DeliveryPolicy {
+  pub fn parse(policy: &str) -> Result<Self> {
+    match policy {
+      "blocking" => Ok(Self::Blocking),
+      "drop-oldest" => Ok(Self::DropOldest),
+      "coalesce-latest" => Ok(Self::CoalesceLatest),
+      _ => Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "Invalid delivery policy: {}. Expected 'blocking', 'drop-oldest' or 'coalesce-latest'",
+          policy
+        ),
+      )),
+    }
+  }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Delivery {
+  pub queue_size: usize,
+  pub policy: DeliveryPolicy,
+}
+
+// 默认与旧版本一致，不丢弃任何事件
+impl Default for Delivery {
+  fn default() -> Self {
+    Self {
+      queue_size: UNBOUNDED,
+      policy: DeliveryPolicy::Blocking,
+    }
+  }
+}
+
+impl Delivery {
+  pub fn from_options(options: Option<&MonitorOptions>) -> Result<Self> {
+    let mut delivery = Self::default();
+    let options = match options {
+      Some(options) => options,
+      None => return Ok(delivery),
+    };
+
+    if let Some(policy) = &options.policy {
+      delivery.policy = DeliveryPolicy::parse(policy)?;
+    }
+
+    delivery.queue_size = match options.queue_size {
+      Some(0) => {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "Invalid queue size: 0. Expected a positive integer".to_string(),
+        ))
+      }
+      Some(queue_size) => queue_size as usize,
+      None if delivery.policy == DeliveryPolicy::Blocking => UNBOUNDED,
+      None => DEFAULT_QUEUE_SIZE,
+    };
+
+    Ok(delivery)
+  }
+}
+
+// 合并事件时用于判断两个事件是否属于同一会话
+pub trait SessionEvent {
+  fn session_key(&self) -> &str;
+
+  // 合并同一会话尚未投递的事件时调用，previous 为被替换的旧事件；默认直接丢弃旧事件
+  fn coalesce(&mut self, _previous: Self)
+  where
+    Self: Sized,
+  {
+  }
+}
+
+impl SessionEvent for MediaInfo {
+  fn session_key(&self) -> &str {
+    &self.session_key
+  }
+}
+
+impl SessionEvent for SessionCallbackData {
+  fn session_key(&self) -> &str {
+    &self.session_key
+  }
+}
+
+impl SessionEvent for MediaPropsCallbackData {
+  fn session_key(&self) -> &str {
+    &self.session_key
+  }
+
+  // 被替换的事件可能是唯一携带新缩略图的事件，哈希相同时沿用其中的图片
+  fn coalesce(&mut self, previous: Self) {
+    let media = &mut self.media_props;
+    if media.thumbnail.is_none() && media.thumbnail_hash == previous.media_props.thumbnail_hash {
+      media.thumbnail = previous.media_props.thumbnail;
+    }
+  }
+}
+
+impl SessionEvent for PlaybackInfoCallbackData {
+  fn session_key(&self) -> &str {
+    &self.session_key
+  }
+}
+
+impl SessionEvent for TimelinePropsCallbackData {
+  fn session_key(&self) -> &str {
+    &self.session_key
+  }
+}
+
+impl SessionEvent for SessionUpdatedCallbackData {
+  fn session_key(&self) -> &str {
+    &self.session_key
+  }
+
+  // 合并后的事件携带两次更新中所有变化的字段，旧事件中的缩略图在未变化时沿用
+  fn coalesce(&mut self, previous: Self) {
+    let mut changed_fields = previous.changed_fields;
+    for field in std::mem::take(&mut self.changed_fields) {
+      if !changed_fields.contains(&field) {
+        changed_fields.push(field);
+      }
+    }
+    self.changed_fields = changed_fields;
+
+    let media = &mut self.media_info.media;
+    if media.thumbnail.is_none() && media.thumbnail_hash == previous.media_info.media.thumbnail_hash
+    {
+      media.thumbnail = previous.media_info.media.thumbnail;
+    }
+  }
+}
+
+// 单个监听器的有界事件队列，队列中的每个事件都对应一次已发出的 JS 线程通知
+pub struct EventQueue<T> {
+  items: Mutex<VecDeque<T>>,
+  delivery: Delivery,
+  dropped: Arc<AtomicU64>,
+}
+
+impl<T: SessionEvent> EventQueue<T> {
+  pub fn new(delivery: Delivery, dropped: Arc<AtomicU64>) -> Self {
+    Self {
+      items: Mutex::new(VecDeque::new()),
+      delivery,
+      dropped,
+    }
+  }
+
+  // 返回 true 表示队列新增了一个事件，需要再通知一次 JS 线程
+  pub fn push(&self, event: T) -> bool {
+    let mut items = self.items.lock().unwrap_or_else(|e| e.into_inner());
+
+    if self.delivery.policy == DeliveryPolicy::CoalesceLatest {
+      if let Some(pending) = items
+        .iter_mut()
+        .find(|pending| pending.session_key() == event.session_key())
+      {
+        let previous = std::mem::replace(pending, event);
+        pending.coalesce(previous);
+        self.dropped.fetch_add(1, Ordering::Relaxed);
+        return false;
+      }
+    }
+
+    if items.len() >= self.delivery.queue_size {
+      // 被丢弃事件的通知由新事件接管
+      items.pop_front();
+      items.push_back(event);
+      self.dropped.fetch_add(1, Ordering::Relaxed);
+      return false;
+    }
+
+    items.push_back(event);
+    true
+  }
+
+  pub fn pop(&self) -> Option<T> {
+    self
+      .items
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .pop_front()
+  }
+}
+
+// 按投递策略把 JS 回调包装成事件回调，非阻塞策略下 WinRT 线程只负责入队
+pub fn js_callback<T>(
+  callback: &JsFunction,
+  delivery: Delivery,
+  dropped: &Arc<AtomicU64>,
+) -> Result<EventCallback<T>>
+where
+  T: SessionEvent + ToNapiValue + Send + 'static,
+{
+  if delivery.policy == DeliveryPolicy::Blocking {
+    let tsfn: ThreadsafeFunction<T> = callback
+      .create_threadsafe_function(delivery.queue_size, |ctx: ThreadSafeCallContext<T>| {
+        Ok(vec![ctx.value])
+      })?;
+
+    return Ok(Arc::new(move |data| {
+      tsfn.call(Ok(data), ThreadsafeFunctionCallMode::Blocking);
+    }));
+  }
+
+  let queue = Arc::new(EventQueue::new(delivery, dropped.clone()));
+  let pending = queue.clone();
+  let tsfn: ThreadsafeFunction<()> =
+    callback.create_threadsafe_function(0, move |_: ThreadSafeCallContext<()>| {
+      pending
+        .pop()
+        .map(|event| vec![event])
+        .ok_or_else(|| Error::new(Status::GenericFailure, "No pending event".to_string()))
+    })?;
+
+  Ok(Arc::new(move |data| {
+    if queue.push(data) {
+      tsfn.call(Ok(()), ThreadsafeFunctionCallMode::NonBlocking);
+    }
+  }))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::session::fake::FakeSession;
+  use crate::session::MediaSession;
+  use crate::thumbnail::ThumbnailSettings;
+  use crate::timeout::DEFAULT_TIMEOUT;
+  use crate::types::MediaProps;
+
+  #[derive(Debug, PartialEq)]
+  struct Event(&'static str, u32);
+
+  impl SessionEvent for Event {
+    fn session_key(&self) -> &str {
+      self.0
+    }
+  }
+
+  fn queue(queue_size: usize, policy: DeliveryPolicy) -> (EventQueue<Event>, Arc<AtomicU64>) {
+    let dropped = Arc::new(AtomicU64::new(0));
+    let delivery = Delivery { queue_size, policy };
+    (EventQueue::new(delivery, dropped.clone()), dropped)
+  }
+
+  fn drain(queue: &EventQueue<Event>) -> Vec<Event> {
+    std::iter::from_fn(|| queue.pop()).collect()
+  }
+
+  #[test]
+  fn drop_oldest_keeps_the_newest_events() {
+    let (queue, dropped) = queue(2, DeliveryPolicy::DropOldest);
+
+    assert!(queue.push(Event("a#1", 1)));
+    assert!(queue.push(Event("a#1", 2)));
+    assert!(!queue.push(Event("a#1", 3)));
+
+    assert_eq!(drain(&queue), [Event("a#1", 2), Event("a#1", 3)]);
+    assert_eq!(dropped.load(Ordering::Relaxed), 1);
+  }
+
+  #[test]
+  fn coalesce_latest_replaces_pending_event_of_the_same_session() {
+    let (queue, dropped) = queue(8, DeliveryPolicy::CoalesceLatest);
+
+    assert!(queue.push(Event("a#1", 1)));
+    assert!(queue.push(Event("b#1", 1)));
+    assert!(!queue.push(Event("a#1", 2)));
+
+    assert_eq!(drain(&queue), [Event("a#1", 2), Event("b#1", 1)]);
+    assert_eq!(dropped.load(Ordering::Relaxed), 1);
+  }
+
+  #[test]
+  fn coalesced_media_events_keep_the_omitted_thumbnail() {
+    let queue = EventQueue::new(
+      Delivery {
+        queue_size: 8,
+        policy: DeliveryPolicy::CoalesceLatest,
+      },
+      Arc::new(AtomicU64::new(0)),
+    );
+    let event = |title: &str, thumbnail: Option<Vec<u8>>| MediaPropsCallbackData {
+      source_app_id: "a".to_string(),
+      session_key: "a#1".to_string(),
+      media_props: MediaProps {
+        title: title.to_string(),
+        thumbnail: thumbnail.map(Into::into),
+        thumbnail_hash: Some("new".to_string()),
+        ..Default::default()
+      },
+    };
+
+    queue.push(event("A", Some(vec![1, 2])));
+    queue.push(event("B", None));
+
+    let merged = queue.pop().unwrap();
+    assert_eq!(merged.media_props.title, "B");
+    assert_eq!(
+      merged.media_props.thumbnail.unwrap().as_bytes(),
+      &[1u8, 2][..]
+    );
+  }
+
+  #[test]
+  fn coalesced_session_updates_keep_every_changed_field() {
+    let queue = EventQueue::new(
+      Delivery {
+        queue_size: 8,
+        policy: DeliveryPolicy::CoalesceLatest,
+      },
+      Arc::new(AtomicU64::new(0)),
+    );
+    let update = |title: &str, thumbnail: Option<Vec<u8>>, changed_fields: &[&str]| {
+      let mut media_info = FakeSession::new("a")
+        .media_info(&ThumbnailSettings::default(), DEFAULT_TIMEOUT)
+        .unwrap()
+        .unwrap();
+      media_info.media.title = title.to_string();
+      media_info.media.thumbnail = thumbnail.map(Into::into);
+      SessionUpdatedCallbackData {
+        source_app_id: "a".to_string(),
+        session_key: "a#1".to_string(),
+        media_info,
+        changed_fields: changed_fields.iter().map(|f| f.to_string()).collect(),
+      }
+    };
+
+    queue.push(update(
+      "A",
+      Some(vec![1]),
+      &["media.title", "media.thumbnailHash"],
+    ));
+    queue.push(update(
+      "B",
+      None,
+      &["media.title", "playback.playbackStatus"],
+    ));
+
+    let merged = queue.pop().unwrap();
+    assert!(queue.pop().is_none());
+    assert_eq!(merged.media_info.media.title, "B");
+    assert_eq!(
+      merged.changed_fields,
+      [
+        "media.title",
+        "media.thumbnailHash",
+        "playback.playbackStatus"
+      ]
+    );
+    assert!(merged.media_info.media.thumbnail.is_some());
+  }
+
+  #[test]
+  fn coalesce_latest_is_still_bounded() {
+    let (queue, dropped) = queue(1, DeliveryPolicy::CoalesceLatest);
+
+    assert!(queue.push(Event("a#1", 1)));
+    assert!(!queue.push(Event("b#1", 1)));
+
+    assert_eq!(drain(&queue), [Event("b#1", 1)]);
+    assert_eq!(dropped.load(Ordering::Relaxed), 1);
+  }
+
+  #[test]
+  fn one_notification_per_queued_event() {
+    let (queue, _) = queue(2, DeliveryPolicy::DropOldest);
+    let notifications = (1..=5).filter(|&n| queue.push(Event("a#1", n))).count();
+
+    assert_eq!(notifications, drain(&queue).len());
+  }
+
+  #[test]
+  fn parses_delivery_options() {
+    let delivery = Delivery::from_options(Some(&MonitorOptions {
+      queue_size: Some(8),
+      policy: Some("coalesce-latest".to_string()),
+      ..Default::default()
+    }))
+    .unwrap();
+
+    assert_eq!(delivery.queue_size, 8);
+    assert_eq!(delivery.policy, DeliveryPolicy::CoalesceLatest);
+
+    let drop_oldest = Delivery::from_options(Some(&MonitorOptions {
+      policy: Some("drop-oldest".to_string()),
+      ..Default::default()
+    }))
+    .unwrap();
+    assert_eq!(drop_oldest.queue_size, DEFAULT_QUEUE_SIZE);
+  }
+
+  #[test]
+  fn delivers_every_event_by_default() {
+    let delivery = Delivery::from_options(None).unwrap();
+    assert_eq!(delivery, Delivery::default());
+    assert_eq!(delivery.policy, DeliveryPolicy::Blocking);
+    assert_eq!(delivery.queue_size, UNBOUNDED);
+
+    let options = Delivery::from_options(Some(&MonitorOptions::default())).unwrap();
+    assert_eq!(options, Delivery::default());
+  }
+
+  #[test]
+  fn rejects_invalid_delivery_options() {
+    let zero = Delivery::from_options(Some(&MonitorOptions {
+      queue_size: Some(0),
+      policy: None,
+      ..Default::default()
+    }));
+    let unknown = Delivery::from_options(Some(&MonitorOptions {
+      queue_size: None,
+      policy: Some("latest".to_string()),
+      ..Default::default()
+    }));
+
+    assert_eq!(zero.unwrap_err().status, Status::InvalidArg);
+    assert_eq!(unknown.unwrap_err().status, Status::InvalidArg);
+  }
+}
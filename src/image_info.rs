@@ -0,0 +1,242 @@
+// 根据文件头识别缩略图格式与尺寸，只读取头部，截断的图片同样适用
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ImageFormat {
+  Png,
+  Jpeg,
+  Bmp,
+  Gif,
+  WebP,
+}
+
+impl ImageFormat {
+  pub fn mime_type(&self) -> &'static str {
+    match self {
+      ImageFormat::Png => "image/png",
+      ImageFormat::Jpeg => "image/jpeg",
+      ImageFormat::Bmp => "image/bmp",
+      ImageFormat::Gif => "image/gif",
+      ImageFormat::WebP => "image/webp",
+    }
+  }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct ImageInfo {
+  pub mime_type: String,
+  pub width: Option<u32>,
+  pub height: Option<u32>,
+}
+
+pub fn detect_format(bytes: &[u8]) -> Option<ImageFormat> {
+  if bytes.starts_with(b"\x89PNG\r\n\x1a\n") {
+    Some(ImageFormat::Png)
+  } else if bytes.starts_with(&[0xFF, 0xD8, 0xFF]) {
+    Some(ImageFormat::Jpeg)
+  } else if bytes.starts_with(b"BM") && bytes.len() >= 18 {
+    Some(ImageFormat::Bmp)
+  } else if bytes.starts_with(b"GIF87a") || bytes.starts_with(b"GIF89a") {
+    Some(ImageFormat::Gif)
+  } else if bytes.len() >= 12 && &bytes[0..4] == b"RIFF" && &bytes[8..12] == b"WEBP" {
+    Some(ImageFormat::WebP)
+  } else {
+    None
+  }
+}
+
+// 文件头无法识别时退回到流报告的 content type
+pub fn detect_image(bytes: &[u8], content_type: Option<&str>) -> Option<ImageInfo> {
+  match detect_format(bytes) {
+    Some(format) => {
+      let size = image_size(format, bytes);
+      Some(ImageInfo {
+        mime_type: format.mime_type().to_string(),
+        width: size.map(|(width, _)| width),
+        height: size.map(|(_, height)| height),
+      })
+    }
+    None => content_type
+      .filter(|content_type| content_type.starts_with("image/"))
+      .map(|content_type| ImageInfo {
+        mime_type: content_type.to_string(),
+        width: None,
+        height: None,
+      }),
+  }
+}
+
+pub fn image_size(format: ImageFormat, bytes: &[u8]) -> Option<(u32, u32)> {
+  match format {
+    ImageFormat::Png => png_size(bytes),
+    ImageFormat::Jpeg => jpeg_size(bytes),
+    ImageFormat::Bmp => bmp_size(bytes),
+    ImageFormat::Gif => Some((le16(bytes, 6)? as u32, le16(bytes, 8)? as u32)),
+    ImageFormat::WebP => webp_size(bytes),
+  }
+}
+
+fn png_size(bytes: &[u8]) -> Option<(u32, u32)> {
+  if bytes.get(12..16)? != b"IHDR" {
+    return None;
+  }
+  Some((be32(bytes, 16)?, be32(bytes, 20)?))
+}
+
+fn jpeg_size(bytes: &[u8]) -> Option<(u32, u32)> {
+  let mut pos = 2;
+  loop {
+    // 跳过标记前的填充字节
+    while *bytes.get(pos)? == 0xFF && *bytes.get(pos + 1)? == 0xFF {
+      pos += 1;
+    }
+    if *bytes.get(pos)? != 0xFF {
+      return None;
+    }
+
+    let marker = *bytes.get(pos + 1)?;
+    match marker {
+      // 没有长度字段的独立标记
+      0x01 | 0xD0..=0xD8 => pos += 2,
+      // SOF 段，DHT/JPG/DAC 除外
+      0xC0..=0xCF if !matches!(marker, 0xC4 | 0xC8 | 0xCC) => {
+        let height = be16(bytes, pos + 5)? as u32;
+        let width = be16(bytes, pos + 7)? as u32;
+        return Some((width, height));
+      }
+      0xD9 | 0xDA => return None,
+      _ => pos += 2 + be16(bytes, pos + 2)? as usize,
+    }
+  }
+}
+
+fn bmp_size(bytes: &[u8]) -> Option<(u32, u32)> {
+  // BITMAPCOREHEADER 使用 16 位尺寸，其它版本使用有符号 32 位，高度为负表示自上而下存储
+  if le32(bytes, 14)? == 12 {
+    return Some((le16(bytes, 18)? as u32, le16(bytes, 20)? as u32));
+  }
+  let width = le32(bytes, 18)? as i32;
+  let height = le32(bytes, 22)? as i32;
+  Some((width.unsigned_abs(), height.unsigned_abs()))
+}
+
+fn webp_size(bytes: &[u8]) -> Option<(u32, u32)> {
+  match bytes.get(12..16)? {
+    b"VP8 " => Some((
+      (le16(bytes, 26)? & 0x3FFF) as u32,
+      (le16(bytes, 28)? & 0x3FFF) as u32,
+    )),
+    b"VP8L" => {
+      let bits = le32(bytes, 21)?;
+      Some(((bits & 0x3FFF) + 1, ((bits >> 14) & 0x3FFF) + 1))
+    }
+    b"VP8X" => Some((le24(bytes, 24)? + 1, le24(bytes, 27)? + 1)),
+    _ => None,
+  }
+}
+
+fn be16(bytes: &[u8], pos: usize) -> Option<u16> {
+  Some(u16::from_be_bytes(
+    bytes.get(pos..pos + 2)?.try_into().ok()?,
+  ))
+}
+
+fn be32(bytes: &[u8], pos: usize) -> Option<u32> {
+  Some(u32::from_be_bytes(
+    bytes.get(pos..pos + 4)?.try_into().ok()?,
+  ))
+}
+
+fn le16(bytes: &[u8], pos: usize) -> Option<u16> {
+  Some(u16::from_le_bytes(
+    bytes.get(pos..pos + 2)?.try_into().ok()?,
+  ))
+}
+
+fn le24(bytes: &[u8], pos: usize) -> Option<u32> {
+  let bytes = bytes.get(pos..pos + 3)?;
+  Some(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], 0]))
+}
+
+fn le32(bytes: &[u8], pos: usize) -> Option<u32> {
+  Some(u32::from_le_bytes(
+    bytes.get(pos..pos + 4)?.try_into().ok()?,
+  ))
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const PNG: &[u8] = include_bytes!("../__test__/fixtures/cover.png");
+  const JPEG: &[u8] = include_bytes!("../__test__/fixtures/cover.jpg");
+  const BMP: &[u8] = include_bytes!("../__test__/fixtures/cover.bmp");
+  const GIF: &[u8] = include_bytes!("../__test__/fixtures/cover.gif");
+  const WEBP: &[u8] = include_bytes!("../__test__/fixtures/cover.webp");
+
+  fn info(mime_type: &str, width: u32, height: u32) -> Option<ImageInfo> {
+    Some(ImageInfo {
+      mime_type: mime_type.to_string(),
+      width: Some(width),
+      height: Some(height),
+    })
+  }
+
+  #[test]
+  fn detects_fixture_images() {
+    assert_eq!(detect_image(PNG, None), info("image/png", 3, 2));
+    assert_eq!(detect_image(JPEG, None), info("image/jpeg", 3, 2));
+    assert_eq!(detect_image(BMP, None), info("image/bmp", 3, 2));
+    assert_eq!(detect_image(GIF, None), info("image/gif", 3, 2));
+    assert_eq!(detect_image(WEBP, None), info("image/webp", 3, 2));
+  }
+
+  #[test]
+  fn magic_bytes_take_precedence_over_content_type() {
+    assert_eq!(
+      detect_image(PNG, Some("image/jpeg")),
+      info("image/png", 3, 2)
+    );
+  }
+
+  #[test]
+  fn falls_back_to_image_content_type() {
+    let unknown = [0u8; 16];
+
+    assert_eq!(
+      detect_image(&unknown, Some("image/avif")),
+      Some(ImageInfo {
+        mime_type: "image/avif".to_string(),
+        width: None,
+        height: None,
+      })
+    );
+    assert_eq!(
+      detect_image(&unknown, Some("application/octet-stream")),
+      None
+    );
+    assert_eq!(detect_image(&unknown, None), None);
+  }
+
+  #[test]
+  fn reads_size_from_truncated_images() {
+    assert_eq!(detect_image(&PNG[..24], None), info("image/png", 3, 2));
+    assert_eq!(
+      detect_image(&PNG[..10], None),
+      Some(ImageInfo {
+        mime_type: "image/png".to_string(),
+        width: None,
+        height: None,
+      })
+    );
+  }
+
+  #[test]
+  fn reads_top_down_bmp_and_extended_webp_sizes() {
+    let mut top_down = BMP.to_vec();
+    top_down[22..26].copy_from_slice(&(-2i32).to_le_bytes());
+    assert_eq!(image_size(ImageFormat::Bmp, &top_down), Some((3, 2)));
+
+    let mut extended = b"RIFF\0\0\0\0WEBPVP8X\x0a\0\0\0\0\0\0\0".to_vec();
+    extended.extend_from_slice(&[0x7F, 0x07, 0x00, 0x37, 0x04, 0x00]);
+    assert_eq!(image_size(ImageFormat::WebP, &extended), Some((1920, 1080)));
+  }
+}
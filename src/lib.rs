@@ -3,12 +3,44 @@
 #[macro_use]
 extern crate napi_derive;
 
+mod backend;
+mod controls;
+mod delivery;
+mod image_info;
 mod media_control;
 mod monitor;
+#[cfg(target_os = "linux")]
+mod mpris;
+mod palette;
+mod position;
+mod query;
+mod recording;
+mod seek;
+mod session;
+mod session_key;
 mod session_manager;
+mod shared_manager;
+mod simulator;
+mod thumbnail;
+mod thumbnail_cache;
+mod timeout;
+mod transcode;
+mod transport;
 mod types;
 mod utils;
 
-pub use crate::media_control::{get_sessions, get_current_session, get_session_by_id};
+pub use crate::media_control::{
+  get_current_session, get_current_session_async, get_session_by_id, get_session_by_id_async,
+  get_session_by_key, get_session_by_key_async, get_sessions, get_sessions_async,
+  get_sessions_by_app_id, get_sessions_by_app_id_async, get_thumbnail, get_thumbnail_by_hash,
+  pause, play, seek_by, seek_to, set_playback_rate, set_repeat_mode, set_shuffle, skip_next,
+  skip_previous, stop, toggle_play_pause,
+};
 pub use crate::monitor::SMTCMonitor;
-pub use crate::types::{MediaInfo, MediaProps, PlaybackInfo, TimelineProps};
+pub use crate::recording::EventReplay;
+pub use crate::shared_manager::{get_session_manager_metrics, reset_session_manager};
+pub use crate::simulator::MediaSimulator;
+pub use crate::types::{
+  MediaInfo, MediaProps, PlaybackControls, PlaybackInfo, PlaybackStatus, PlaybackType, RepeatMode,
+  TimelineProps,
+};
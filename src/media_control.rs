@@ -1,86 +1,350 @@
 use napi::{bindgen_prelude::*, Result};
-use windows::Media::Control::GlobalSystemMediaTransportControlsSessionManager;
+#[cfg(windows)]
+use std::time::Duration;
+#[cfg(windows)]
+use windows::Media::Control::{
+  GlobalSystemMediaTransportControlsSession, GlobalSystemMediaTransportControlsSessionManager,
+};
 
-use crate::types::MediaInfo;
-use crate::utils;
+use crate::query::{SessionQuery, SessionTask, SessionsTask};
+use crate::seek::{SeekTarget, SeekTask};
+#[cfg(windows)]
+use crate::session_key;
+#[cfg(windows)]
+use crate::shared_manager;
+use crate::thumbnail::{ThumbnailOptions, ThumbnailSettings, ThumbnailTask};
+use crate::thumbnail_cache;
+use crate::timeout;
+#[cfg(windows)]
+use crate::transport;
+use crate::transport::{TransportCommand, TransportTask};
+use crate::types::{MediaInfo, RepeatMode};
 
 #[napi]
-pub fn get_current_session() -> Result<Option<MediaInfo>> {
-  let manager = create_manager()?;
-
-  manager
-    .GetCurrentSession()
-    .ok()
-    .map_or(Ok(None), |session| {
-      utils::get_media_info_for_session(&session)
-    })
+pub fn get_current_session(
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<Option<MediaInfo>> {
+  SessionTask::new(
+    SessionQuery::Current,
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )
+  .compute()
 }
 
 #[napi]
-pub fn get_sessions() -> Result<Vec<MediaInfo>> {
-  let manager = create_manager()?;
-  let sessions = match manager.GetSessions() {
-    Ok(s) => s,
-    Err(_) => return Ok(Vec::new()),
-  };
-  
-  let size = match sessions.Size() {
-    Ok(s) => s,
-    Err(_) => return Ok(Vec::new()),
-  };
+pub fn get_sessions(
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<Vec<MediaInfo>> {
+  SessionsTask::new(
+    SessionQuery::All,
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )
+  .compute()
+}
 
-  let mut result = Vec::new();
-  for i in 0..size {
-    let session = match sessions.GetAt(i) {
-      Ok(s) => s,
-      Err(_) => continue,
-    };
-    
-    if let Ok(Some(info)) = utils::get_media_info_for_session(&session) {
-      result.push(info);
-    }
-  }
+#[napi]
+pub fn get_session_by_id(
+  source_app_id: String,
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<Option<MediaInfo>> {
+  SessionTask::new(
+    SessionQuery::ById(source_app_id),
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )
+  .compute()
+}
 
-  Ok(result)
+#[napi]
+pub fn get_session_by_key(
+  session_key: String,
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<Option<MediaInfo>> {
+  SessionTask::new(
+    SessionQuery::ByKey(session_key),
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )
+  .compute()
 }
 
 #[napi]
-pub fn get_session_by_id(source_app_id: String) -> Result<Option<MediaInfo>> {
-  let manager = create_manager()?;
+pub fn get_sessions_by_app_id(
+  source_app_id: String,
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<Vec<MediaInfo>> {
+  SessionsTask::new(
+    SessionQuery::ByAppId(source_app_id),
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )
+  .compute()
+}
+
+#[napi(ts_return_type = "Promise<MediaInfo | null>")]
+pub fn get_current_session_async(
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<SessionTask>> {
+  Ok(AsyncTask::new(SessionTask::new(
+    SessionQuery::Current,
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+#[napi(ts_return_type = "Promise<MediaInfo[]>")]
+pub fn get_sessions_async(
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<SessionsTask>> {
+  Ok(AsyncTask::new(SessionsTask::new(
+    SessionQuery::All,
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+#[napi(ts_return_type = "Promise<MediaInfo | null>")]
+pub fn get_session_by_id_async(
+  source_app_id: String,
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<SessionTask>> {
+  Ok(AsyncTask::new(SessionTask::new(
+    SessionQuery::ById(source_app_id),
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+#[napi(ts_return_type = "Promise<MediaInfo | null>")]
+pub fn get_session_by_key_async(
+  session_key: String,
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<SessionTask>> {
+  Ok(AsyncTask::new(SessionTask::new(
+    SessionQuery::ByKey(session_key),
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+#[napi(ts_return_type = "Promise<MediaInfo[]>")]
+pub fn get_sessions_by_app_id_async(
+  source_app_id: String,
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<SessionsTask>> {
+  Ok(AsyncTask::new(SessionsTask::new(
+    SessionQuery::ByAppId(source_app_id),
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+fn thumbnail_settings(options: Option<ThumbnailOptions>) -> Result<ThumbnailSettings> {
+  ThumbnailSettings::from_options(options.as_ref())
+}
+
+// 按会话键读取封面，找不到会话或会话没有封面时返回 null
+#[napi(ts_return_type = "Promise<Buffer | null>")]
+pub fn get_thumbnail(
+  session_key: String,
+  options: Option<ThumbnailOptions>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<ThumbnailTask>> {
+  Ok(AsyncTask::new(ThumbnailTask::new(
+    session_key,
+    thumbnail_settings(options)?,
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+// 按内容哈希从缓存取回封面，返回缓存时的原始数据；哈希已被淘汰时返回 null
+#[napi(ts_return_type = "Buffer | null")]
+pub fn get_thumbnail_by_hash(hash: String) -> Option<Buffer> {
+  thumbnail_cache::cached_thumbnail(&hash).map(|bytes| Buffer::from(bytes.to_vec()))
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn play(
+  source_app_id: Option<String>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(source_app_id, TransportCommand::Play, timeout_ms)
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn pause(
+  source_app_id: Option<String>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(source_app_id, TransportCommand::Pause, timeout_ms)
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn toggle_play_pause(
+  source_app_id: Option<String>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(source_app_id, TransportCommand::TogglePlayPause, timeout_ms)
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn stop(
+  source_app_id: Option<String>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(source_app_id, TransportCommand::Stop, timeout_ms)
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn skip_next(
+  source_app_id: Option<String>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(source_app_id, TransportCommand::SkipNext, timeout_ms)
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn skip_previous(
+  source_app_id: Option<String>,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(source_app_id, TransportCommand::SkipPrevious, timeout_ms)
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn set_shuffle(
+  source_app_id: Option<String>,
+  active: bool,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(
+    source_app_id,
+    TransportCommand::SetShuffle(active),
+    timeout_ms,
+  )
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn set_repeat_mode(
+  source_app_id: Option<String>,
+  mode: RepeatMode,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(
+    source_app_id,
+    TransportCommand::set_repeat_mode(mode)?,
+    timeout_ms,
+  )
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn set_playback_rate(
+  source_app_id: Option<String>,
+  rate: f64,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  transport_task(
+    source_app_id,
+    TransportCommand::set_playback_rate(rate)?,
+    timeout_ms,
+  )
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn seek_to(
+  source_app_id: Option<String>,
+  seconds: f64,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<SeekTask>> {
+  Ok(AsyncTask::new(SeekTask::new(
+    None,
+    source_app_id,
+    SeekTarget::Absolute(seconds),
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn seek_by(
+  source_app_id: Option<String>,
+  offset_seconds: f64,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<SeekTask>> {
+  Ok(AsyncTask::new(SeekTask::new(
+    None,
+    source_app_id,
+    SeekTarget::Relative(offset_seconds),
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+fn transport_task(
+  source_app_id: Option<String>,
+  command: TransportCommand,
+  timeout_ms: Option<u32>,
+) -> Result<AsyncTask<TransportTask>> {
+  Ok(AsyncTask::new(TransportTask::new(
+    None,
+    source_app_id,
+    command,
+    timeout::timeout_or_default(timeout_ms)?,
+  )))
+}
+
+#[cfg(windows)]
+pub fn create_manager(
+  timeout: Duration,
+) -> Result<GlobalSystemMediaTransportControlsSessionManager> {
+  let operation = GlobalSystemMediaTransportControlsSessionManager::RequestAsync()
+    .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+
+  timeout::wait(&operation, "Requesting the session manager", timeout)
+}
+
+#[cfg(windows)]
+// 获取所有会话，读取失败的条目会被跳过；同时清理已消失会话的会话键
+pub fn collect_sessions(
+  manager: &GlobalSystemMediaTransportControlsSessionManager,
+) -> Vec<GlobalSystemMediaTransportControlsSession> {
+  // 管理器失效时丢弃共享实例，下次调用重新创建
   let sessions = match manager.GetSessions() {
     Ok(s) => s,
-    Err(_) => return Ok(None),
+    Err(_) => {
+      shared_manager::invalidate(manager);
+      return Vec::new();
+    }
   };
-  
+
   let size = match sessions.Size() {
-    Ok(s) => s, 
-    Err(_) => return Ok(None),
+    Ok(s) => s,
+    Err(_) => return Vec::new(),
   };
 
-  for i in 0..size {
-    let session = match sessions.GetAt(i) {
-      Ok(s) => s,
-      Err(_) => continue,
-    };
-    
-    let id = match session.SourceAppUserModelId() {
-      Ok(id) => id,
-      Err(_) => continue,
-    };
-    
-    if id.to_string() == source_app_id {
-      return utils::get_media_info_for_session(&session);
-    }
-  }
-
-  Ok(None)
+  let sessions: Vec<_> = (0..size).filter_map(|i| sessions.GetAt(i).ok()).collect();
+  session_key::retain_session_keys(&sessions);
+  sessions
 }
 
-pub fn create_manager() -> Result<GlobalSystemMediaTransportControlsSessionManager> {
-  let operation = GlobalSystemMediaTransportControlsSessionManager::RequestAsync()
-    .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
-
-  operation
-    .get()
-    .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
-}
\ No newline at end of file
+#[cfg(windows)]
+// 按会话键或 sourceAppId 查找会话，未指定时返回当前会话
+pub fn find_session(
+  manager: &GlobalSystemMediaTransportControlsSessionManager,
+  source_app_id: Option<&str>,
+) -> Option<GlobalSystemMediaTransportControlsSession> {
+  match source_app_id {
+    Some(_) => transport::select_session(collect_sessions(manager), None, source_app_id),
+    None => manager.GetCurrentSession().ok(),
+  }
+}
@@ -1,258 +1,395 @@
-use napi::{
-  bindgen_prelude::*,
-  threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
-  JsFunction, Result,
-};
+use napi::{bindgen_prelude::*, JsFunction, Result};
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::{Arc, Mutex};
-use windows::{
-  Foundation::{EventRegistrationToken, TypedEventHandler},
-  Media::Control::{
-    GlobalSystemMediaTransportControlsSession, GlobalSystemMediaTransportControlsSessionManager,
-  },
-};
-
-use crate::session_manager::{self, SessionManager};
-use crate::types::MediaInfo;
-use crate::utils::win_to_napi_err;
-use crate::{media_control, MediaProps, PlaybackInfo, TimelineProps};
+use std::time::Duration;
+
+use crate::backend::{self, EventToken, SharedBackend};
+use crate::delivery::{self, Delivery, SessionEvent};
+use crate::recording::EventRecorder;
+use crate::seek::{SeekTarget, SeekTask};
+use crate::session::MediaSession;
+use crate::session_manager::{self, CallbackList, SessionManager};
+use crate::simulator::MediaSimulator;
+use crate::thumbnail::{ThumbnailOptions, ThumbnailSettings};
+use crate::timeout;
+use crate::transport::{TransportCommand, TransportTask};
+use crate::{MediaInfo, MediaProps, PlaybackInfo, RepeatMode, TimelineProps};
 
 #[napi(object)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionCallbackData {
+  pub source_app_id: String,
+  pub session_key: String,
+}
+
+#[napi(object)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MediaPropsCallbackData {
   pub source_app_id: String,
+  pub session_key: String,
   pub media_props: MediaProps,
 }
 
 #[napi(object)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PlaybackInfoCallbackData {
   pub source_app_id: String,
+  pub session_key: String,
   pub playback_info: PlaybackInfo,
 }
 
 #[napi(object)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TimelinePropsCallbackData {
   pub source_app_id: String,
+  pub session_key: String,
   pub timeline_props: TimelineProps,
 }
 
+#[napi(object)]
+#[derive(Clone)]
+pub struct SessionUpdatedCallbackData {
+  pub source_app_id: String,
+  pub session_key: String,
+  // 更新后的完整会话快照
+  pub media_info: MediaInfo,
+  // 发生变化的字段，例如 "media.title"、"playback.playbackStatus"、"timeline.position"
+  pub changed_fields: Vec<String>,
+}
+
+// 会话管理器持有的全部会话快照
+#[napi(object)]
+#[derive(Clone)]
+pub struct MonitorState {
+  pub sessions: Vec<MediaInfo>,
+  pub current_session_key: Option<String>,
+}
+
+#[napi(object)]
+#[derive(Default)]
+pub struct MonitorOptions {
+  pub queue_size: Option<u32>,
+  #[napi(ts_type = "'blocking' | 'drop-oldest' | 'coalesce-latest'")]
+  pub policy: Option<String>,
+  pub thumbnail: Option<ThumbnailOptions>,
+  pub timeout_ms: Option<u32>,
+  pub omit_unchanged_thumbnail: Option<bool>,
+}
+
 #[napi(js_name = "SMTCMonitor")]
 pub struct SMTCMonitor {
   manager: Arc<Mutex<SessionManager>>,
-  smtc_manager: Option<GlobalSystemMediaTransportControlsSessionManager>,
-  sessions_changed_token: Option<EventRegistrationToken>,
-  current_session_changed_token: Option<EventRegistrationToken>,
+  delivery: Delivery,
+  dropped_events: Arc<AtomicU64>,
+  backend: Option<SharedBackend>,
+  backend_tokens: Vec<EventToken>,
+  // 正在写入的录制文件及其订阅 id
+  recording: Option<(Arc<EventRecorder>, Vec<u32>)>,
+}
+
+impl Default for SMTCMonitor {
+  fn default() -> Self {
+    Self::with_options(Delivery::default(), ThumbnailSettings::default())
+  }
 }
 
 #[napi]
 impl SMTCMonitor {
   #[napi(constructor)]
-  pub fn new() -> Self {
+  pub fn new(options: Option<MonitorOptions>) -> Result<Self> {
+    let delivery = Delivery::from_options(options.as_ref())?;
+    let thumbnail = options
+      .as_ref()
+      .and_then(|options| options.thumbnail.as_ref());
+    let thumbnail_settings = ThumbnailSettings::from_options(thumbnail)?;
+    let timeout =
+      timeout::timeout_or_default(options.as_ref().and_then(|options| options.timeout_ms))?;
+    let monitor = Self::with_options(delivery, thumbnail_settings);
+    monitor.manager.lock().unwrap().timeout = timeout;
+    if let Some(omit) = options
+      .as_ref()
+      .and_then(|options| options.omit_unchanged_thumbnail)
+    {
+      monitor.manager.lock().unwrap().omit_unchanged_thumbnail = omit;
+    }
+    Ok(monitor)
+  }
+
+  fn with_options(delivery: Delivery, thumbnail_settings: ThumbnailSettings) -> Self {
     Self {
-      manager: Arc::new(Mutex::new(SessionManager::new())),
-      smtc_manager: None,
-      sessions_changed_token: None,
-      current_session_changed_token: None,
+      manager: Arc::new(Mutex::new(SessionManager::new(thumbnail_settings))),
+      delivery,
+      dropped_events: Arc::new(AtomicU64::new(0)),
+      backend: None,
+      backend_tokens: Vec::new(),
+      recording: None,
     }
   }
 
   #[napi]
   pub fn initialize(&mut self) -> Result<()> {
-    self.smtc_manager = Some(media_control::create_manager()?);
+    self.attach(backend::resolve(None, self.timeout())?)
+  }
 
-    let manager = self.smtc_manager.as_ref().unwrap().clone();
-    let manager_clone = manager.clone();
-    let inner_manager = self.manager.clone();
+  // 代替 initialize()，改为监听模拟器中的会话
+  #[napi]
+  pub fn attach_simulator(&mut self, simulator: &MediaSimulator) -> Result<()> {
+    self.attach(simulator.backend())
+  }
 
-    self.scan_existing_sessions()?;
+  // 开始监听指定后端的会话，initialize() 使用系统的 SMTC 会话管理器；
+  // 已连接后端时需要先 destroy()，避免新旧后端的会话混在一起
+  pub fn attach(&mut self, backend: SharedBackend) -> Result<()> {
+    if self.backend.is_some() {
+      return Err(Error::new(
+        Status::GenericFailure,
+        "SMTCMonitor is already initialized. Call destroy() before initializing it again."
+          .to_string(),
+      ));
+    }
 
-    let token = win_to_napi_err(
-      manager.SessionsChanged(&TypedEventHandler::new(move |_, _| {
-        Self::handle_sessions_changed(&manager_clone, &inner_manager);
-        Ok(())
-      })),
-    )?;
+    self.backend_tokens = session_manager::attach(&backend, &self.manager)?;
+    self.backend = Some(backend);
+    Ok(())
+  }
 
-    self.sessions_changed_token = Some(token);
+  #[napi(ts_args_type = "callback: (error:unknown, media: MediaInfo) => void")]
+  pub fn on_session_added(&mut self, callback: JsFunction) -> Result<u32> {
+    self.subscribe(|inner| &mut inner.session_added_callbacks, &callback)
+  }
 
-    // 监听当前会话变化
-    let manager_clone = manager.clone();
-    let inner_manager = self.manager.clone();
-    let current_session_token = win_to_napi_err(
-      manager.CurrentSessionChanged(&TypedEventHandler::new(move |_, _| {
-        Self::handle_current_session_changed(&manager_clone, &inner_manager);
-        Ok(())
-      })),
-    )?;
+  #[napi(
+    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, sessionKey: string}) => void"
+  )]
+  pub fn on_session_removed(&mut self, callback: JsFunction) -> Result<u32> {
+    self.subscribe(|inner| &mut inner.session_removed_callbacks, &callback)
+  }
 
-    self.current_session_changed_token = Some(current_session_token);
+  #[napi(
+    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, sessionKey: string, mediaProps: MediaProps}) => void"
+  )]
+  pub fn on_media_properties_changed(&mut self, callback: JsFunction) -> Result<u32> {
+    self.subscribe(|inner| &mut inner.media_props_callbacks, &callback)
+  }
 
-    Ok(())
+  #[napi(
+    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, sessionKey: string, playbackInfo: PlaybackInfo}) => void"
+  )]
+  pub fn on_playback_info_changed(&mut self, callback: JsFunction) -> Result<u32> {
+    self.subscribe(|inner| &mut inner.playback_info_callbacks, &callback)
   }
 
-  fn handle_sessions_changed(
-    manager: &GlobalSystemMediaTransportControlsSessionManager,
-    inner_manager: &Arc<Mutex<SessionManager>>,
-  ) {
-    let sessions = match manager.GetSessions() {
-      Ok(s) => s,
-      Err(_) => return,
-    };
+  #[napi(
+    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, sessionKey: string, timelineProps: TimelineProps}) => void"
+  )]
+  pub fn on_timeline_properties_changed(&mut self, callback: JsFunction) -> Result<u32> {
+    self.subscribe(|inner| &mut inner.timeline_props_callbacks, &callback)
+  }
 
-    let inner = match inner_manager.lock() {
-      Ok(inner) => inner,
-      Err(_) => return,
-    };
+  #[napi(
+    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, sessionKey: string}) => void"
+  )]
+  pub fn on_current_session_changed(&mut self, callback: JsFunction) -> Result<u32> {
+    self.subscribe(
+      |inner| &mut inner.current_session_changed_callbacks,
+      &callback,
+    )
+  }
 
-    let mut inner = inner; // 转为可变引用
-    let mut current_ids = Vec::new();
+  #[napi(
+    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, sessionKey: string, mediaInfo: MediaInfo, changedFields: string[]}) => void"
+  )]
+  pub fn on_session_updated(&mut self, callback: JsFunction) -> Result<u32> {
+    self.subscribe(|inner| &mut inner.session_updated_callbacks, &callback)
+  }
 
-    let size = match sessions.Size() {
-      Ok(size) => size,
-      Err(_) => return,
-    };
+  // 会话管理器中所有会话的最新快照，按会话键排序
+  #[napi]
+  pub fn get_state(&self) -> MonitorState {
+    self
+      .manager
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .state()
+  }
 
-    // 处理现有会话
-    for i in 0..size {
-      let session = match sessions.GetAt(i) {
-        Ok(s) => s,
-        Err(_) => continue,
-      };
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn play(&self, source_app_id: Option<String>) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::Play)
+  }
 
-      let id = match session.SourceAppUserModelId() {
-        Ok(id) => id.to_string(),
-        Err(_) => continue,
-      };
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn pause(&self, source_app_id: Option<String>) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::Pause)
+  }
 
-      current_ids.push(id.clone());
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn toggle_play_pause(
+    &self,
+    source_app_id: Option<String>,
+  ) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::TogglePlayPause)
+  }
 
-      if !inner.sessions.contains_key(&id) {
-        Self::register_session(&mut inner, id.clone(), session);
-      }
-    }
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn stop(&self, source_app_id: Option<String>) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::Stop)
+  }
 
-    // 处理已移除的会话
-    let removed_ids: Vec<_> = inner
-      .sessions
-      .keys()
-      .filter(|id| !current_ids.contains(id))
-      .cloned()
-      .collect();
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn skip_next(&self, source_app_id: Option<String>) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::SkipNext)
+  }
 
-    for id in removed_ids {
-      inner.sessions.remove(&id);
-      for callback in &inner.session_removed_callbacks {
-        callback.call(Ok(id.clone()), ThreadsafeFunctionCallMode::Blocking);
-      }
-    }
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn skip_previous(&self, source_app_id: Option<String>) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::SkipPrevious)
   }
 
-  fn handle_current_session_changed(
-    manager: &GlobalSystemMediaTransportControlsSessionManager,
-    inner_manager: &Arc<Mutex<SessionManager>>,
-  ) {
-    let current_session = match manager.GetCurrentSession() {
-      Ok(session) => session,
-      Err(_) => return,
-    };
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn set_shuffle(
+    &self,
+    source_app_id: Option<String>,
+    active: bool,
+  ) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::SetShuffle(active))
+  }
 
-    let source_app_id = match current_session.SourceAppUserModelId() {
-      Ok(id) => id.to_string(),
-      Err(_) => return,
-    };
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn set_repeat_mode(
+    &self,
+    source_app_id: Option<String>,
+    mode: RepeatMode,
+  ) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::set_repeat_mode(mode)?)
+  }
 
-    if let Ok(inner) = inner_manager.lock() {
-      for callback in &inner.current_session_changed_callbacks {
-        callback.call(Ok(source_app_id.clone()), ThreadsafeFunctionCallMode::Blocking);
-      }
-    }
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn set_playback_rate(
+    &self,
+    source_app_id: Option<String>,
+    rate: f64,
+  ) -> Result<AsyncTask<TransportTask>> {
+    self.transport_task(source_app_id, TransportCommand::set_playback_rate(rate)?)
   }
 
-  #[napi(ts_args_type = "callback: (error:unknown, media: MediaInfo) => void")]
-  pub fn on_session_added(&mut self, callback: JsFunction) -> Result<()> {
-    let tsfn: ThreadsafeFunction<MediaInfo> =
-      callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
-    let mut inner = self.manager.lock().unwrap();
-    inner.session_added_callbacks.push(tsfn);
-    Ok(())
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn seek_to(
+    &self,
+    source_app_id: Option<String>,
+    seconds: f64,
+  ) -> Result<AsyncTask<SeekTask>> {
+    let backend = self.get_backend()?;
+    Ok(AsyncTask::new(SeekTask::new(
+      Some(backend),
+      source_app_id,
+      SeekTarget::Absolute(seconds),
+      self.timeout(),
+    )))
   }
 
-  #[napi(ts_args_type = "callback: (error:unknown, sourceAppId: string) => void")]
-  pub fn on_session_removed(&mut self, callback: JsFunction) -> Result<()> {
-    let tsfn: ThreadsafeFunction<String> =
-      callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
-    let mut inner = self.manager.lock().unwrap();
-    inner.session_removed_callbacks.push(tsfn);
-    Ok(())
+  #[napi(ts_return_type = "Promise<boolean>")]
+  pub fn seek_by(
+    &self,
+    source_app_id: Option<String>,
+    offset_seconds: f64,
+  ) -> Result<AsyncTask<SeekTask>> {
+    let backend = self.get_backend()?;
+    Ok(AsyncTask::new(SeekTask::new(
+      Some(backend),
+      source_app_id,
+      SeekTarget::Relative(offset_seconds),
+      self.timeout(),
+    )))
   }
 
-  #[napi(
-    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, mediaProps: MediaProps}) => void"
-  )]
-  pub fn on_media_properties_changed(&mut self, callback: JsFunction) -> Result<()> {
-    let tsfn: ThreadsafeFunction<MediaPropsCallbackData> =
-      callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
-    let mut inner = self.manager.lock().unwrap();
-    inner.media_props_callbacks.push(tsfn);
-    Ok(())
+  #[napi]
+  pub fn get_estimated_position(&self, source_app_id: Option<String>) -> Result<Option<f64>> {
+    let backend = self.get_backend()?;
+
+    let session = match backend::find_session(&*backend, source_app_id.as_deref()) {
+      Some(session) => session,
+      None => return Ok(None),
+    };
+
+    Ok(session.timeline_props(self.timeout())?.estimated_position)
   }
 
-  #[napi(
-    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, playbackInfo: PlaybackInfo}) => void"
-  )]
-  pub fn on_playback_info_changed(&mut self, callback: JsFunction) -> Result<()> {
-    let tsfn: ThreadsafeFunction<PlaybackInfoCallbackData> =
-      callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
-    let mut inner = self.manager.lock().unwrap();
-    inner.playback_info_callbacks.push(tsfn);
-    Ok(())
+  // 因队列已满或被合并而未投递给 JS 的事件总数
+  #[napi]
+  pub fn get_dropped_event_count(&self) -> f64 {
+    self.dropped_events.load(Ordering::Relaxed) as f64
   }
 
-  #[napi(
-    ts_args_type = "callback: (error:unknown, data: {sourceAppId: string, timelineProps: TimelineProps}) => void"
-  )]
-  pub fn on_timeline_properties_changed(&mut self, callback: JsFunction) -> Result<()> {
-    let tsfn: ThreadsafeFunction<TimelinePropsCallbackData> =
-      callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
-    let mut inner = self.manager.lock().unwrap();
-    inner.timeline_props_callbacks.push(tsfn);
+  // 把之后分发的全部事件写入 JSON Lines 文件，已存在的文件会被覆盖；
+  // 已初始化时先写入现有会话与当前会话，回放时从相同的状态开始
+  #[napi]
+  pub fn start_recording(&mut self, path: String) -> Result<()> {
+    let recorder = EventRecorder::create(&path)?;
+    self.stop_recording();
+
+    let ids = recorder.subscribe(&mut self.manager.lock().unwrap_or_else(|e| e.into_inner()));
+    if let Some(backend) = &self.backend {
+      recorder.record_snapshot(&self.manager, backend);
+    }
+    self.recording = Some((recorder, ids));
     Ok(())
   }
 
-  #[napi(ts_args_type = "callback: (error:unknown, sourceAppId: string) => void")]
-  pub fn on_current_session_changed(&mut self, callback: JsFunction) -> Result<()> {
-    let tsfn: ThreadsafeFunction<String> = 
-      callback.create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
-    let mut inner = self.manager.lock().unwrap();
-    inner.current_session_changed_callbacks.push(tsfn);
-    Ok(())
+  // 没有正在进行的录制时返回 false
+  #[napi]
+  pub fn stop_recording(&mut self) -> bool {
+    let (_, ids) = match self.recording.take() {
+      Some(recording) => recording,
+      None => return false,
+    };
+
+    let mut inner = self.manager.lock().unwrap_or_else(|e| e.into_inner());
+    for id in ids {
+      inner.unsubscribe(id);
+    }
+    true
   }
 
+  // 移除 on* 方法返回的订阅，仅释放对应的回调；订阅不存在时返回 false
   #[napi]
-  pub fn destroy(&mut self) -> Result<()> {
-    if let (Some(manager), Some(token)) = (&self.smtc_manager, self.sessions_changed_token.take()) {
-      let _ = manager.RemoveSessionsChanged(token);
+  pub fn off(&mut self, subscription_id: u32) -> bool {
+    match self.manager.lock() {
+      Ok(mut inner) => inner.unsubscribe(subscription_id),
+      Err(_) => false,
     }
+  }
+
+  #[napi]
+  pub fn remove_listener(&mut self, subscription_id: u32) -> bool {
+    self.off(subscription_id)
+  }
 
-    if let (Some(manager), Some(token)) = (&self.smtc_manager, self.current_session_changed_token.take()) {
-      let _ = manager.RemoveCurrentSessionChanged(token);
+  #[napi]
+  pub fn destroy(&mut self) -> Result<()> {
+    if let Some(backend) = &self.backend {
+      session_manager::detach(&**backend, std::mem::take(&mut self.backend_tokens));
     }
 
+    self.stop_recording();
+
     if let Ok(mut inner) = self.manager.lock() {
       inner.clear_all_sessions();
 
-      inner.session_added_callbacks.clear();
-      inner.session_removed_callbacks.clear();
-      inner.media_props_callbacks.clear();
-      inner.playback_info_callbacks.clear();
-      inner.timeline_props_callbacks.clear();
-      inner.current_session_changed_callbacks.clear();
+      inner.clear_all_callbacks();
     }
 
-    self.smtc_manager = None;
+    self.backend = None;
     Ok(())
   }
 
-  fn get_manager(&self) -> Result<GlobalSystemMediaTransportControlsSessionManager> {
-    self.smtc_manager.clone().ok_or_else(|| {
+  fn get_backend(&self) -> Result<SharedBackend> {
+    self.backend.clone().ok_or_else(|| {
       Error::new(
         Status::GenericFailure,
         "SMTCMonitor not initialized. Please call initialize() first.".to_string(),
@@ -260,47 +397,76 @@ impl SMTCMonitor {
     })
   }
 
-  fn scan_existing_sessions(&mut self) -> Result<()> {
-    let manager = self.get_manager()?;
-    let sessions = match manager.GetSessions() {
-      Ok(s) => s,
-      Err(_) => return Ok(()),
-    };
-
-    let mut inner = match self.manager.lock() {
-      Ok(inner) => inner,
-      Err(_) => return Ok(()),
-    };
-
-    let size = match sessions.Size() {
-      Ok(size) => size,
-      Err(_) => return Ok(()),
-    };
-
-    for i in 0..size {
-      let session = match sessions.GetAt(i) {
-        Ok(s) => s,
-        Err(_) => continue,
-      };
-
-      let id = match session.SourceAppUserModelId() {
-        Ok(id) => id.to_string(),
-        Err(_) => continue,
-      };
+  fn subscribe<T>(
+    &self,
+    select: fn(&mut SessionManager) -> &mut CallbackList<T>,
+    callback: &JsFunction,
+  ) -> Result<u32>
+  where
+    T: SessionEvent + ToNapiValue + Clone + Send + 'static,
+  {
+    let callback = delivery::js_callback(callback, self.delivery, &self.dropped_events)?;
+    let mut inner = self.manager.lock().map_err(|_| {
+      Error::new(
+        Status::GenericFailure,
+        "Session manager is poisoned".to_string(),
+      )
+    })?;
+    Ok(inner.subscribe(select, callback))
+  }
 
-      if !inner.sessions.contains_key(&id) {
-        Self::register_session(&mut inner, id.clone(), session);
-      }
-    }
+  fn transport_task(
+    &self,
+    source_app_id: Option<String>,
+    command: TransportCommand,
+  ) -> Result<AsyncTask<TransportTask>> {
+    let backend = self.get_backend()?;
+    Ok(AsyncTask::new(TransportTask::new(
+      Some(backend),
+      source_app_id,
+      command,
+      self.timeout(),
+    )))
+  }
 
-    Ok(())
+  // 构造时由 timeoutMs 指定，同时用于读取会话状态与发送命令
+  fn timeout(&self) -> Duration {
+    self
+      .manager
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .timeout
   }
+}
 
-  fn register_session(
-    inner: &mut SessionManager,
-    id: String,
-    session: GlobalSystemMediaTransportControlsSession,
-  ) {
-    session_manager::register_session(inner, id, session);
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::fake::FakeBackend;
+  use crate::session::fake::FakeSession;
+
+  #[test]
+  fn rejects_a_second_backend_until_destroyed() {
+    let first = Arc::new(FakeBackend::default());
+    first.add(FakeSession::new("a.exe"));
+    let second = Arc::new(FakeBackend::default());
+    second.add(FakeSession::new("b.exe"));
+
+    let mut monitor = SMTCMonitor::default();
+    monitor.attach(first.clone()).unwrap();
+    assert!(monitor.attach(second.clone()).is_err());
+    assert_eq!(second.handler_count(), 0);
+
+    monitor.destroy().unwrap();
+    assert_eq!(first.handler_count(), 0);
+    monitor.attach(second.clone()).unwrap();
+
+    let state = monitor.get_state();
+    let keys: Vec<_> = state
+      .sessions
+      .iter()
+      .map(|s| s.session_key.as_str())
+      .collect();
+    assert_eq!(keys, ["b.exe#1"]);
   }
 }
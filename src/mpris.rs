@@ -0,0 +1,1568 @@
+use napi::{Error, Result, Status};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs::File;
+use std::future::Future;
+use std::io::Read;
+use std::os::unix::ffi::OsStringExt;
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Sender};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::thread;
+use std::time::Duration;
+use tokio::runtime;
+use tokio::sync::mpsc::{self as mpsc_async, UnboundedSender};
+use tokio::sync::oneshot;
+use zbus::blocking::{fdo::DBusProxy, Connection, MessageIterator};
+use zbus::zvariant::{export::serde::Serialize, DynamicType, ObjectPath, OwnedValue, Value};
+use zbus::{MatchRule, Message, MessageType};
+
+use crate::backend::{
+  self, BackendEvent, EventHandler, EventToken, Handlers, MediaBackend, SharedSession,
+};
+use crate::controls::Capability;
+use crate::position::estimate_position;
+use crate::session::MediaSession;
+use crate::session_key::SessionKeys;
+use crate::shared_manager;
+use crate::thumbnail::{self, ChunkSource, ThumbnailData, ThumbnailSettings};
+use crate::timeout::{self, PendingOperation, DEFAULT_TIMEOUT};
+use crate::types::{
+  MediaInfo, MediaProps, PlaybackControls, PlaybackInfo, PlaybackStatus, PlaybackType, RepeatMode,
+  TimelineProps,
+};
+use crate::utils;
+
+const BUS_PREFIX: &str = "org.mpris.MediaPlayer2.";
+const OBJECT_PATH: &str = "/org/mpris/MediaPlayer2";
+const PLAYER_INTERFACE: &str = "org.mpris.MediaPlayer2.Player";
+const PROPERTIES_INTERFACE: &str = "org.freedesktop.DBus.Properties";
+const DBUS_NAME: &str = "org.freedesktop.DBus";
+// 表示没有曲目的 trackid，不能用于 SetPosition
+const NO_TRACK: &str = "/org/mpris/MediaPlayer2/TrackList/NoTrack";
+
+const MATCH_RULES: [&str; 3] = [
+  "type='signal',sender='org.freedesktop.DBus',interface='org.freedesktop.DBus',member='NameOwnerChanged',arg0namespace='org.mpris.MediaPlayer2'",
+  "type='signal',interface='org.freedesktop.DBus.Properties',member='PropertiesChanged',path='/org/mpris/MediaPlayer2',arg0='org.mpris.MediaPlayer2.Player'",
+  "type='signal',interface='org.mpris.MediaPlayer2.Player',member='Seeked',path='/org/mpris/MediaPlayer2'",
+];
+
+type Properties = HashMap<String, OwnedValue>;
+
+// zbus 与 org.freedesktop.DBus 接口的错误都转换为 napi 错误
+pub fn dbus_to_napi_err(err: impl std::fmt::Display) -> Error {
+  Error::new(Status::GenericFailure, err.to_string())
+}
+
+// 播放器使用的整数类型并不统一，元数据中的值还会多包一层 variant
+fn as_str<'a>(value: &'a Value<'_>) -> Option<&'a str> {
+  match value {
+    Value::Str(s) => Some(s.as_str()),
+    Value::ObjectPath(path) => Some(path.as_str()),
+    Value::Value(inner) => as_str(inner),
+    _ => None,
+  }
+}
+
+fn as_bool(value: &Value<'_>) -> Option<bool> {
+  match value {
+    Value::Bool(b) => Some(*b),
+    Value::Value(inner) => as_bool(inner),
+    _ => None,
+  }
+}
+
+fn as_i64(value: &Value<'_>) -> Option<i64> {
+  match value {
+    Value::U8(v) => Some(*v as i64),
+    Value::I16(v) => Some(*v as i64),
+    Value::U16(v) => Some(*v as i64),
+    Value::I32(v) => Some(*v as i64),
+    Value::U32(v) => Some(*v as i64),
+    Value::I64(v) => Some(*v),
+    Value::U64(v) => i64::try_from(*v).ok(),
+    Value::F64(v) => Some(*v as i64),
+    Value::Value(inner) => as_i64(inner),
+    _ => None,
+  }
+}
+
+fn as_f64(value: &Value<'_>) -> Option<f64> {
+  match value {
+    Value::F64(v) => Some(*v),
+    Value::Value(inner) => as_f64(inner),
+    other => as_i64(other).map(|v| v as f64),
+  }
+}
+
+// xesam 中的艺术家、流派为字符串数组，部分播放器只给出单个字符串
+fn as_strings(value: &Value<'_>) -> Vec<String> {
+  match value {
+    Value::Array(array) => array
+      .inner()
+      .iter()
+      .filter_map(as_str)
+      .map(str::to_string)
+      .collect(),
+    Value::Value(inner) => as_strings(inner),
+    other => as_str(other)
+      .map(|s| vec![s.to_string()])
+      .unwrap_or_default(),
+  }
+}
+
+fn dict_get<'a>(value: &'a Value<'static>, key: &str) -> Option<&'a Value<'static>> {
+  match value {
+    Value::Dict(dict) => dict
+      .iter()
+      .find(|(k, _)| as_str(k) == Some(key))
+      .map(|(_, v)| v),
+    Value::Value(inner) => dict_get(inner, key),
+    _ => None,
+  }
+}
+
+fn from_microseconds(value: i64) -> f64 {
+  value as f64 / 1_000_000.0
+}
+
+fn to_playback_status(status: &str) -> PlaybackStatus {
+  match status {
+    "Playing" => PlaybackStatus::PLAYING,
+    "Paused" => PlaybackStatus::PAUSED,
+    "Stopped" => PlaybackStatus::STOPPED,
+    _ => PlaybackStatus::UNRECOGNIZED,
+  }
+}
+
+fn from_loop_status(status: &str) -> Option<RepeatMode> {
+  match status {
+    "None" => Some(RepeatMode::NONE),
+    "Track" => Some(RepeatMode::TRACK),
+    "Playlist" => Some(RepeatMode::LIST),
+    _ => Some(RepeatMode::UNRECOGNIZED),
+  }
+}
+
+fn to_loop_status(mode: RepeatMode) -> Option<&'static str> {
+  match mode {
+    RepeatMode::NONE => Some("None"),
+    RepeatMode::TRACK => Some("Track"),
+    RepeatMode::LIST => Some("Playlist"),
+    RepeatMode::UNRECOGNIZED => None,
+  }
+}
+
+// 去掉总线名前缀与多实例播放器附加的 instance 后缀，
+// 例如 org.mpris.MediaPlayer2.firefox.instance_1_42 对应 firefox
+fn source_app_id(bus_name: &str) -> String {
+  let name = bus_name.strip_prefix(BUS_PREFIX).unwrap_or(bus_name);
+  let app_id = name
+    .split('.')
+    .take_while(|part| !part.starts_with("instance"))
+    .collect::<Vec<_>>()
+    .join(".");
+
+  if app_id.is_empty() {
+    name.to_string()
+  } else {
+    app_id
+  }
+}
+
+// 只支持本地文件，网络图片不会被下载
+fn art_path(url: &str) -> Option<PathBuf> {
+  let path = url.strip_prefix("file://")?;
+  let path = path.strip_prefix("localhost").unwrap_or(path);
+  if !path.starts_with('/') {
+    return None;
+  }
+
+  let bytes = path.as_bytes();
+  let mut decoded = Vec::with_capacity(bytes.len());
+  let mut i = 0;
+  while i < bytes.len() {
+    if bytes[i] == b'%' {
+      let hex = path.get(i + 1..i + 3)?;
+      decoded.push(u8::from_str_radix(hex, 16).ok()?);
+      i += 3;
+    } else {
+      decoded.push(bytes[i]);
+      i += 1;
+    }
+  }
+
+  Some(PathBuf::from(OsString::from_vec(decoded)))
+}
+
+struct FileSource(File);
+
+impl ChunkSource for FileSource {
+  fn read_chunk(&mut self, len: u32) -> Result<Vec<u8>> {
+    let mut chunk = Vec::with_capacity(len as usize);
+    (&mut self.0)
+      .take(len as u64)
+      .read_to_end(&mut chunk)
+      .map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    Ok(chunk)
+  }
+}
+
+// 读取失败时视为没有缩略图
+fn load_art(url: &str, settings: &ThumbnailSettings) -> Option<ThumbnailData> {
+  if !settings.include {
+    return None;
+  }
+
+  let file = File::open(art_path(url)?).ok()?;
+  let reported_size = file.metadata().map(|metadata| metadata.len()).unwrap_or(0);
+  let data =
+    thumbnail::read_to_limit(&mut FileSource(file), reported_size, settings.max_bytes).ok()?;
+
+  Some(data)
+    .filter(|data| !data.bytes.is_empty())
+    .map(|data| thumbnail::normalize(data, settings))
+}
+
+// GetAll 返回的 org.mpris.MediaPlayer2.Player 属性
+struct PlayerProps(Properties);
+
+impl PlayerProps {
+  fn get(&self, name: &str) -> Option<&Value<'static>> {
+    self.0.get(name).map(|value| &**value)
+  }
+
+  fn flag(&self, name: &str) -> bool {
+    self.get(name).and_then(as_bool).unwrap_or(false)
+  }
+
+  fn metadata(&self, key: &str) -> Option<&Value<'static>> {
+    dict_get(self.get("Metadata")?, key)
+  }
+
+  fn track_id(&self) -> Option<&str> {
+    self
+      .metadata("mpris:trackid")
+      .and_then(as_str)
+      .filter(|id| *id != NO_TRACK)
+  }
+
+  fn media_props(&self, settings: &ThumbnailSettings) -> MediaProps {
+    let text = |key| {
+      self
+        .metadata(key)
+        .and_then(as_str)
+        .unwrap_or_default()
+        .to_string()
+    };
+    let names = |key| self.metadata(key).map(as_strings).unwrap_or_default();
+
+    let mut media = MediaProps {
+      title: text("xesam:title"),
+      artist: names("xesam:artist").join(", "),
+      album_title: text("xesam:album"),
+      album_artist: names("xesam:albumArtist").join(", "),
+      genres: names("xesam:genre"),
+      track_number: self
+        .metadata("xesam:trackNumber")
+        .and_then(as_i64)
+        .and_then(|n| u32::try_from(n).ok())
+        .unwrap_or(0),
+      ..MediaProps::default()
+    };
+
+    let art = self
+      .metadata("mpris:artUrl")
+      .and_then(as_str)
+      .and_then(|url| load_art(url, settings));
+    utils::apply_thumbnail(&mut media, art, settings);
+    media
+  }
+
+  fn playback_info(&self) -> PlaybackInfo {
+    PlaybackInfo {
+      playback_status: self
+        .get("PlaybackStatus")
+        .and_then(as_str)
+        .map(to_playback_status)
+        .unwrap_or(PlaybackStatus::UNRECOGNIZED),
+      // MPRIS 不区分音乐与视频
+      playback_type: PlaybackType::UNKNOWN,
+      is_shuffle_active: self.get("Shuffle").and_then(as_bool),
+      auto_repeat_mode: self
+        .get("LoopStatus")
+        .and_then(as_str)
+        .and_then(from_loop_status),
+      playback_rate: self.get("Rate").and_then(as_f64),
+      controls: self.controls(),
+    }
+  }
+
+  fn controls(&self) -> PlaybackControls {
+    let control = self.flag("CanControl");
+    let (min_rate, max_rate) = self.rate_range();
+
+    PlaybackControls::from_fn(|capability| match capability {
+      Capability::Play => self.flag("CanPlay"),
+      Capability::Pause | Capability::PlayPauseToggle => self.flag("CanPause"),
+      Capability::Stop => control,
+      Capability::Next => self.flag("CanGoNext"),
+      Capability::Previous => self.flag("CanGoPrevious"),
+      Capability::Seek => self.flag("CanSeek"),
+      Capability::Shuffle => control && self.get("Shuffle").is_some(),
+      Capability::Repeat => control && self.get("LoopStatus").is_some(),
+      Capability::PlaybackRate => control && min_rate < max_rate,
+      _ => false,
+    })
+  }
+
+  // 未提供速率范围时视为只支持 1.0
+  fn rate_range(&self) -> (f64, f64) {
+    (
+      self.get("MinimumRate").and_then(as_f64).unwrap_or(1.0),
+      self.get("MaximumRate").and_then(as_f64).unwrap_or(1.0),
+    )
+  }
+
+  fn position(&self) -> i64 {
+    self.get("Position").and_then(as_i64).unwrap_or(0)
+  }
+
+  fn timeline_props(&self, playback: &PlaybackInfo, now: f64) -> TimelineProps {
+    let duration = self
+      .metadata("mpris:length")
+      .and_then(as_i64)
+      .map(from_microseconds)
+      .unwrap_or(0.0)
+      .max(0.0);
+
+    let mut timeline = TimelineProps {
+      position: from_microseconds(self.position()),
+      duration,
+      start_time: 0.0,
+      min_seek_time: 0.0,
+      max_seek_time: if self.flag("CanSeek") { duration } else { 0.0 },
+      // Position 不会随 PropertiesChanged 推送，读取时刻即为更新时间
+      last_updated_time: Some(now),
+      estimated_position: None,
+    };
+    timeline.estimated_position = Some(estimate_position(&timeline, playback, now));
+    timeline
+  }
+}
+
+// 属性变化对应的会话事件；播放状态与速率会影响推算位置，同时触发时间线事件
+fn changed_events(changed: &[String]) -> Vec<BackendEvent> {
+  let mut events = Vec::new();
+  for name in changed {
+    let related: &[BackendEvent] = match name.as_str() {
+      "Metadata" => &[
+        BackendEvent::MediaPropsChanged,
+        BackendEvent::TimelinePropsChanged,
+      ],
+      "PlaybackStatus" | "Rate" | "CanSeek" => &[
+        BackendEvent::PlaybackInfoChanged,
+        BackendEvent::TimelinePropsChanged,
+      ],
+      "Position" => &[BackendEvent::TimelinePropsChanged],
+      "LoopStatus" | "Shuffle" | "CanPlay" | "CanPause" | "CanGoNext" | "CanGoPrevious"
+      | "CanControl" | "MinimumRate" | "MaximumRate" => &[BackendEvent::PlaybackInfoChanged],
+      _ => &[],
+    };
+
+    for event in related {
+      if !events.contains(event) {
+        events.push(*event);
+      }
+    }
+  }
+  events
+}
+
+// MPRIS 没有当前会话的概念：最近开始播放的会话作为当前会话，
+// 当前会话退出后改选正在播放的会话，都没有播放时选第一个会话
+fn pick_current(
+  current: Option<&str>,
+  started: Option<&str>,
+  sessions: &[(&str, bool)],
+) -> Option<String> {
+  if let Some(started) = started {
+    return Some(started.to_string());
+  }
+
+  if let Some(current) = current.filter(|current| sessions.iter().any(|(key, _)| key == current)) {
+    return Some(current.to_string());
+  }
+
+  sessions
+    .iter()
+    .find(|(_, playing)| *playing)
+    .or(sessions.first())
+    .map(|(key, _)| key.to_string())
+}
+
+type CallJob = Pin<Box<dyn Future<Output = ()> + Send>>;
+
+// 所有 D-Bus 调用共用一个工作线程，在其单线程运行时上并发等待各自的回复
+fn call_worker() -> &'static UnboundedSender<CallJob> {
+  static WORKER: OnceLock<UnboundedSender<CallJob>> = OnceLock::new();
+  WORKER.get_or_init(|| {
+    let (sender, mut jobs) = mpsc_async::unbounded_channel::<CallJob>();
+    thread::spawn(move || {
+      let runtime = match runtime::Builder::new_current_thread().enable_all().build() {
+        Ok(runtime) => runtime,
+        Err(_) => return,
+      };
+      runtime.block_on(async move {
+        while let Some(job) = jobs.recv().await {
+          tokio::spawn(job);
+        }
+      });
+    });
+    sender
+  })
+}
+
+// 在工作线程上执行的 D-Bus 调用，由 timeout::wait 限制等待时间；
+// 超时后取消调用，迟到的回复直接丢弃
+struct PendingCall {
+  state: Arc<Mutex<CallState>>,
+  cancel: Mutex<Option<oneshot::Sender<()>>>,
+}
+
+#[derive(Default)]
+struct CallState {
+  result: Option<zbus::Result<Message>>,
+  done: Option<Sender<()>>,
+}
+
+impl PendingCall {
+  fn spawn(call: impl Future<Output = zbus::Result<Message>> + Send + 'static) -> Result<Self> {
+    let state = Arc::new(Mutex::new(CallState::default()));
+    let shared = state.clone();
+    let (cancel, cancelled) = oneshot::channel();
+    let job: CallJob = Box::pin(async move {
+      tokio::select! {
+        result = call => {
+          let mut state = shared.lock().unwrap_or_else(|e| e.into_inner());
+          state.result = Some(result);
+          if let Some(done) = state.done.take() {
+            let _ = done.send(());
+          }
+        }
+        _ = cancelled => {}
+      }
+    });
+
+    call_worker().send(job).map_err(|_| {
+      Error::new(
+        Status::GenericFailure,
+        "The D-Bus worker thread is not running".to_string(),
+      )
+    })?;
+
+    Ok(Self {
+      state,
+      cancel: Mutex::new(Some(cancel)),
+    })
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, CallState> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+}
+
+impl PendingOperation for PendingCall {
+  type Output = Message;
+
+  fn on_completed(&self, done: Sender<()>) -> Result<()> {
+    let mut state = self.lock();
+    if state.result.is_some() {
+      let _ = done.send(());
+    } else {
+      state.done = Some(done);
+    }
+    Ok(())
+  }
+
+  fn results(&self) -> Result<Message> {
+    match self.lock().result.take() {
+      Some(result) => result.map_err(dbus_to_napi_err),
+      None => Err(Error::new(
+        Status::GenericFailure,
+        "The D-Bus call has not completed",
+      )),
+    }
+  }
+
+  fn cancel(&self) {
+    let cancel = self.cancel.lock().unwrap_or_else(|e| e.into_inner()).take();
+    if let Some(cancel) = cancel {
+      let _ = cancel.send(());
+    }
+  }
+}
+
+#[derive(Clone)]
+struct Bus(Connection);
+
+impl Bus {
+  fn call<B>(
+    &self,
+    destination: &str,
+    interface: &'static str,
+    method: &'static str,
+    body: B,
+    name: &str,
+    timeout: Duration,
+  ) -> Result<Message>
+  where
+    B: Serialize + DynamicType + Send + Sync + 'static,
+  {
+    let connection = self.0.inner().clone();
+    let destination = destination.to_string();
+    let operation = PendingCall::spawn(async move {
+      connection
+        .call_method(
+          Some(destination.as_str()),
+          OBJECT_PATH,
+          Some(interface),
+          method,
+          &body,
+        )
+        .await
+    })?;
+
+    timeout::wait(&operation, name, timeout)
+  }
+
+  fn properties(&self, destination: &str, timeout: Duration) -> Result<PlayerProps> {
+    let reply = self.call(
+      destination,
+      PROPERTIES_INTERFACE,
+      "GetAll",
+      (PLAYER_INTERFACE,),
+      "Reading player properties",
+      timeout,
+    )?;
+    reply
+      .body()
+      .deserialize::<Properties>()
+      .map(PlayerProps)
+      .map_err(dbus_to_napi_err)
+  }
+}
+
+// 会话总线上的一个 MPRIS 播放器
+pub struct MprisSession {
+  bus: Bus,
+  bus_name: String,
+  owner: String,
+  source_app_id: String,
+  key: String,
+  playing: AtomicBool,
+  handlers: Handlers,
+}
+
+impl MprisSession {
+  fn properties(&self, timeout: Duration) -> Result<PlayerProps> {
+    self.bus.properties(&self.bus_name, timeout)
+  }
+
+  fn send<B>(&self, method: &'static str, body: B, timeout: Duration) -> Result<()>
+  where
+    B: Serialize + DynamicType + Send + Sync + 'static,
+  {
+    self
+      .bus
+      .call(
+        &self.bus_name,
+        PLAYER_INTERFACE,
+        method,
+        body,
+        "Waiting for the player",
+        timeout,
+      )
+      .map(|_| ())
+  }
+
+  // 对应的控制能力未开启时不发送命令，与 SMTC 返回 false 的行为一致
+  fn command(
+    &self,
+    enabled: fn(&PlaybackControls) -> bool,
+    method: &'static str,
+    timeout: Duration,
+  ) -> Result<bool> {
+    if !enabled(&self.properties(timeout)?.controls()) {
+      return Ok(false);
+    }
+
+    self.send(method, (), timeout)?;
+    Ok(true)
+  }
+
+  fn set_property(
+    &self,
+    enabled: fn(&PlaybackControls) -> bool,
+    name: &'static str,
+    value: Value<'static>,
+    timeout: Duration,
+  ) -> Result<bool> {
+    if !enabled(&self.properties(timeout)?.controls()) {
+      return Ok(false);
+    }
+
+    self.bus.call(
+      &self.bus_name,
+      PROPERTIES_INTERFACE,
+      "Set",
+      (PLAYER_INTERFACE, name, value),
+      "Waiting for the player",
+      timeout,
+    )?;
+    Ok(true)
+  }
+}
+
+impl MediaSession for MprisSession {
+  fn source_app_id(&self) -> Result<String> {
+    Ok(self.source_app_id.clone())
+  }
+
+  fn session_key(&self) -> Result<String> {
+    Ok(self.key.clone())
+  }
+
+  fn media_info(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaInfo>> {
+    let props = self.properties(timeout)?;
+    let playback = props.playback_info();
+    let now = utils::now_millis();
+
+    Ok(Some(MediaInfo {
+      source_app_id: self.source_app_id.clone(),
+      session_key: self.key.clone(),
+      media: props.media_props(thumbnail),
+      timeline: props.timeline_props(&playback, now),
+      playback,
+      last_updated_time: now,
+      error: None,
+    }))
+  }
+
+  fn media_props(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaProps>> {
+    Ok(Some(self.properties(timeout)?.media_props(thumbnail)))
+  }
+
+  fn playback_info(&self, timeout: Duration) -> Result<Option<PlaybackInfo>> {
+    Ok(Some(self.properties(timeout)?.playback_info()))
+  }
+
+  fn try_play(&self, timeout: Duration) -> Result<bool> {
+    self.command(|controls| controls.play, "Play", timeout)
+  }
+
+  fn try_pause(&self, timeout: Duration) -> Result<bool> {
+    self.command(|controls| controls.pause, "Pause", timeout)
+  }
+
+  fn try_toggle_play_pause(&self, timeout: Duration) -> Result<bool> {
+    self.command(|controls| controls.play_pause_toggle, "PlayPause", timeout)
+  }
+
+  fn try_stop(&self, timeout: Duration) -> Result<bool> {
+    self.command(|controls| controls.stop, "Stop", timeout)
+  }
+
+  fn try_skip_next(&self, timeout: Duration) -> Result<bool> {
+    self.command(|controls| controls.next, "Next", timeout)
+  }
+
+  fn try_skip_previous(&self, timeout: Duration) -> Result<bool> {
+    self.command(|controls| controls.previous, "Previous", timeout)
+  }
+
+  fn is_seek_enabled(&self, timeout: Duration) -> Result<bool> {
+    Ok(self.properties(timeout)?.flag("CanSeek"))
+  }
+
+  fn timeline_props(&self, timeout: Duration) -> Result<TimelineProps> {
+    let props = self.properties(timeout)?;
+    Ok(props.timeline_props(&props.playback_info(), utils::now_millis()))
+  }
+
+  fn try_change_playback_position(&self, position: f64, timeout: Duration) -> Result<bool> {
+    let props = self.properties(timeout)?;
+    if !props.flag("CanSeek") {
+      return Ok(false);
+    }
+
+    // SetPosition 需要当前曲目的 trackid，播放器未提供时改用相对跳转
+    let target = (position * 1_000_000.0).round() as i64;
+    match props
+      .track_id()
+      .and_then(|id| ObjectPath::try_from(id.to_string()).ok())
+    {
+      Some(track_id) => self.send("SetPosition", (track_id, target), timeout)?,
+      None => self.send("Seek", (target - props.position(),), timeout)?,
+    }
+    Ok(true)
+  }
+
+  fn try_change_shuffle_active(&self, active: bool, timeout: Duration) -> Result<bool> {
+    self.set_property(
+      |controls| controls.shuffle,
+      "Shuffle",
+      Value::from(active),
+      timeout,
+    )
+  }
+
+  fn try_change_auto_repeat_mode(&self, mode: RepeatMode, timeout: Duration) -> Result<bool> {
+    let status = match to_loop_status(mode) {
+      Some(status) => status,
+      None => return Ok(false),
+    };
+
+    self.set_property(
+      |controls| controls.repeat,
+      "LoopStatus",
+      Value::from(status),
+      timeout,
+    )
+  }
+
+  fn try_change_playback_rate(&self, rate: f64, timeout: Duration) -> Result<bool> {
+    let (min_rate, max_rate) = self.properties(timeout)?.rate_range();
+    if !(min_rate..=max_rate).contains(&rate) {
+      return Ok(false);
+    }
+
+    self.set_property(
+      |controls| controls.playback_rate,
+      "Rate",
+      Value::from(rate),
+      timeout,
+    )
+  }
+
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+    match event {
+      BackendEvent::MediaPropsChanged
+      | BackendEvent::PlaybackInfoChanged
+      | BackendEvent::TimelinePropsChanged => Ok(self.handlers.add(event, handler)),
+      _ => Err(backend::unsupported_event(event)),
+    }
+  }
+
+  fn unsubscribe(&self, token: EventToken) {
+    self.handlers.remove(token);
+  }
+}
+
+enum Signal {
+  OwnerChanged {
+    name: String,
+    old_owner: String,
+    new_owner: String,
+  },
+  PropertiesChanged {
+    sender: String,
+    changed: Vec<String>,
+    playing: Option<bool>,
+  },
+  Seeked {
+    sender: String,
+  },
+}
+
+impl Signal {
+  fn parse(message: &Message) -> Option<Self> {
+    let header = message.header();
+    if header.message_type() != MessageType::Signal {
+      return None;
+    }
+
+    let sender = header.sender()?.to_string();
+    let body = message.body();
+    match (header.interface()?.as_str(), header.member()?.as_str()) {
+      (DBUS_NAME, "NameOwnerChanged") if sender == DBUS_NAME => {
+        let (name, old_owner, new_owner): (String, String, String) = body.deserialize().ok()?;
+        name
+          .starts_with(BUS_PREFIX)
+          .then_some(Signal::OwnerChanged {
+            name,
+            old_owner,
+            new_owner,
+          })
+      }
+      (PROPERTIES_INTERFACE, "PropertiesChanged") if header.path()?.as_str() == OBJECT_PATH => {
+        let (interface, changed, invalidated): (String, Properties, Vec<String>) =
+          body.deserialize().ok()?;
+        if interface != PLAYER_INTERFACE {
+          return None;
+        }
+
+        let playing = changed
+          .get("PlaybackStatus")
+          .and_then(|status| as_str(status))
+          .map(|status| status == "Playing");
+        Some(Signal::PropertiesChanged {
+          sender,
+          changed: changed.into_keys().chain(invalidated).collect(),
+          playing,
+        })
+      }
+      (PLAYER_INTERFACE, "Seeked") => Some(Signal::Seeked { sender }),
+      _ => None,
+    }
+  }
+}
+
+struct BackendState {
+  sessions: Vec<Arc<MprisSession>>,
+  // 以播放器连接的唯一名登记会话键，同一应用的多个实例分别编号
+  keys: SessionKeys<String>,
+  current: Option<String>,
+}
+
+// 通过会话总线上的 org.mpris.MediaPlayer2.* 提供媒体会话，对应 Windows 上的 SMTC 会话管理器
+pub struct MprisBackend {
+  bus: Bus,
+  state: Mutex<BackendState>,
+  handlers: Handlers,
+}
+
+// 按实例比较，供共享管理器判断缓存中的是否为同一个后端
+impl PartialEq for MprisBackend {
+  fn eq(&self, other: &Self) -> bool {
+    std::ptr::eq(self, other)
+  }
+}
+
+impl MprisBackend {
+  pub fn connect() -> Result<Arc<Self>> {
+    let connection = Connection::session().map_err(|e| {
+      Error::new(
+        Status::GenericFailure,
+        format!("Failed to connect to the D-Bus session bus: {}", e),
+      )
+    })?;
+    Self::with_connection(connection)
+  }
+
+  pub fn with_connection(connection: Connection) -> Result<Arc<Self>> {
+    // 先开始接收消息再列出已有的播放器，避免遗漏两者之间出现的播放器
+    let messages = MessageIterator::from(&connection);
+    let dbus = DBusProxy::new(&connection).map_err(dbus_to_napi_err)?;
+    for rule in MATCH_RULES {
+      let rule = MatchRule::try_from(rule).map_err(dbus_to_napi_err)?;
+      dbus.add_match_rule(rule).map_err(dbus_to_napi_err)?;
+    }
+
+    let mut names: Vec<String> = dbus
+      .list_names()
+      .map_err(dbus_to_napi_err)?
+      .into_iter()
+      .map(|name| name.to_string())
+      .filter(|name| name.starts_with(BUS_PREFIX))
+      .collect();
+    names.sort();
+
+    let backend = Arc::new(Self {
+      bus: Bus(connection),
+      state: Mutex::new(BackendState {
+        sessions: Vec::new(),
+        keys: SessionKeys::new(),
+        current: None,
+      }),
+      handlers: Handlers::default(),
+    });
+
+    for name in &names {
+      // 列出后才退出的播放器取不到所有者，直接跳过
+      let owner = zbus::names::BusName::try_from(name.as_str())
+        .ok()
+        .and_then(|bus_name| dbus.get_name_owner(bus_name).ok());
+      if let Some(owner) = owner {
+        backend.add_player(name, owner.as_str());
+      }
+    }
+    backend.update_current(None);
+    backend.listen(messages);
+
+    Ok(backend)
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, BackendState> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  // 读取线程只负责解析信号，处理器在另一个线程中执行：处理器会同步调用播放器，
+  // 如果在读取线程中执行，积压的消息会阻塞连接，调用的回复将无法送达
+  fn listen(self: &Arc<Self>, messages: MessageIterator) {
+    let (sender, receiver) = mpsc::channel();
+    thread::spawn(move || {
+      for message in messages.flatten() {
+        if let Some(signal) = Signal::parse(&message) {
+          if sender.send(signal).is_err() {
+            break;
+          }
+        }
+      }
+    });
+
+    // 只持有弱引用，后端被丢弃后收到下一个信号时两个线程都会退出
+    let backend = Arc::downgrade(self);
+    thread::spawn(move || {
+      for signal in receiver {
+        match backend.upgrade() {
+          Some(backend) => backend.handle(signal),
+          None => return,
+        }
+      }
+
+      // 连接已断开，丢弃共享实例，下次使用时重新连接
+      if let Some(backend) = backend.upgrade() {
+        shared_manager::invalidate(&backend);
+      }
+    });
+  }
+
+  fn handle(&self, signal: Signal) {
+    match signal {
+      Signal::OwnerChanged {
+        name,
+        old_owner,
+        new_owner,
+      } => {
+        if !old_owner.is_empty() {
+          self.remove_player(&name);
+        }
+        if !new_owner.is_empty() {
+          self.add_player(&name, &new_owner);
+        }
+        self.handlers.emit(BackendEvent::SessionsChanged);
+        self.update_current(None);
+      }
+      Signal::PropertiesChanged {
+        sender,
+        changed,
+        playing,
+      } => {
+        let session = match self.session_by_owner(&sender) {
+          Some(session) => session,
+          None => return,
+        };
+
+        for event in changed_events(&changed) {
+          session.handlers.emit(event);
+        }
+
+        if let Some(playing) = playing {
+          session.playing.store(playing, Ordering::Relaxed);
+          self.update_current(playing.then_some(session.key.as_str()));
+        }
+      }
+      Signal::Seeked { sender } => {
+        if let Some(session) = self.session_by_owner(&sender) {
+          session.handlers.emit(BackendEvent::TimelinePropsChanged);
+        }
+      }
+    }
+  }
+
+  fn add_player(&self, bus_name: &str, owner: &str) {
+    // 同一个播放器可能注册多个总线名，只保留一个会话
+    if self.session_by_owner(owner).is_some() {
+      return;
+    }
+
+    let playing = self
+      .bus
+      .properties(bus_name, DEFAULT_TIMEOUT)
+      .ok()
+      .and_then(|props| {
+        props
+          .get("PlaybackStatus")
+          .and_then(as_str)
+          .map(|s| s == "Playing")
+      })
+      .unwrap_or(false);
+    let source_app_id = source_app_id(bus_name);
+
+    let mut state = self.lock();
+    let key = state.keys.key_for(&owner.to_string(), &source_app_id);
+    state.sessions.push(Arc::new(MprisSession {
+      bus: self.bus.clone(),
+      bus_name: bus_name.to_string(),
+      owner: owner.to_string(),
+      source_app_id,
+      key,
+      playing: AtomicBool::new(playing),
+      handlers: Handlers::default(),
+    }));
+  }
+
+  fn remove_player(&self, bus_name: &str) {
+    let mut state = self.lock();
+    state
+      .sessions
+      .retain(|session| session.bus_name != bus_name);
+    let owners: Vec<String> = state
+      .sessions
+      .iter()
+      .map(|session| session.owner.clone())
+      .collect();
+    state.keys.retain(&owners);
+  }
+
+  fn session_by_owner(&self, owner: &str) -> Option<Arc<MprisSession>> {
+    self
+      .lock()
+      .sessions
+      .iter()
+      .find(|session| session.owner == owner)
+      .cloned()
+  }
+
+  fn update_current(&self, started: Option<&str>) {
+    let changed = {
+      let mut state = self.lock();
+      let sessions: Vec<_> = state
+        .sessions
+        .iter()
+        .map(|session| {
+          (
+            session.key.as_str(),
+            session.playing.load(Ordering::Relaxed),
+          )
+        })
+        .collect();
+      let current = pick_current(state.current.as_deref(), started, &sessions);
+      if current == state.current {
+        false
+      } else {
+        state.current = current;
+        true
+      }
+    };
+
+    if changed {
+      self.handlers.emit(BackendEvent::CurrentSessionChanged);
+    }
+  }
+}
+
+impl MediaBackend for MprisBackend {
+  fn sessions(&self) -> Vec<SharedSession> {
+    self
+      .lock()
+      .sessions
+      .iter()
+      .map(|session| session.clone() as SharedSession)
+      .collect()
+  }
+
+  fn current_session(&self) -> Option<SharedSession> {
+    let state = self.lock();
+    let current = state.current.as_deref()?;
+    state
+      .sessions
+      .iter()
+      .find(|session| session.key == current)
+      .map(|session| session.clone() as SharedSession)
+  }
+
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+    match event {
+      BackendEvent::SessionsChanged | BackendEvent::CurrentSessionChanged => {
+        Ok(self.handlers.add(event, handler))
+      }
+      _ => Err(backend::unsupported_event(event)),
+    }
+  }
+
+  fn unsubscribe(&self, token: EventToken) {
+    self.handlers.remove(token);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::io::{BufRead, BufReader};
+  use std::process::{Child, Command, Stdio};
+  use std::sync::mpsc::Receiver;
+  use std::time::Duration;
+  use zbus::blocking::ConnectionBuilder;
+
+  fn props(entries: Vec<(&str, Value<'static>)>) -> PlayerProps {
+    PlayerProps(
+      entries
+        .into_iter()
+        .map(|(name, value)| (name.to_string(), value.try_to_owned().unwrap()))
+        .collect(),
+    )
+  }
+
+  fn metadata(entries: Vec<(&str, Value<'static>)>) -> Value<'static> {
+    let metadata: HashMap<String, Value<'static>> = entries
+      .into_iter()
+      .map(|(key, value)| (key.to_string(), Value::Value(Box::new(value))))
+      .collect();
+    Value::from(metadata)
+  }
+
+  #[test]
+  fn maps_status_and_loop_mode() {
+    assert_eq!(to_playback_status("Playing"), PlaybackStatus::PLAYING);
+    assert_eq!(to_playback_status("Paused"), PlaybackStatus::PAUSED);
+    assert_eq!(to_playback_status("Stopped"), PlaybackStatus::STOPPED);
+    assert_eq!(
+      to_playback_status("Buffering"),
+      PlaybackStatus::UNRECOGNIZED
+    );
+
+    for mode in [RepeatMode::NONE, RepeatMode::TRACK, RepeatMode::LIST] {
+      assert_eq!(from_loop_status(to_loop_status(mode).unwrap()), Some(mode));
+    }
+    assert_eq!(from_loop_status("Shuffle"), Some(RepeatMode::UNRECOGNIZED));
+    assert_eq!(to_loop_status(RepeatMode::UNRECOGNIZED), None);
+  }
+
+  #[test]
+  fn derives_source_app_id_from_bus_name() {
+    assert_eq!(source_app_id("org.mpris.MediaPlayer2.vlc"), "vlc");
+    assert_eq!(
+      source_app_id("org.mpris.MediaPlayer2.firefox.instance_1_42"),
+      "firefox"
+    );
+    assert_eq!(
+      source_app_id("org.mpris.MediaPlayer2.chromium.instance1234"),
+      "chromium"
+    );
+    assert_eq!(
+      source_app_id("org.mpris.MediaPlayer2.org.gnome.Music"),
+      "org.gnome.Music"
+    );
+  }
+
+  #[test]
+  fn decodes_local_art_urls() {
+    assert_eq!(
+      art_path("file:///tmp/cover%20art.png"),
+      Some(PathBuf::from("/tmp/cover art.png"))
+    );
+    assert_eq!(
+      art_path("file://localhost/tmp/a.jpg"),
+      Some(PathBuf::from("/tmp/a.jpg"))
+    );
+    assert_eq!(art_path("https://example.com/a.jpg"), None);
+    assert_eq!(art_path("file:///tmp/bad%2"), None);
+  }
+
+  #[test]
+  fn converts_player_properties() {
+    let player = props(vec![
+      ("PlaybackStatus", Value::from("Playing")),
+      ("LoopStatus", Value::from("Playlist")),
+      ("Shuffle", Value::from(true)),
+      ("Rate", Value::from(1.0)),
+      ("MaximumRate", Value::from(2.0)),
+      ("Position", Value::from(30_000_000i64)),
+      ("CanControl", Value::from(true)),
+      ("CanPlay", Value::from(true)),
+      ("CanPause", Value::from(true)),
+      ("CanSeek", Value::from(true)),
+      (
+        "Metadata",
+        metadata(vec![
+          ("xesam:title", Value::from("Song")),
+          ("xesam:artist", Value::from(vec!["A", "B"])),
+          ("xesam:album", Value::from("Album")),
+          ("xesam:trackNumber", Value::from(3i32)),
+          ("mpris:length", Value::from(180_000_000u64)),
+        ]),
+      ),
+    ]);
+
+    let media = player.media_props(&ThumbnailSettings::default());
+    assert_eq!(media.title, "Song");
+    assert_eq!(media.artist, "A, B");
+    assert_eq!(media.album_title, "Album");
+    assert_eq!(media.track_number, 3);
+    assert!(media.thumbnail.is_none());
+
+    let playback = player.playback_info();
+    assert_eq!(playback.playback_status, PlaybackStatus::PLAYING);
+    assert_eq!(playback.auto_repeat_mode, Some(RepeatMode::LIST));
+    assert_eq!(playback.is_shuffle_active, Some(true));
+    let controls = &playback.controls;
+    assert!(controls.play && controls.pause && controls.seek);
+    assert!(controls.shuffle && controls.repeat && controls.playback_rate);
+    assert!(!controls.next && !controls.record);
+
+    let timeline = player.timeline_props(&playback, 1000.0);
+    assert_eq!(timeline.position, 30.0);
+    assert_eq!(timeline.duration, 180.0);
+    assert_eq!(timeline.max_seek_time, 180.0);
+    assert_eq!(timeline.last_updated_time, Some(1000.0));
+  }
+
+  #[test]
+  fn maps_changed_properties_to_events() {
+    let names = |names: &[&str]| names.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+
+    assert_eq!(
+      changed_events(&names(&["Metadata", "Position"])),
+      vec![
+        BackendEvent::MediaPropsChanged,
+        BackendEvent::TimelinePropsChanged
+      ]
+    );
+    assert_eq!(
+      changed_events(&names(&["PlaybackStatus", "Shuffle"])),
+      vec![
+        BackendEvent::PlaybackInfoChanged,
+        BackendEvent::TimelinePropsChanged
+      ]
+    );
+    assert!(changed_events(&names(&["Volume"])).is_empty());
+  }
+
+  #[test]
+  fn picks_current_player() {
+    let sessions = [("a#1", false), ("b#1", true)];
+    assert_eq!(pick_current(None, None, &sessions), Some("b#1".to_string()));
+    assert_eq!(
+      pick_current(Some("a#1"), None, &sessions),
+      Some("a#1".to_string())
+    );
+    assert_eq!(
+      pick_current(Some("a#1"), Some("c#1"), &sessions),
+      Some("c#1".to_string())
+    );
+    // 当前会话退出后改选正在播放的会话
+    assert_eq!(
+      pick_current(Some("gone#1"), None, &sessions),
+      Some("b#1".to_string())
+    );
+    assert_eq!(pick_current(Some("a#1"), None, &[]), None);
+  }
+
+  // 测试用的 MPRIS 播放器，记录收到的调用
+  #[derive(Default)]
+  struct FakeState {
+    calls: Vec<String>,
+    shuffle: bool,
+  }
+
+  struct FakePlayer(Arc<Mutex<FakeState>>);
+
+  impl FakePlayer {
+    fn record(&self, call: String) {
+      self.0.lock().unwrap().calls.push(call);
+    }
+  }
+
+  #[zbus::interface(name = "org.mpris.MediaPlayer2.Player")]
+  impl FakePlayer {
+    fn play(&self) {
+      self.record("Play".to_string());
+    }
+
+    fn pause(&self) {
+      self.record("Pause".to_string());
+    }
+
+    fn set_position(&self, track_id: ObjectPath<'_>, position: i64) {
+      self.record(format!("SetPosition {} {}", track_id, position));
+    }
+
+    #[zbus(property)]
+    fn playback_status(&self) -> String {
+      "Paused".to_string()
+    }
+
+    #[zbus(property)]
+    fn metadata(&self) -> HashMap<String, OwnedValue> {
+      let track_id = ObjectPath::try_from("/fake/track/1").unwrap();
+      HashMap::from([
+        (
+          "mpris:trackid".to_string(),
+          Value::from(track_id).try_into().unwrap(),
+        ),
+        (
+          "xesam:title".to_string(),
+          Value::from("Fake Song").try_into().unwrap(),
+        ),
+        (
+          "mpris:length".to_string(),
+          Value::from(120_000_000i64).try_into().unwrap(),
+        ),
+      ])
+    }
+
+    #[zbus(property)]
+    fn position(&self) -> i64 {
+      5_000_000
+    }
+
+    #[zbus(property)]
+    fn shuffle(&self) -> bool {
+      self.0.lock().unwrap().shuffle
+    }
+
+    #[zbus(property)]
+    fn set_shuffle(&mut self, shuffle: bool) {
+      self.0.lock().unwrap().shuffle = shuffle;
+    }
+
+    #[zbus(property)]
+    fn can_control(&self) -> bool {
+      true
+    }
+
+    #[zbus(property)]
+    fn can_play(&self) -> bool {
+      true
+    }
+
+    #[zbus(property)]
+    fn can_pause(&self) -> bool {
+      true
+    }
+
+    #[zbus(property)]
+    fn can_seek(&self) -> bool {
+      true
+    }
+
+    #[zbus(property)]
+    fn can_go_next(&self) -> bool {
+      false
+    }
+  }
+
+  // 独立的会话总线，退出测试时结束进程
+  struct TestBus {
+    daemon: Child,
+    address: String,
+    _dir: PathBuf,
+  }
+
+  impl Drop for TestBus {
+    fn drop(&mut self) {
+      let _ = self.daemon.kill();
+      let _ = self.daemon.wait();
+      let _ = std::fs::remove_dir_all(&self._dir);
+    }
+  }
+
+  fn start_bus() -> Option<TestBus> {
+    let dir = std::env::temp_dir().join(format!("smtc-mpris-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).ok()?;
+    let config = dir.join("bus.conf");
+    std::fs::write(
+      &config,
+      format!(
+        r#"<!DOCTYPE busconfig PUBLIC "-//freedesktop//DTD D-Bus Bus Configuration 1.0//EN"
+ "http://www.freedesktop.org/standards/dbus/1.0/busconfig.dtd">
+<busconfig>
+  <type>session</type>
+  <listen>unix:tmpdir={}</listen>
+  <auth>EXTERNAL</auth>
+  <policy context="default">
+    <allow send_destination="*" eavesdrop="true"/>
+    <allow eavesdrop="true"/>
+    <allow own="*"/>
+  </policy>
+</busconfig>
+"#,
+        dir.display()
+      ),
+    )
+    .ok()?;
+
+    let mut daemon = Command::new("dbus-daemon")
+      .arg(format!("--config-file={}", config.display()))
+      .args(["--nofork", "--print-address=1"])
+      .stdout(Stdio::piped())
+      .stderr(Stdio::null())
+      .spawn()
+      .ok()?;
+
+    let mut address = String::new();
+    let stdout = daemon.stdout.take()?;
+    BufReader::new(stdout).read_line(&mut address).ok()?;
+    Some(TestBus {
+      daemon,
+      address: address.trim().to_string(),
+      _dir: dir,
+    })
+  }
+
+  fn connect(bus: &TestBus) -> ConnectionBuilder<'static> {
+    ConnectionBuilder::address(bus.address.as_str()).unwrap()
+  }
+
+  fn start_player(bus: &TestBus, name: &'static str) -> (Connection, Arc<Mutex<FakeState>>) {
+    let state = Arc::new(Mutex::new(FakeState::default()));
+    let connection = connect(bus)
+      .name(name)
+      .unwrap()
+      .serve_at(OBJECT_PATH, FakePlayer(state.clone()))
+      .unwrap()
+      .build()
+      .unwrap();
+    (connection, state)
+  }
+
+  fn notify(
+    handlers: &dyn Fn(BackendEvent, EventHandler) -> Result<EventToken>,
+    event: BackendEvent,
+  ) -> Receiver<()> {
+    let (sender, receiver) = mpsc::channel();
+    let sender = Mutex::new(sender);
+    handlers(
+      event,
+      Arc::new(move || {
+        let _ = sender.lock().unwrap().send(());
+      }),
+    )
+    .unwrap();
+    receiver
+  }
+
+  fn wait_for(receiver: &Receiver<()>) {
+    receiver
+      .recv_timeout(Duration::from_secs(5))
+      .expect("event was not delivered");
+  }
+
+  #[test]
+  fn times_out_and_cancels_pending_calls() {
+    let dropped = Arc::new(AtomicBool::new(false));
+    let guard = DropFlag(dropped.clone());
+    let operation = PendingCall::spawn(async move {
+      let _guard = guard;
+      std::future::pending::<zbus::Result<Message>>().await
+    })
+    .unwrap();
+
+    let err = timeout::wait(
+      &operation,
+      "Waiting for the player",
+      Duration::from_millis(50),
+    )
+    .unwrap_err();
+    assert!(timeout::is_timeout(&err));
+
+    // 取消后工作线程丢弃挂起的调用
+    for _ in 0..100 {
+      if dropped.load(Ordering::SeqCst) {
+        break;
+      }
+      thread::sleep(Duration::from_millis(10));
+    }
+    assert!(dropped.load(Ordering::SeqCst));
+  }
+
+  struct DropFlag(Arc<AtomicBool>);
+
+  impl Drop for DropFlag {
+    fn drop(&mut self) {
+      self.0.store(true, Ordering::SeqCst);
+    }
+  }
+
+  // 系统中没有 dbus-daemon 时跳过
+  #[test]
+  fn reads_and_controls_players_on_a_test_bus() {
+    let bus = match start_bus() {
+      Some(bus) => bus,
+      None => return,
+    };
+
+    let (player, state) = start_player(&bus, "org.mpris.MediaPlayer2.fake.instance42");
+    let backend = MprisBackend::with_connection(connect(&bus).build().unwrap()).unwrap();
+
+    let sessions = backend.sessions();
+    assert_eq!(sessions.len(), 1);
+    let session = backend.current_session().unwrap();
+    assert_eq!(session.source_app_id().unwrap(), "fake");
+    assert_eq!(session.session_key().unwrap(), "fake#1");
+
+    let info = session
+      .media_info(&ThumbnailSettings::default(), DEFAULT_TIMEOUT)
+      .unwrap()
+      .unwrap();
+    assert_eq!(info.media.title, "Fake Song");
+    assert_eq!(info.playback.playback_status, PlaybackStatus::PAUSED);
+    assert_eq!(info.playback.is_shuffle_active, Some(false));
+    assert_eq!(info.timeline.position, 5.0);
+    assert_eq!(info.timeline.duration, 120.0);
+
+    // 命令与属性写入都转发给播放器
+    assert!(session.try_pause(DEFAULT_TIMEOUT).unwrap());
+    assert!(!session.try_skip_next(DEFAULT_TIMEOUT).unwrap());
+    assert!(session
+      .try_change_shuffle_active(true, DEFAULT_TIMEOUT)
+      .unwrap());
+    assert!(session
+      .try_change_playback_position(30.0, DEFAULT_TIMEOUT)
+      .unwrap());
+    {
+      let state = state.lock().unwrap();
+      assert_eq!(
+        state.calls,
+        vec!["Pause", "SetPosition /fake/track/1 30000000"]
+      );
+      assert!(state.shuffle);
+    }
+
+    // PropertiesChanged 转换为会话事件
+    let playback_changed = notify(
+      &|event, handler| session.subscribe(event, handler),
+      BackendEvent::PlaybackInfoChanged,
+    );
+    let changed: HashMap<&str, Value<'_>> = HashMap::from([("Shuffle", Value::from(true))]);
+    player
+      .emit_signal(
+        None::<()>,
+        OBJECT_PATH,
+        PROPERTIES_INTERFACE,
+        "PropertiesChanged",
+        &(PLAYER_INTERFACE, changed, Vec::<String>::new()),
+      )
+      .unwrap();
+    wait_for(&playback_changed);
+
+    // 播放器出现与退出都会触发会话列表变化
+    let sessions_changed = notify(
+      &|event, handler| backend.subscribe(event, handler),
+      BackendEvent::SessionsChanged,
+    );
+    let (other, _) = start_player(&bus, "org.mpris.MediaPlayer2.other");
+    wait_for(&sessions_changed);
+    assert_eq!(backend.sessions().len(), 2);
+
+    drop(other);
+    wait_for(&sessions_changed);
+    assert_eq!(backend.sessions().len(), 1);
+    assert_eq!(
+      backend.current_session().unwrap().session_key().unwrap(),
+      "fake#1"
+    );
+  }
+}
@@ -0,0 +1,195 @@
+use image::RgbaImage;
+use napi_derive::napi;
+use serde::{Deserialize, Serialize};
+
+// 取色前先缩小图片，颜色统计只需要少量像素
+const SAMPLE_SIZE: u32 = 64;
+const BLURHASH_COMPONENTS: (u32, u32) = (4, 3);
+
+#[napi(object)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ThumbnailPalette {
+  // 占比最高的颜色，格式为 #rrggbb
+  pub dominant: String,
+  // 没有合适的颜色时为 undefined
+  pub vibrant: Option<String>,
+  pub muted: Option<String>,
+  // 可在封面加载前作为占位图的 BlurHash
+  pub blurhash: String,
+}
+
+struct Swatch {
+  rgb: [u8; 3],
+  count: u32,
+}
+
+// 目标饱和度与亮度，按 Vibrant.js 的权重打分
+struct Target {
+  saturation: (f32, f32, f32),
+  lightness: (f32, f32, f32),
+}
+
+const VIBRANT: Target = Target {
+  saturation: (0.35, 1.0, 1.0),
+  lightness: (0.3, 0.5, 0.7),
+};
+
+const MUTED: Target = Target {
+  saturation: (0.0, 0.3, 0.4),
+  lightness: (0.3, 0.5, 0.7),
+};
+
+// 解码失败（如截断的图片）时返回 None
+pub fn extract_palette(bytes: &[u8]) -> Option<ThumbnailPalette> {
+  let mut image = image::load_from_memory(bytes).ok()?;
+  if image.width() > SAMPLE_SIZE || image.height() > SAMPLE_SIZE {
+    image = image.thumbnail(SAMPLE_SIZE, SAMPLE_SIZE);
+  }
+
+  palette_from_image(&image.to_rgba8())
+}
+
+fn palette_from_image(image: &RgbaImage) -> Option<ThumbnailPalette> {
+  let swatches = quantize(image);
+  let dominant = swatches.iter().max_by_key(|swatch| swatch.count)?;
+  let (components_x, components_y) = BLURHASH_COMPONENTS;
+  let blurhash = blurhash::encode(
+    components_x,
+    components_y,
+    image.width(),
+    image.height(),
+    image.as_raw(),
+  )
+  .ok()?;
+
+  Some(ThumbnailPalette {
+    dominant: to_hex(dominant.rgb),
+    vibrant: pick(&swatches, &VIBRANT, dominant.count).map(to_hex),
+    muted: pick(&swatches, &MUTED, dominant.count).map(to_hex),
+    blurhash,
+  })
+}
+
+// 每个通道量化为 16 级，同一格内的像素取平均色；忽略接近透明的像素
+fn quantize(image: &RgbaImage) -> Vec<Swatch> {
+  let mut buckets = vec![[0u32; 4]; 16 * 16 * 16];
+  for pixel in image.pixels() {
+    let [r, g, b, a] = pixel.0;
+    if a < 128 {
+      continue;
+    }
+
+    let index = ((r as usize >> 4) << 8) | ((g as usize >> 4) << 4) | (b as usize >> 4);
+    let bucket = &mut buckets[index];
+    bucket[0] += r as u32;
+    bucket[1] += g as u32;
+    bucket[2] += b as u32;
+    bucket[3] += 1;
+  }
+
+  buckets
+    .into_iter()
+    .filter(|bucket| bucket[3] > 0)
+    .map(|[r, g, b, count]| Swatch {
+      rgb: [(r / count) as u8, (g / count) as u8, (b / count) as u8],
+      count,
+    })
+    .collect()
+}
+
+fn pick(swatches: &[Swatch], target: &Target, max_count: u32) -> Option<[u8; 3]> {
+  let in_range = |value: f32, (min, _, max): (f32, f32, f32)| value >= min && value <= max;
+
+  swatches
+    .iter()
+    .filter_map(|swatch| {
+      let (saturation, lightness) = saturation_lightness(swatch.rgb);
+      if !in_range(saturation, target.saturation) || !in_range(lightness, target.lightness) {
+        return None;
+      }
+
+      let score = (1.0 - (saturation - target.saturation.1).abs()) * 3.0
+        + (1.0 - (lightness - target.lightness.1).abs()) * 6.5
+        + swatch.count as f32 / max_count as f32 * 0.5;
+      Some((score, swatch.rgb))
+    })
+    .max_by(|(a, _), (b, _)| a.total_cmp(b))
+    .map(|(_, rgb)| rgb)
+}
+
+// HSL 中的饱和度与亮度
+fn saturation_lightness([r, g, b]: [u8; 3]) -> (f32, f32) {
+  let max = r.max(g).max(b) as f32 / 255.0;
+  let min = r.min(g).min(b) as f32 / 255.0;
+  let lightness = (max + min) / 2.0;
+  let saturation = if max == min {
+    0.0
+  } else {
+    (max - min) / (1.0 - (2.0 * lightness - 1.0).abs())
+  };
+
+  (saturation, lightness)
+}
+
+fn to_hex([r, g, b]: [u8; 3]) -> String {
+  format!("#{:02x}{:02x}{:02x}", r, g, b)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::Rgba;
+
+  // 16x16：左侧 12 列为灰蓝色 (96, 112, 128)，右侧 4 列为红色 (220, 40, 50)
+  const PALETTE: &[u8] = include_bytes!("../__test__/fixtures/palette.png");
+
+  #[test]
+  fn extracts_palette_from_fixture() {
+    let palette = extract_palette(PALETTE).unwrap();
+
+    assert_eq!(palette.dominant, "#607080");
+    assert_eq!(palette.vibrant.as_deref(), Some("#dc2832"));
+    assert_eq!(palette.muted.as_deref(), Some("#607080"));
+    // 1 位分量数 + 1 位最大值 + 4 位 DC + 每个 AC 分量 2 位
+    assert_eq!(palette.blurhash.len(), 6 + 2 * (4 * 3 - 1));
+    assert_eq!(
+      blurhash::decode(&palette.blurhash, 4, 4, 1.0)
+        .unwrap()
+        .len(),
+      4 * 4 * 4
+    );
+  }
+
+  #[test]
+  fn extracts_palette_from_every_fixture_format() {
+    for bytes in [
+      &include_bytes!("../__test__/fixtures/cover.png")[..],
+      include_bytes!("../__test__/fixtures/cover.jpg"),
+      include_bytes!("../__test__/fixtures/cover.bmp"),
+      include_bytes!("../__test__/fixtures/cover.gif"),
+      include_bytes!("../__test__/fixtures/cover.webp"),
+    ] {
+      let palette = extract_palette(bytes).unwrap();
+      assert_eq!(palette.dominant.len(), 7);
+    }
+  }
+
+  #[test]
+  fn grayscale_images_have_no_vibrant_color() {
+    let image = RgbaImage::from_pixel(8, 8, Rgba([128, 128, 128, 255]));
+    let palette = palette_from_image(&image).unwrap();
+
+    assert_eq!(palette.dominant, "#808080");
+    assert_eq!(palette.vibrant, None);
+    assert_eq!(palette.muted.as_deref(), Some("#808080"));
+  }
+
+  #[test]
+  fn ignores_transparent_pixels_and_broken_images() {
+    let transparent = RgbaImage::from_pixel(4, 4, Rgba([255, 0, 0, 0]));
+
+    assert_eq!(palette_from_image(&transparent), None);
+    assert_eq!(extract_palette(&PALETTE[..40]), None);
+  }
+}
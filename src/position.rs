@@ -0,0 +1,120 @@
+use crate::types::{PlaybackInfo, PlaybackStatus, TimelineProps};
+
+// 根据播放器最近一次上报的位置推算当前位置
+// 仅在播放中时随时间推进，并按播放速率缩放，结果限制在时间线范围内
+pub fn estimate_position(timeline: &TimelineProps, playback: &PlaybackInfo, now: f64) -> f64 {
+  let mut position = timeline.position;
+
+  if playback.playback_status == PlaybackStatus::PLAYING {
+    if let Some(reported_at) = timeline.last_updated_time {
+      let elapsed = ((now - reported_at) / 1000.0).max(0.0);
+      let rate = playback
+        .playback_rate
+        .filter(|rate| *rate > 0.0)
+        .unwrap_or(1.0);
+      position += elapsed * rate;
+    }
+  }
+
+  clamp_to_timeline(timeline, position)
+}
+
+fn clamp_to_timeline(timeline: &TimelineProps, position: f64) -> f64 {
+  let position = position.max(timeline.start_time);
+
+  // 直播等没有时长的内容不设上限
+  if timeline.duration > timeline.start_time {
+    position.min(timeline.duration)
+  } else {
+    position
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::types::{PlaybackControls, PlaybackType};
+
+  fn timeline(position: f64, reported_at: Option<f64>) -> TimelineProps {
+    TimelineProps {
+      position,
+      duration: 200.0,
+      last_updated_time: reported_at,
+      ..Default::default()
+    }
+  }
+
+  fn playback(status: PlaybackStatus, rate: Option<f64>) -> PlaybackInfo {
+    PlaybackInfo {
+      playback_status: status,
+      playback_type: PlaybackType::MUSIC,
+      is_shuffle_active: None,
+      auto_repeat_mode: None,
+      playback_rate: rate,
+      controls: PlaybackControls::default(),
+    }
+  }
+
+  #[test]
+  fn advances_while_playing() {
+    let timeline = timeline(30.0, Some(1_000_000.0));
+    let playing = playback(PlaybackStatus::PLAYING, None);
+
+    assert_eq!(estimate_position(&timeline, &playing, 1_000_000.0), 30.0);
+    assert_eq!(estimate_position(&timeline, &playing, 1_002_500.0), 32.5);
+  }
+
+  #[test]
+  fn scales_with_playback_rate() {
+    let timeline = timeline(30.0, Some(1_000_000.0));
+
+    let fast = playback(PlaybackStatus::PLAYING, Some(2.0));
+    assert_eq!(estimate_position(&timeline, &fast, 1_004_000.0), 38.0);
+
+    let slow = playback(PlaybackStatus::PLAYING, Some(0.5));
+    assert_eq!(estimate_position(&timeline, &slow, 1_004_000.0), 32.0);
+  }
+
+  #[test]
+  fn stays_put_when_not_playing() {
+    let timeline = timeline(30.0, Some(1_000_000.0));
+
+    for status in [
+      PlaybackStatus::CLOSED,
+      PlaybackStatus::OPENED,
+      PlaybackStatus::CHANGING,
+      PlaybackStatus::STOPPED,
+      PlaybackStatus::PAUSED,
+      PlaybackStatus::UNRECOGNIZED,
+    ] {
+      let paused = playback(status, None);
+      assert_eq!(estimate_position(&timeline, &paused, 1_010_000.0), 30.0);
+    }
+  }
+
+  #[test]
+  fn stays_put_without_report_time() {
+    let timeline = timeline(30.0, None);
+    let playing = playback(PlaybackStatus::PLAYING, None);
+
+    assert_eq!(estimate_position(&timeline, &playing, 1_010_000.0), 30.0);
+  }
+
+  #[test]
+  fn clamps_to_duration_and_ignores_clock_skew() {
+    let timeline = timeline(195.0, Some(1_000_000.0));
+    let playing = playback(PlaybackStatus::PLAYING, None);
+
+    assert_eq!(estimate_position(&timeline, &playing, 1_060_000.0), 200.0);
+    assert_eq!(estimate_position(&timeline, &playing, 990_000.0), 195.0);
+  }
+
+  #[test]
+  fn does_not_clamp_without_duration() {
+    let mut timeline = timeline(30.0, Some(1_000_000.0));
+    timeline.duration = 0.0;
+    let playing = playback(PlaybackStatus::PLAYING, None);
+
+    assert_eq!(estimate_position(&timeline, &playing, 1_600_000.0), 630.0);
+  }
+}
@@ -0,0 +1,174 @@
+use napi::{bindgen_prelude::*, Result};
+use std::time::Duration;
+
+use crate::backend::{self, MediaBackend};
+use crate::session::MediaSession;
+use crate::thumbnail::ThumbnailSettings;
+use crate::types::MediaInfo;
+
+// 同步与异步的会话查询共用同一套查找逻辑
+#[derive(Clone, Debug, PartialEq)]
+pub enum SessionQuery {
+  Current,
+  All,
+  // 会话键或 sourceAppId
+  ById(String),
+  ByKey(String),
+  ByAppId(String),
+}
+
+impl SessionQuery {
+  pub fn run(
+    &self,
+    backend: &dyn MediaBackend,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Vec<MediaInfo>> {
+    let sessions = match self {
+      SessionQuery::Current => backend.current_session().into_iter().collect(),
+      SessionQuery::All => backend.sessions(),
+      SessionQuery::ById(id) => backend::find_session(backend, Some(id))
+        .into_iter()
+        .collect(),
+      SessionQuery::ByKey(key) => backend
+        .sessions()
+        .into_iter()
+        .find(|session| matches!(session.session_key(), Ok(k) if &k == key))
+        .into_iter()
+        .collect(),
+      SessionQuery::ByAppId(app_id) => backend
+        .sessions()
+        .into_iter()
+        .filter(|session| matches!(session.source_app_id(), Ok(id) if &id == app_id))
+        .collect(),
+    };
+
+    // 查询单个会话时保留读取错误，查询多个会话时跳过读取失败的条目
+    if self.is_single() {
+      return match sessions.first() {
+        Some(session) => Ok(
+          session
+            .media_info(thumbnail, timeout)?
+            .into_iter()
+            .collect(),
+        ),
+        None => Ok(Vec::new()),
+      };
+    }
+
+    Ok(
+      sessions
+        .iter()
+        .filter_map(|session| session.media_info(thumbnail, timeout).ok().flatten())
+        .collect(),
+    )
+  }
+
+  pub fn is_single(&self) -> bool {
+    matches!(
+      self,
+      SessionQuery::Current | SessionQuery::ById(_) | SessionQuery::ByKey(_)
+    )
+  }
+}
+
+// 在 libuv 线程池中执行 WinRT 调用，避免阻塞 JS 主线程
+pub struct SessionsTask {
+  query: SessionQuery,
+  thumbnail: ThumbnailSettings,
+  timeout: Duration,
+}
+
+impl SessionsTask {
+  pub fn new(query: SessionQuery, thumbnail: ThumbnailSettings, timeout: Duration) -> Self {
+    Self {
+      query,
+      thumbnail,
+      timeout,
+    }
+  }
+}
+
+impl Task for SessionsTask {
+  type Output = Vec<MediaInfo>;
+  type JsValue = Vec<MediaInfo>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let backend = backend::resolve(None, self.timeout)?;
+    self.query.run(&*backend, &self.thumbnail, self.timeout)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+// 只返回一个会话的查询，找不到时 resolve 为 null
+pub struct SessionTask(SessionsTask);
+
+impl SessionTask {
+  pub fn new(query: SessionQuery, thumbnail: ThumbnailSettings, timeout: Duration) -> Self {
+    Self(SessionsTask::new(query, thumbnail, timeout))
+  }
+}
+
+impl Task for SessionTask {
+  type Output = Option<MediaInfo>;
+  type JsValue = Option<MediaInfo>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    Ok(self.0.compute()?.into_iter().next())
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::fake::FakeBackend;
+  use crate::session::fake::FakeSession;
+  use crate::timeout::DEFAULT_TIMEOUT;
+
+  fn keys(infos: Vec<MediaInfo>) -> Vec<String> {
+    infos.into_iter().map(|info| info.session_key).collect()
+  }
+
+  #[test]
+  fn selects_sessions_for_each_query() {
+    let backend = FakeBackend::default();
+    backend.add(FakeSession::new("chrome.exe"));
+    let mut second_tab = FakeSession::new("chrome.exe");
+    second_tab.key = "chrome.exe#2".to_string();
+    backend.add(second_tab);
+    backend.add(FakeSession::new("player.exe"));
+
+    let run = |query: SessionQuery| {
+      keys(
+        query
+          .run(&backend, &ThumbnailSettings::default(), DEFAULT_TIMEOUT)
+          .unwrap(),
+      )
+    };
+
+    assert_eq!(run(SessionQuery::All).len(), 3);
+    assert!(run(SessionQuery::Current).is_empty());
+    assert_eq!(
+      run(SessionQuery::ByAppId("chrome.exe".to_string())),
+      ["chrome.exe#1", "chrome.exe#2"]
+    );
+    assert_eq!(
+      run(SessionQuery::ByKey("chrome.exe#2".to_string())),
+      ["chrome.exe#2"]
+    );
+    assert_eq!(
+      run(SessionQuery::ById("player.exe".to_string())),
+      ["player.exe#1"]
+    );
+
+    backend.set_current("player.exe#1");
+    assert_eq!(run(SessionQuery::Current), ["player.exe#1"]);
+  }
+}
@@ -0,0 +1,592 @@
+use napi::{bindgen_prelude::*, Result};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufRead, BufReader, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::backend::SharedBackend;
+use crate::monitor::{
+  MediaPropsCallbackData, PlaybackInfoCallbackData, SessionCallbackData, TimelinePropsCallbackData,
+};
+use crate::session::MediaSession;
+use crate::session_manager::{EventCallback, SessionManager};
+use crate::simulator::{MediaSimulator, SimulatedBackend};
+use crate::types::{MediaInfo, MediaProps, TimelineProps};
+use crate::utils;
+
+// 等待下一个事件时按此间隔检查是否已停止回放
+const STOP_CHECK_INTERVAL: Duration = Duration::from_millis(50);
+
+// 事件名与 JS 端 SMTCMonitor 触发的事件一致
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(tag = "event", content = "data")]
+pub enum MonitorEvent {
+  #[serde(rename = "session-added")]
+  SessionAdded(MediaInfo),
+  #[serde(rename = "session-removed")]
+  SessionRemoved(SessionCallbackData),
+  #[serde(rename = "session-media-changed")]
+  MediaPropsChanged(MediaPropsCallbackData),
+  #[serde(rename = "session-playback-changed")]
+  PlaybackInfoChanged(PlaybackInfoCallbackData),
+  #[serde(rename = "session-timeline-changed")]
+  TimelinePropsChanged(TimelinePropsCallbackData),
+  #[serde(rename = "current-session-changed")]
+  CurrentSessionChanged(SessionCallbackData),
+}
+
+// 录制文件中的一行，time 为事件分发时间（Unix 毫秒）
+#[derive(Clone, Serialize, Deserialize)]
+pub struct EventRecord {
+  pub time: f64,
+  #[serde(flatten)]
+  pub event: MonitorEvent,
+}
+
+// 将会话管理器分发的事件逐行写入 JSON Lines 文件
+pub struct EventRecorder {
+  file: Mutex<File>,
+}
+
+impl EventRecorder {
+  pub fn create(path: &str) -> Result<Arc<Self>> {
+    let file = File::create(path).map_err(|e| {
+      Error::new(
+        Status::GenericFailure,
+        format!("Failed to create recording \"{}\": {}", path, e),
+      )
+    })?;
+
+    Ok(Arc::new(Self {
+      file: Mutex::new(file),
+    }))
+  }
+
+  // 每个事件单独写入一行，进程异常退出时已写入的事件仍然完整；写入失败时丢弃该事件
+  pub fn record(&self, event: MonitorEvent) {
+    let record = EventRecord {
+      time: utils::now_millis(),
+      event,
+    };
+    if let Ok(mut line) = serde_json::to_string(&record) {
+      line.push('\n');
+      let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+      let _ = file.write_all(line.as_bytes());
+    }
+  }
+
+  // 订阅全部事件类型，返回的订阅 id 在停止录制时交给 SessionManager::unsubscribe。
+  // 记录发生在投递队列之前，被合并或丢弃的事件同样会写入
+  pub fn subscribe(self: &Arc<Self>, inner: &mut SessionManager) -> Vec<u32> {
+    vec![
+      inner.subscribe(
+        |inner| &mut inner.session_added_callbacks,
+        self.callback(MonitorEvent::SessionAdded),
+      ),
+      inner.subscribe(
+        |inner| &mut inner.session_removed_callbacks,
+        self.callback(MonitorEvent::SessionRemoved),
+      ),
+      inner.subscribe(
+        |inner| &mut inner.media_props_callbacks,
+        self.callback(MonitorEvent::MediaPropsChanged),
+      ),
+      inner.subscribe(
+        |inner| &mut inner.playback_info_callbacks,
+        self.callback(MonitorEvent::PlaybackInfoChanged),
+      ),
+      inner.subscribe(
+        |inner| &mut inner.timeline_props_callbacks,
+        self.callback(MonitorEvent::TimelinePropsChanged),
+      ),
+      inner.subscribe(
+        |inner| &mut inner.current_session_changed_callbacks,
+        self.callback(MonitorEvent::CurrentSessionChanged),
+      ),
+    ]
+  }
+
+  fn callback<T: 'static>(self: &Arc<Self>, wrap: fn(T) -> MonitorEvent) -> EventCallback<T> {
+    let recorder = self.clone();
+    Arc::new(move |data| recorder.record(wrap(data)))
+  }
+
+  // 录制开始前已存在的会话记为 session-added，回放时先恢复这些会话
+  pub fn record_snapshot(&self, manager: &Arc<Mutex<SessionManager>>, backend: &SharedBackend) {
+    let (sessions, thumbnail_settings, timeout) = {
+      let inner = manager.lock().unwrap_or_else(|e| e.into_inner());
+      let sessions: Vec<_> = inner
+        .sessions
+        .values()
+        .map(|session| session.session.clone())
+        .collect();
+      (sessions, inner.thumbnail_settings, inner.timeout)
+    };
+
+    for session in sessions {
+      if let Ok(Some(info)) = session.media_info(&thumbnail_settings, timeout) {
+        self.record(MonitorEvent::SessionAdded(info));
+      }
+    }
+
+    let current = backend.current_session();
+    if let Some((source_app_id, session_key)) =
+      current.and_then(|session| Some((session.source_app_id().ok()?, session.session_key().ok()?)))
+    {
+      self.record(MonitorEvent::CurrentSessionChanged(SessionCallbackData {
+        source_app_id,
+        session_key,
+      }));
+    }
+  }
+}
+
+pub fn read_recording(path: &str) -> Result<Vec<EventRecord>> {
+  let file = File::open(path).map_err(|e| {
+    Error::new(
+      Status::GenericFailure,
+      format!("Failed to open recording \"{}\": {}", path, e),
+    )
+  })?;
+
+  let mut records = Vec::new();
+  for (index, line) in BufReader::new(file).lines().enumerate() {
+    let line = line.map_err(|e| Error::new(Status::GenericFailure, e.to_string()))?;
+    if line.trim().is_empty() {
+      continue;
+    }
+
+    let record = serde_json::from_str(&line).map_err(|e| {
+      Error::new(
+        Status::InvalidArg,
+        format!("Invalid event on line {} of \"{}\": {}", index + 1, path, e),
+      )
+    })?;
+    records.push(record);
+  }
+  Ok(records)
+}
+
+// 录制中的更新时间换算到回放时刻，推算位置与录制时一致
+fn shift_timeline(timeline: &mut TimelineProps, offset: f64) {
+  if let Some(time) = timeline.last_updated_time.as_mut() {
+    *time += offset;
+  }
+}
+
+// 录制文件不保存封面数据，回放时清除封面字段，避免产生无法取回的哈希
+fn clear_thumbnail(media: &mut MediaProps) {
+  media.thumbnail = None;
+  media.thumbnail_truncated = false;
+  media.thumbnail_hash = None;
+  media.thumbnail_mime_type = None;
+  media.thumbnail_width = None;
+  media.thumbnail_height = None;
+  media.thumbnail_palette = None;
+}
+
+// 把一条录制的事件作用到模拟后端，由后端触发与真实会话相同的事件；
+// 找不到会话时（例如录制文件被截断）跳过该事件
+fn apply(backend: &SimulatedBackend, event: MonitorEvent, offset: f64) {
+  let _ = match event {
+    MonitorEvent::SessionAdded(mut info) => {
+      shift_timeline(&mut info.timeline, offset);
+      clear_thumbnail(&mut info.media);
+      backend.restore_session(info);
+      Ok(())
+    }
+    MonitorEvent::SessionRemoved(data) => {
+      backend.remove_session(&data.session_key);
+      Ok(())
+    }
+    MonitorEvent::MediaPropsChanged(mut data) => {
+      clear_thumbnail(&mut data.media_props);
+      backend.replace_media_props(&data.session_key, data.media_props)
+    }
+    MonitorEvent::PlaybackInfoChanged(data) => {
+      backend.replace_playback_info(&data.session_key, data.playback_info)
+    }
+    MonitorEvent::TimelinePropsChanged(mut data) => {
+      shift_timeline(&mut data.timeline_props, offset);
+      backend.replace_timeline_props(&data.session_key, data.timeline_props)
+    }
+    MonitorEvent::CurrentSessionChanged(data) => {
+      backend.set_current_session(Some(&data.session_key))
+    }
+  };
+}
+
+// 按录制时的间隔（除以 speed）依次应用事件，speed 为 0 时不等待；返回已应用的事件数
+fn replay(
+  backend: &SimulatedBackend,
+  records: &[EventRecord],
+  speed: f64,
+  stopped: &AtomicBool,
+) -> u32 {
+  let started = Instant::now();
+  let first_time = match records.first() {
+    Some(record) => record.time,
+    None => return 0,
+  };
+
+  let mut applied = 0;
+  for record in records {
+    if speed > 0.0 {
+      let delay = Duration::from_secs_f64(((record.time - first_time) / speed / 1000.0).max(0.0));
+      while let Some(remaining) = delay.checked_sub(started.elapsed()) {
+        if remaining.is_zero() || stopped.load(Ordering::Relaxed) {
+          break;
+        }
+        thread::sleep(remaining.min(STOP_CHECK_INTERVAL));
+      }
+    }
+
+    if stopped.load(Ordering::Relaxed) {
+      break;
+    }
+    apply(
+      backend,
+      record.event.clone(),
+      utils::now_millis() - record.time,
+    );
+    applied += 1;
+  }
+  applied
+}
+
+#[napi(object)]
+pub struct ReplayOptions {
+  // 回放速度倍数，默认 1 为实时回放，0 表示不等待、尽快回放
+  pub speed: Option<f64>,
+}
+
+fn parse_speed(options: Option<&ReplayOptions>) -> Result<f64> {
+  let speed = options.and_then(|options| options.speed).unwrap_or(1.0);
+  if speed.is_nan() || speed < 0.0 {
+    return Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "Invalid replay speed: {}. Expected a non-negative number",
+        speed
+      ),
+    ));
+  }
+  Ok(speed)
+}
+
+// 把录制文件中的事件回放到模拟器，SMTCMonitor 通过 attachSimulator 接收这些事件
+#[napi]
+pub struct EventReplay {
+  simulator: MediaSimulator,
+  records: Arc<Vec<EventRecord>>,
+  speed: f64,
+  stopped: Arc<AtomicBool>,
+}
+
+#[napi]
+impl EventReplay {
+  #[napi(constructor)]
+  pub fn new(path: String, options: Option<ReplayOptions>) -> Result<Self> {
+    Ok(Self {
+      simulator: MediaSimulator::default(),
+      records: Arc::new(read_recording(&path)?),
+      speed: parse_speed(options.as_ref())?,
+      stopped: Arc::new(AtomicBool::new(false)),
+    })
+  }
+
+  // 回放使用的模拟器，与 EventReplay 共享同一组会话
+  #[napi]
+  pub fn simulator(&self) -> MediaSimulator {
+    self.simulator.clone()
+  }
+
+  #[napi]
+  pub fn get_event_count(&self) -> u32 {
+    self.records.len() as u32
+  }
+
+  // 回放结束或被 stop() 中断后完成，结果为已回放的事件数
+  #[napi(ts_return_type = "Promise<number>")]
+  pub fn start(&self) -> AsyncTask<ReplayTask> {
+    self.stopped.store(false, Ordering::Relaxed);
+    AsyncTask::new(ReplayTask {
+      backend: self.simulator.simulated_backend(),
+      records: self.records.clone(),
+      speed: self.speed,
+      stopped: self.stopped.clone(),
+    })
+  }
+
+  #[napi]
+  pub fn stop(&self) {
+    self.stopped.store(true, Ordering::Relaxed);
+  }
+}
+
+pub struct ReplayTask {
+  backend: Arc<SimulatedBackend>,
+  records: Arc<Vec<EventRecord>>,
+  speed: f64,
+  stopped: Arc<AtomicBool>,
+}
+
+impl Task for ReplayTask {
+  type Output = u32;
+  type JsValue = u32;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    Ok(replay(
+      &self.backend,
+      &self.records,
+      self.speed,
+      &self.stopped,
+    ))
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::SharedBackend;
+  use crate::session_manager;
+  use crate::simulator::{SimulatedMediaProps, SimulatedPlaybackInfo, SimulatedSessionInit};
+  use crate::thumbnail::ThumbnailSettings;
+  use crate::types::PlaybackStatus;
+  use std::path::{Path, PathBuf};
+
+  fn temp_path(name: &str) -> PathBuf {
+    std::env::temp_dir().join(format!("smtc-{}-{}.jsonl", name, std::process::id()))
+  }
+
+  // 连接到模拟后端的会话管理器，并把分发的事件录制到指定文件
+  fn recorded(path: &Path) -> (Arc<SimulatedBackend>, Arc<Mutex<SessionManager>>) {
+    let backend = Arc::new(SimulatedBackend::default());
+    let shared = Arc::new(Mutex::new(
+      SessionManager::new(ThumbnailSettings::default()),
+    ));
+    let recorder = EventRecorder::create(path.to_str().unwrap()).unwrap();
+    recorder.subscribe(&mut shared.lock().unwrap());
+    session_manager::attach(&(backend.clone() as SharedBackend), &shared).unwrap();
+    (backend, shared)
+  }
+
+  fn summary(records: &[EventRecord]) -> Vec<String> {
+    records
+      .iter()
+      .map(|record| match &record.event {
+        MonitorEvent::SessionAdded(info) => {
+          format!("added {} {}", info.session_key, info.media.title)
+        }
+        MonitorEvent::SessionRemoved(data) => format!("removed {}", data.session_key),
+        MonitorEvent::MediaPropsChanged(data) => {
+          format!("media {} {}", data.session_key, data.media_props.title)
+        }
+        MonitorEvent::PlaybackInfoChanged(data) => format!(
+          "playback {} {:?}",
+          data.session_key, data.playback_info.playback_status
+        ),
+        MonitorEvent::TimelinePropsChanged(data) => format!(
+          "timeline {} {}",
+          data.session_key, data.timeline_props.position
+        ),
+        MonitorEvent::CurrentSessionChanged(data) => format!("current {}", data.session_key),
+      })
+      .collect()
+  }
+
+  fn record_session(backend: &SimulatedBackend) {
+    let key = backend.add_session(
+      "player.exe",
+      SimulatedSessionInit {
+        media: Some(SimulatedMediaProps {
+          title: Some("Song".to_string()),
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+    );
+    backend.set_current_session(Some(&key)).unwrap();
+    backend
+      .update_playback_info(
+        &key,
+        SimulatedPlaybackInfo {
+          playback_status: Some(PlaybackStatus::PAUSED),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    backend
+      .update_media_props(
+        &key,
+        SimulatedMediaProps {
+          title: Some("Next".to_string()),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    backend.remove_session(&key);
+  }
+
+  #[test]
+  fn writes_one_json_line_per_event() {
+    let record = EventRecord {
+      time: 1000.0,
+      event: MonitorEvent::SessionRemoved(SessionCallbackData {
+        source_app_id: "player.exe".to_string(),
+        session_key: "player.exe#1".to_string(),
+      }),
+    };
+
+    let line = serde_json::to_string(&record).unwrap();
+    assert_eq!(
+      line,
+      r#"{"time":1000.0,"event":"session-removed","data":{"sourceAppId":"player.exe","sessionKey":"player.exe#1"}}"#
+    );
+    let parsed: EventRecord = serde_json::from_str(&line).unwrap();
+    assert_eq!(summary(&[parsed]), ["removed player.exe#1"]);
+  }
+
+  #[test]
+  fn records_dispatched_events_in_order() {
+    let path = temp_path("record");
+    let (backend, _shared) = recorded(&path);
+    record_session(&backend);
+
+    let records = read_recording(path.to_str().unwrap()).unwrap();
+    assert_eq!(
+      summary(&records),
+      [
+        "added player.exe#1 Song",
+        "current player.exe#1",
+        "playback player.exe#1 PAUSED",
+        "media player.exe#1 Next",
+        "removed player.exe#1",
+      ]
+    );
+    assert!(records.windows(2).all(|pair| pair[0].time <= pair[1].time));
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn replays_a_recording_through_the_pipeline() {
+    let original = temp_path("original");
+    let (backend, _shared) = recorded(&original);
+    record_session(&backend);
+    let records = read_recording(original.to_str().unwrap()).unwrap();
+
+    // 回放到另一个会话管理器，重新录制的事件应与原始录制一致
+    let replayed = temp_path("replayed");
+    let (replay_backend, _replay_shared) = recorded(&replayed);
+    let applied = replay(&replay_backend, &records, 0.0, &AtomicBool::new(false));
+
+    assert_eq!(applied, 5);
+    assert_eq!(
+      summary(&read_recording(replayed.to_str().unwrap()).unwrap()),
+      summary(&records)
+    );
+    std::fs::remove_file(original).unwrap();
+    std::fs::remove_file(replayed).unwrap();
+  }
+
+  #[test]
+  fn replays_without_thumbnails() {
+    let original = temp_path("thumbnail");
+    let (backend, _shared) = recorded(&original);
+    let key = backend.add_session(
+      "player.exe",
+      SimulatedSessionInit {
+        media: Some(SimulatedMediaProps {
+          title: Some("Song".to_string()),
+          thumbnail: Some(vec![1, 2, 3].into()),
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+    );
+    backend
+      .update_media_props(
+        &key,
+        SimulatedMediaProps {
+          title: Some("Next".to_string()),
+          thumbnail: Some(vec![4, 5, 6].into()),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    let records = read_recording(original.to_str().unwrap()).unwrap();
+    match &records[0].event {
+      MonitorEvent::SessionAdded(info) => assert!(info.media.thumbnail_hash.is_some()),
+      _ => panic!("unexpected event"),
+    }
+
+    let replayed = temp_path("thumbnail-replayed");
+    let (replay_backend, _replay_shared) = recorded(&replayed);
+    assert_eq!(
+      replay(&replay_backend, &records, 0.0, &AtomicBool::new(false)),
+      2
+    );
+
+    let media: Vec<_> = read_recording(replayed.to_str().unwrap())
+      .unwrap()
+      .into_iter()
+      .map(|record| match record.event {
+        MonitorEvent::SessionAdded(info) => info.media,
+        MonitorEvent::MediaPropsChanged(data) => data.media_props,
+        _ => panic!("unexpected event"),
+      })
+      .collect();
+    assert_eq!(media.len(), 2);
+    assert!(media.iter().all(|media| media.thumbnail_hash.is_none()
+      && media.thumbnail_mime_type.is_none()
+      && !media.thumbnail_truncated));
+    std::fs::remove_file(original).unwrap();
+    std::fs::remove_file(replayed).unwrap();
+  }
+
+  #[test]
+  fn waits_between_events_according_to_speed() {
+    let removed = |time| EventRecord {
+      time,
+      event: MonitorEvent::SessionRemoved(SessionCallbackData {
+        source_app_id: "player.exe".to_string(),
+        session_key: "player.exe#1".to_string(),
+      }),
+    };
+    let records = [removed(0.0), removed(200.0)];
+    let backend = SimulatedBackend::default();
+
+    let started = Instant::now();
+    assert_eq!(replay(&backend, &records, 2.0, &AtomicBool::new(false)), 2);
+    assert!(started.elapsed() >= Duration::from_millis(100));
+
+    assert_eq!(replay(&backend, &records, 1.0, &AtomicBool::new(true)), 0);
+  }
+
+  #[test]
+  fn reports_invalid_lines() {
+    let path = temp_path("invalid");
+    std::fs::write(&path, "\n{\"time\":1,\"event\":\"unknown\"}\n").unwrap();
+
+    let err = match read_recording(path.to_str().unwrap()) {
+      Ok(_) => panic!("invalid recording was accepted"),
+      Err(err) => err,
+    };
+    assert_eq!(err.status, Status::InvalidArg);
+    assert!(err.reason.contains("line 2"));
+    std::fs::remove_file(path).unwrap();
+  }
+
+  #[test]
+  fn rejects_negative_speed() {
+    assert!(parse_speed(Some(&ReplayOptions { speed: Some(-1.0) })).is_err());
+    assert_eq!(parse_speed(None).unwrap(), 1.0);
+  }
+}
@@ -0,0 +1,275 @@
+use napi::{bindgen_prelude::*, Result};
+use std::fmt;
+use std::time::Duration;
+
+use crate::backend::{self, SharedBackend};
+use crate::session::MediaSession;
+use crate::transport::session_not_found;
+use crate::types::TimelineProps;
+
+// 可跳转的区间；许多播放器不填写 MaxSeekTime，此时退回到 EndTime
+pub fn seek_range(timeline: &TimelineProps) -> (f64, f64) {
+  let min = timeline.min_seek_time.max(timeline.start_time);
+  let max = if timeline.max_seek_time > min {
+    timeline.max_seek_time
+  } else if timeline.duration > min {
+    timeline.duration
+  } else {
+    f64::INFINITY
+  };
+
+  (min, max)
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SeekTarget {
+  Absolute(f64),
+  Relative(f64),
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum SeekError {
+  NotSupported,
+  InvalidPosition(f64),
+}
+
+impl SeekError {
+  pub fn code(&self) -> &'static str {
+    match self {
+      SeekError::NotSupported => "ERR_SEEK_NOT_SUPPORTED",
+      SeekError::InvalidPosition(_) => "ERR_SEEK_INVALID_POSITION",
+    }
+  }
+}
+
+impl fmt::Display for SeekError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      SeekError::NotSupported => write!(f, "Media session does not allow seeking"),
+      SeekError::InvalidPosition(seconds) => write!(f, "Invalid seek position: {}", seconds),
+    }
+  }
+}
+
+impl From<SeekError> for Error {
+  fn from(err: SeekError) -> Self {
+    let status = match err {
+      SeekError::NotSupported => Status::GenericFailure,
+      SeekError::InvalidPosition(_) => Status::InvalidArg,
+    };
+    Error::new(status, err.to_string())
+  }
+}
+
+// 计算跳转的目标位置（秒），结果被限制在会话允许的区间内
+pub fn resolve_seek_target(
+  timeline: &TimelineProps,
+  target: SeekTarget,
+) -> std::result::Result<f64, SeekError> {
+  let seconds = match target {
+    SeekTarget::Absolute(seconds) => seconds,
+    SeekTarget::Relative(offset) => {
+      timeline.estimated_position.unwrap_or(timeline.position) + offset
+    }
+  };
+
+  if !seconds.is_finite() {
+    return Err(SeekError::InvalidPosition(seconds));
+  }
+
+  let (min, max) = seek_range(timeline);
+  Ok(seconds.clamp(min, max))
+}
+
+pub fn seek_session<S: MediaSession>(
+  session: &S,
+  target: SeekTarget,
+  timeout: Duration,
+) -> std::result::Result<bool, SeekFailure> {
+  if !session.is_seek_enabled(timeout)? {
+    return Err(SeekError::NotSupported.into());
+  }
+
+  let timeline = session.timeline_props(timeout)?;
+  let seconds = resolve_seek_target(&timeline, target)?;
+
+  Ok(session.try_change_playback_position(seconds, timeout)?)
+}
+
+// 区分可由调用方识别的跳转错误和一般的 WinRT 错误
+#[derive(Debug)]
+pub enum SeekFailure {
+  Seek(SeekError),
+  Other(Error),
+}
+
+impl From<SeekError> for SeekFailure {
+  fn from(err: SeekError) -> Self {
+    SeekFailure::Seek(err)
+  }
+}
+
+impl From<Error> for SeekFailure {
+  fn from(err: Error) -> Self {
+    SeekFailure::Other(err)
+  }
+}
+
+pub struct SeekTask {
+  backend: Option<SharedBackend>,
+  source_app_id: Option<String>,
+  target: SeekTarget,
+  timeout: Duration,
+  error: Option<SeekError>,
+}
+
+impl SeekTask {
+  pub fn new(
+    backend: Option<SharedBackend>,
+    source_app_id: Option<String>,
+    target: SeekTarget,
+    timeout: Duration,
+  ) -> Self {
+    Self {
+      backend,
+      source_app_id,
+      target,
+      timeout,
+      error: None,
+    }
+  }
+}
+
+impl Task for SeekTask {
+  type Output = bool;
+  type JsValue = bool;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let backend = backend::resolve(self.backend.as_ref(), self.timeout)?;
+
+    let source_app_id = self.source_app_id.as_deref();
+    let session = backend::find_session(&*backend, source_app_id)
+      .ok_or_else(|| session_not_found(source_app_id))?;
+
+    match seek_session(&session, self.target, self.timeout) {
+      Ok(accepted) => Ok(accepted),
+      Err(SeekFailure::Seek(err)) => {
+        self.error = Some(err.clone());
+        Err(err.into())
+      }
+      Err(SeekFailure::Other(err)) => Err(err),
+    }
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+
+  // 为跳转错误附加 `code` 属性，使 JS 端可以区分错误类型
+  fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+    let seek_error = match self.error.take() {
+      Some(seek_error) => seek_error,
+      None => return Err(err),
+    };
+
+    let mut js_error = env.create_error(err)?;
+    js_error.set_named_property("code", env.create_string(seek_error.code())?)?;
+    Err(Error::from(js_error.into_unknown()))
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::session::fake::FakeSession;
+  use crate::timeout::DEFAULT_TIMEOUT;
+
+  fn timeline() -> TimelineProps {
+    TimelineProps {
+      position: 30.0,
+      duration: 200.0,
+      max_seek_time: 180.0,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn clamps_absolute_target_to_seek_range() {
+    let timeline = timeline();
+    assert_eq!(
+      resolve_seek_target(&timeline, SeekTarget::Absolute(90.5)),
+      Ok(90.5)
+    );
+    assert_eq!(
+      resolve_seek_target(&timeline, SeekTarget::Absolute(-5.0)),
+      Ok(0.0)
+    );
+    assert_eq!(
+      resolve_seek_target(&timeline, SeekTarget::Absolute(500.0)),
+      Ok(180.0)
+    );
+  }
+
+  #[test]
+  fn relative_target_is_offset_from_current_position() {
+    let timeline = timeline();
+    assert_eq!(
+      resolve_seek_target(&timeline, SeekTarget::Relative(10.0)),
+      Ok(40.0)
+    );
+    assert_eq!(
+      resolve_seek_target(&timeline, SeekTarget::Relative(-45.0)),
+      Ok(0.0)
+    );
+  }
+
+  #[test]
+  fn relative_target_prefers_estimated_position() {
+    let mut timeline = timeline();
+    timeline.estimated_position = Some(42.0);
+    assert_eq!(
+      resolve_seek_target(&timeline, SeekTarget::Relative(10.0)),
+      Ok(52.0)
+    );
+  }
+
+  #[test]
+  fn falls_back_to_end_time_without_max_seek_time() {
+    let mut timeline = timeline();
+    timeline.max_seek_time = 0.0;
+    assert_eq!(seek_range(&timeline), (0.0, 200.0));
+
+    timeline.duration = 0.0;
+    assert_eq!(seek_range(&timeline), (0.0, f64::INFINITY));
+  }
+
+  #[test]
+  fn rejects_non_finite_target() {
+    let timeline = timeline();
+    let result = resolve_seek_target(&timeline, SeekTarget::Absolute(f64::NAN));
+    assert!(matches!(result, Err(SeekError::InvalidPosition(_))));
+  }
+
+  #[test]
+  fn seeks_session_to_the_resolved_position() {
+    let mut session = FakeSession::new("player.exe");
+    session.timeline = timeline();
+
+    assert!(seek_session(&session, SeekTarget::Absolute(12.5), DEFAULT_TIMEOUT).unwrap());
+    assert_eq!(session.last_received().as_deref(), Some("seek:12.5"));
+  }
+
+  #[test]
+  fn reports_sessions_that_do_not_allow_seeking() {
+    let mut session = FakeSession::new("player.exe");
+    session.seek_enabled = false;
+
+    let result = seek_session(&session, SeekTarget::Absolute(12.5), DEFAULT_TIMEOUT);
+    assert!(matches!(
+      result,
+      Err(SeekFailure::Seek(SeekError::NotSupported))
+    ));
+    assert!(session.last_received().is_none());
+    assert_eq!(SeekError::NotSupported.code(), "ERR_SEEK_NOT_SUPPORTED");
+  }
+}
@@ -0,0 +1,483 @@
+use napi::Result;
+#[cfg(windows)]
+use napi::{Error, Status};
+use std::sync::Arc;
+use std::time::Duration;
+#[cfg(windows)]
+use windows::{
+  Foundation::{EventRegistrationToken, IAsyncOperation, TypedEventHandler},
+  Media::{Control::GlobalSystemMediaTransportControlsSession, MediaPlaybackAutoRepeatMode},
+};
+
+#[cfg(windows)]
+use crate::backend;
+use crate::backend::{BackendEvent, EventHandler, EventToken};
+#[cfg(windows)]
+use crate::session_key;
+use crate::thumbnail::ThumbnailSettings;
+#[cfg(windows)]
+use crate::timeout;
+use crate::types::{MediaInfo, MediaProps, PlaybackInfo, RepeatMode, TimelineProps};
+#[cfg(windows)]
+use crate::utils::{self, win_to_napi_err};
+
+// 对单个媒体会话的抽象，控制逻辑只依赖此 trait，便于在没有真实 Windows 会话时测试
+pub trait MediaSession {
+  fn source_app_id(&self) -> Result<String>;
+  fn session_key(&self) -> Result<String>;
+  // 以下方法可能需要等待播放器响应，timeout 为每个异步操作的最长等待时间
+  fn media_info(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaInfo>>;
+  fn media_props(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaProps>>;
+  fn playback_info(&self, timeout: Duration) -> Result<Option<PlaybackInfo>>;
+  fn try_play(&self, timeout: Duration) -> Result<bool>;
+  fn try_pause(&self, timeout: Duration) -> Result<bool>;
+  fn try_toggle_play_pause(&self, timeout: Duration) -> Result<bool>;
+  fn try_stop(&self, timeout: Duration) -> Result<bool>;
+  fn try_skip_next(&self, timeout: Duration) -> Result<bool>;
+  fn try_skip_previous(&self, timeout: Duration) -> Result<bool>;
+  fn is_seek_enabled(&self, timeout: Duration) -> Result<bool>;
+  fn timeline_props(&self, timeout: Duration) -> Result<TimelineProps>;
+  // position 为目标位置（秒）
+  fn try_change_playback_position(&self, position: f64, timeout: Duration) -> Result<bool>;
+  fn try_change_shuffle_active(&self, active: bool, timeout: Duration) -> Result<bool>;
+  fn try_change_auto_repeat_mode(&self, mode: RepeatMode, timeout: Duration) -> Result<bool>;
+  fn try_change_playback_rate(&self, rate: f64, timeout: Duration) -> Result<bool>;
+  // 只接受媒体属性、播放信息与时间线三种会话事件
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken>;
+  fn unsubscribe(&self, token: EventToken);
+}
+
+// 让 SharedSession 可以直接传给以 MediaSession 为泛型参数的函数
+impl<T: MediaSession + ?Sized> MediaSession for Arc<T> {
+  fn source_app_id(&self) -> Result<String> {
+    (**self).source_app_id()
+  }
+
+  fn session_key(&self) -> Result<String> {
+    (**self).session_key()
+  }
+
+  fn media_info(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaInfo>> {
+    (**self).media_info(thumbnail, timeout)
+  }
+
+  fn media_props(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaProps>> {
+    (**self).media_props(thumbnail, timeout)
+  }
+
+  fn playback_info(&self, timeout: Duration) -> Result<Option<PlaybackInfo>> {
+    (**self).playback_info(timeout)
+  }
+
+  fn try_play(&self, timeout: Duration) -> Result<bool> {
+    (**self).try_play(timeout)
+  }
+
+  fn try_pause(&self, timeout: Duration) -> Result<bool> {
+    (**self).try_pause(timeout)
+  }
+
+  fn try_toggle_play_pause(&self, timeout: Duration) -> Result<bool> {
+    (**self).try_toggle_play_pause(timeout)
+  }
+
+  fn try_stop(&self, timeout: Duration) -> Result<bool> {
+    (**self).try_stop(timeout)
+  }
+
+  fn try_skip_next(&self, timeout: Duration) -> Result<bool> {
+    (**self).try_skip_next(timeout)
+  }
+
+  fn try_skip_previous(&self, timeout: Duration) -> Result<bool> {
+    (**self).try_skip_previous(timeout)
+  }
+
+  fn is_seek_enabled(&self, timeout: Duration) -> Result<bool> {
+    (**self).is_seek_enabled(timeout)
+  }
+
+  fn timeline_props(&self, timeout: Duration) -> Result<TimelineProps> {
+    (**self).timeline_props(timeout)
+  }
+
+  fn try_change_playback_position(&self, position: f64, timeout: Duration) -> Result<bool> {
+    (**self).try_change_playback_position(position, timeout)
+  }
+
+  fn try_change_shuffle_active(&self, active: bool, timeout: Duration) -> Result<bool> {
+    (**self).try_change_shuffle_active(active, timeout)
+  }
+
+  fn try_change_auto_repeat_mode(&self, mode: RepeatMode, timeout: Duration) -> Result<bool> {
+    (**self).try_change_auto_repeat_mode(mode, timeout)
+  }
+
+  fn try_change_playback_rate(&self, rate: f64, timeout: Duration) -> Result<bool> {
+    (**self).try_change_playback_rate(rate, timeout)
+  }
+
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+    (**self).subscribe(event, handler)
+  }
+
+  fn unsubscribe(&self, token: EventToken) {
+    (**self).unsubscribe(token)
+  }
+}
+
+#[cfg(windows)]
+impl MediaSession for GlobalSystemMediaTransportControlsSession {
+  fn source_app_id(&self) -> Result<String> {
+    win_to_napi_err(self.SourceAppUserModelId()).map(|id| id.to_string())
+  }
+
+  fn session_key(&self) -> Result<String> {
+    self
+      .source_app_id()
+      .map(|id| session_key::session_key(self, &id))
+  }
+
+  fn media_info(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaInfo>> {
+    utils::get_media_info_for_session(self, thumbnail, timeout)
+  }
+
+  fn media_props(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaProps>> {
+    utils::get_media_props_for_session(self, thumbnail, timeout)
+  }
+
+  fn playback_info(&self, _timeout: Duration) -> Result<Option<PlaybackInfo>> {
+    utils::get_playback_info_for_session(self)
+  }
+
+  fn try_play(&self, timeout: Duration) -> Result<bool> {
+    send_request(self.TryPlayAsync(), timeout)
+  }
+
+  fn try_pause(&self, timeout: Duration) -> Result<bool> {
+    send_request(self.TryPauseAsync(), timeout)
+  }
+
+  fn try_toggle_play_pause(&self, timeout: Duration) -> Result<bool> {
+    send_request(self.TryTogglePlayPauseAsync(), timeout)
+  }
+
+  fn try_stop(&self, timeout: Duration) -> Result<bool> {
+    send_request(self.TryStopAsync(), timeout)
+  }
+
+  fn try_skip_next(&self, timeout: Duration) -> Result<bool> {
+    send_request(self.TrySkipNextAsync(), timeout)
+  }
+
+  fn try_skip_previous(&self, timeout: Duration) -> Result<bool> {
+    send_request(self.TrySkipPreviousAsync(), timeout)
+  }
+
+  fn is_seek_enabled(&self, _timeout: Duration) -> Result<bool> {
+    win_to_napi_err(
+      self
+        .GetPlaybackInfo()
+        .and_then(|info| info.Controls())
+        .and_then(|controls| controls.IsPlaybackPositionEnabled()),
+    )
+  }
+
+  fn timeline_props(&self, _timeout: Duration) -> Result<TimelineProps> {
+    let playback = utils::get_playback_info_for_session(self).ok().flatten();
+    utils::get_timeline_props_for_session(self, playback.as_ref())?
+      .ok_or_else(|| Error::new(Status::GenericFailure, "Failed to get timeline properties"))
+  }
+
+  fn try_change_playback_position(&self, position: f64, timeout: Duration) -> Result<bool> {
+    send_request(
+      self.TryChangePlaybackPositionAsync(utils::seconds_to_timespan(position).Duration),
+      timeout,
+    )
+  }
+
+  fn try_change_shuffle_active(&self, active: bool, timeout: Duration) -> Result<bool> {
+    send_request(self.TryChangeShuffleActiveAsync(active), timeout)
+  }
+
+  fn try_change_auto_repeat_mode(&self, mode: RepeatMode, timeout: Duration) -> Result<bool> {
+    let mode = match mode {
+      RepeatMode::NONE => MediaPlaybackAutoRepeatMode::None,
+      RepeatMode::TRACK => MediaPlaybackAutoRepeatMode::Track,
+      RepeatMode::LIST => MediaPlaybackAutoRepeatMode::List,
+      RepeatMode::UNRECOGNIZED => return Ok(false),
+    };
+
+    send_request(self.TryChangeAutoRepeatModeAsync(mode), timeout)
+  }
+
+  fn try_change_playback_rate(&self, rate: f64, timeout: Duration) -> Result<bool> {
+    send_request(self.TryChangePlaybackRateAsync(rate), timeout)
+  }
+
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+    let token = match event {
+      BackendEvent::MediaPropsChanged => {
+        self.MediaPropertiesChanged(&TypedEventHandler::new(move |_, _| {
+          handler();
+          Ok(())
+        }))
+      }
+      BackendEvent::PlaybackInfoChanged => {
+        self.PlaybackInfoChanged(&TypedEventHandler::new(move |_, _| {
+          handler();
+          Ok(())
+        }))
+      }
+      BackendEvent::TimelinePropsChanged => {
+        self.TimelinePropertiesChanged(&TypedEventHandler::new(move |_, _| {
+          handler();
+          Ok(())
+        }))
+      }
+      _ => return Err(backend::unsupported_event(event)),
+    };
+
+    win_to_napi_err(token).map(|token| EventToken {
+      event,
+      value: token.Value,
+    })
+  }
+
+  fn unsubscribe(&self, token: EventToken) {
+    let value = EventRegistrationToken { Value: token.value };
+    let _ = match token.event {
+      BackendEvent::MediaPropsChanged => self.RemoveMediaPropertiesChanged(value),
+      BackendEvent::PlaybackInfoChanged => self.RemovePlaybackInfoChanged(value),
+      BackendEvent::TimelinePropsChanged => self.RemoveTimelinePropertiesChanged(value),
+      _ => Ok(()),
+    };
+  }
+}
+
+#[cfg(windows)]
+// 播放器长时间不响应时放弃等待，避免占用线程池
+fn send_request(
+  operation: windows::core::Result<IAsyncOperation<bool>>,
+  timeout: Duration,
+) -> Result<bool> {
+  timeout::wait(
+    &win_to_napi_err(operation)?,
+    "Waiting for the player",
+    timeout,
+  )
+}
+
+#[cfg(test)]
+pub mod fake {
+  use napi::{Error, Status};
+  use std::sync::atomic::{AtomicUsize, Ordering};
+  use std::sync::Mutex;
+
+  use super::*;
+  use crate::backend::{self, Handlers};
+  use crate::types::{PlaybackStatus, PlaybackType};
+
+  // 记录收到的调用，供各模块的单元测试复用；克隆出的副本共享状态与事件处理器
+  #[derive(Clone)]
+  pub struct FakeSession {
+    pub id: String,
+    pub key: String,
+    pub accept: bool,
+    pub fail: bool,
+    pub seek_enabled: bool,
+    pub timeline: TimelineProps,
+    pub media: Arc<Mutex<MediaProps>>,
+    pub playback: Arc<Mutex<PlaybackInfo>>,
+    pub received: Arc<Mutex<Vec<String>>>,
+    // 每次读取媒体信息前调用，用于检查读取时的外部状态
+    pub on_read: Option<Arc<dyn Fn() + Send + Sync>>,
+    // 前若干次读取媒体信息时返回错误
+    pub failed_reads: Arc<AtomicUsize>,
+    handlers: Arc<Handlers>,
+  }
+
+  impl FakeSession {
+    pub fn new(id: &str) -> Self {
+      Self {
+        id: id.to_string(),
+        key: format!("{}#1", id),
+        accept: true,
+        fail: false,
+        seek_enabled: true,
+        timeline: TimelineProps::default(),
+        media: Arc::new(Mutex::new(MediaProps::default())),
+        playback: Arc::new(Mutex::new(PlaybackInfo {
+          playback_status: PlaybackStatus::PLAYING,
+          playback_type: PlaybackType::MUSIC,
+          is_shuffle_active: None,
+          auto_repeat_mode: None,
+          playback_rate: None,
+          controls: Default::default(),
+        })),
+        received: Arc::new(Mutex::new(Vec::new())),
+        on_read: None,
+        failed_reads: Arc::new(AtomicUsize::new(0)),
+        handlers: Arc::default(),
+      }
+    }
+
+    pub fn last_received(&self) -> Option<String> {
+      self.received.lock().unwrap().last().cloned()
+    }
+
+    pub fn handler_count(&self) -> usize {
+      self.handlers.count()
+    }
+
+    // 模拟播放器触发会话事件
+    pub fn emit(&self, event: BackendEvent) {
+      self.handlers.emit(event);
+    }
+
+    fn record(&self, call: String) -> Result<bool> {
+      self.received.lock().unwrap().push(call);
+      if self.fail {
+        return Err(Error::new(Status::GenericFailure, "0x80004005".to_string()));
+      }
+      Ok(self.accept)
+    }
+  }
+
+  impl MediaSession for FakeSession {
+    fn source_app_id(&self) -> Result<String> {
+      Ok(self.id.clone())
+    }
+
+    fn session_key(&self) -> Result<String> {
+      Ok(self.key.clone())
+    }
+
+    fn media_info(
+      &self,
+      thumbnail: &ThumbnailSettings,
+      timeout: Duration,
+    ) -> Result<Option<MediaInfo>> {
+      if let Some(on_read) = &self.on_read {
+        on_read();
+      }
+
+      let failing = self
+        .failed_reads
+        .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+        .is_ok();
+      if failing {
+        return Err(Error::new(Status::GenericFailure, "0x80004005".to_string()));
+      }
+
+      Ok(Some(MediaInfo {
+        source_app_id: self.id.clone(),
+        session_key: self.key.clone(),
+        media: self.media_props(thumbnail, timeout)?.unwrap_or_default(),
+        playback: self.playback.lock().unwrap().clone(),
+        timeline: self.timeline.clone(),
+        last_updated_time: 0.0,
+        error: None,
+      }))
+    }
+
+    fn media_props(
+      &self,
+      _thumbnail: &ThumbnailSettings,
+      _timeout: Duration,
+    ) -> Result<Option<MediaProps>> {
+      Ok(Some(self.media.lock().unwrap().clone()))
+    }
+
+    fn playback_info(&self, _timeout: Duration) -> Result<Option<PlaybackInfo>> {
+      Ok(Some(self.playback.lock().unwrap().clone()))
+    }
+
+    fn try_play(&self, _timeout: Duration) -> Result<bool> {
+      self.record("play".to_string())
+    }
+
+    fn try_pause(&self, _timeout: Duration) -> Result<bool> {
+      self.record("pause".to_string())
+    }
+
+    fn try_toggle_play_pause(&self, _timeout: Duration) -> Result<bool> {
+      self.record("toggle".to_string())
+    }
+
+    fn try_stop(&self, _timeout: Duration) -> Result<bool> {
+      self.record("stop".to_string())
+    }
+
+    fn try_skip_next(&self, _timeout: Duration) -> Result<bool> {
+      self.record("next".to_string())
+    }
+
+    fn try_skip_previous(&self, _timeout: Duration) -> Result<bool> {
+      self.record("previous".to_string())
+    }
+
+    fn is_seek_enabled(&self, _timeout: Duration) -> Result<bool> {
+      Ok(self.seek_enabled)
+    }
+
+    fn timeline_props(&self, _timeout: Duration) -> Result<TimelineProps> {
+      Ok(self.timeline.clone())
+    }
+
+    fn try_change_playback_position(&self, position: f64, _timeout: Duration) -> Result<bool> {
+      self.record(format!("seek:{}", position))
+    }
+
+    fn try_change_shuffle_active(&self, active: bool, _timeout: Duration) -> Result<bool> {
+      self.record(format!("shuffle:{}", active))
+    }
+
+    fn try_change_auto_repeat_mode(&self, mode: RepeatMode, _timeout: Duration) -> Result<bool> {
+      self.record(format!("repeat:{:?}", mode))
+    }
+
+    fn try_change_playback_rate(&self, rate: f64, _timeout: Duration) -> Result<bool> {
+      self.record(format!("rate:{}", rate))
+    }
+
+    fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+      if matches!(
+        event,
+        BackendEvent::SessionsChanged | BackendEvent::CurrentSessionChanged
+      ) {
+        return Err(backend::unsupported_event(event));
+      }
+
+      Ok(self.handlers.add(event, handler))
+    }
+
+    fn unsubscribe(&self, token: EventToken) {
+      self.handlers.remove(token);
+    }
+  }
+}
@@ -0,0 +1,100 @@
+use std::collections::HashMap;
+#[cfg(windows)]
+use std::sync::{Mutex, OnceLock};
+#[cfg(windows)]
+use windows::Media::Control::GlobalSystemMediaTransportControlsSession;
+
+// SourceAppUserModelId 无法区分同一应用的多个会话（例如浏览器的多个标签页），
+// 因此为每个会话对象分配一个 "<sourceAppId>#<序号>" 形式的唯一键。
+// 会话对象按 COM 标识比较，登记期间持有其引用，保证标识不会被复用。
+pub struct SessionKeys<T> {
+  entries: Vec<(T, String)>,
+  counters: HashMap<String, u32>,
+}
+
+impl<T: PartialEq + Clone> SessionKeys<T> {
+  pub fn new() -> Self {
+    Self {
+      entries: Vec::new(),
+      counters: HashMap::new(),
+    }
+  }
+
+  pub fn key_for(&mut self, session: &T, source_app_id: &str) -> String {
+    if let Some((_, key)) = self.entries.iter().find(|(s, _)| s == session) {
+      return key.clone();
+    }
+
+    let counter = self.counters.entry(source_app_id.to_string()).or_insert(0);
+    *counter += 1;
+
+    let key = format!("{}#{}", source_app_id, counter);
+    self.entries.push((session.clone(), key.clone()));
+    key
+  }
+
+  // 丢弃已不存在的会话
+  pub fn retain(&mut self, alive: &[T]) {
+    self.entries.retain(|(session, _)| alive.contains(session));
+  }
+}
+
+#[cfg(windows)]
+type Session = GlobalSystemMediaTransportControlsSession;
+
+#[cfg(windows)]
+fn registry() -> &'static Mutex<SessionKeys<Session>> {
+  static REGISTRY: OnceLock<Mutex<SessionKeys<Session>>> = OnceLock::new();
+  REGISTRY.get_or_init(|| Mutex::new(SessionKeys::new()))
+}
+
+#[cfg(windows)]
+pub fn session_key(session: &Session, source_app_id: &str) -> String {
+  registry()
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .key_for(session, source_app_id)
+}
+
+#[cfg(windows)]
+pub fn retain_session_keys(alive: &[Session]) {
+  registry()
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .retain(alive);
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn distinguishes_sessions_from_the_same_app() {
+    let mut keys = SessionKeys::new();
+
+    assert_eq!(keys.key_for(&1, "chrome.exe"), "chrome.exe#1");
+    assert_eq!(keys.key_for(&2, "chrome.exe"), "chrome.exe#2");
+    assert_eq!(keys.key_for(&3, "player.exe"), "player.exe#1");
+  }
+
+  #[test]
+  fn keeps_keys_stable_for_known_sessions() {
+    let mut keys = SessionKeys::new();
+
+    let first = keys.key_for(&1, "chrome.exe");
+    keys.key_for(&2, "chrome.exe");
+    assert_eq!(keys.key_for(&1, "chrome.exe"), first);
+  }
+
+  #[test]
+  fn never_reuses_keys_of_removed_sessions() {
+    let mut keys = SessionKeys::new();
+
+    keys.key_for(&1, "chrome.exe");
+    keys.key_for(&2, "chrome.exe");
+    keys.retain(&[2]);
+
+    assert_eq!(keys.key_for(&2, "chrome.exe"), "chrome.exe#2");
+    assert_eq!(keys.key_for(&1, "chrome.exe"), "chrome.exe#3");
+  }
+}
@@ -1,198 +1,1161 @@
-use napi::threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode};
-use std::collections::HashMap;
-use windows::Foundation::{EventRegistrationToken, TypedEventHandler};
-use windows::Media::Control::GlobalSystemMediaTransportControlsSession;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::Duration;
 
-use crate::monitor::{MediaPropsCallbackData, PlaybackInfoCallbackData, TimelinePropsCallbackData};
-use crate::types::MediaInfo;
+use crate::backend::{BackendEvent, EventToken, MediaBackend, SharedBackend, SharedSession};
+use crate::monitor::{
+  MediaPropsCallbackData, MonitorState, PlaybackInfoCallbackData, SessionCallbackData,
+  SessionUpdatedCallbackData, TimelinePropsCallbackData,
+};
+use crate::session::MediaSession;
+use crate::thumbnail::ThumbnailSettings;
+use crate::timeout::DEFAULT_TIMEOUT;
+use crate::types::{MediaInfo, MediaProps, PlaybackInfo, TimelineProps};
 use crate::utils;
 
-#[allow(dead_code)]
 pub struct InnerSession {
-  pub session: GlobalSystemMediaTransportControlsSession,
-  pub callbacks: Vec<ThreadsafeFunction<String>>,
-  pub media_props_token: Option<EventRegistrationToken>,
-  pub playback_info_token: Option<EventRegistrationToken>,
-  pub timeline_props_token: Option<EventRegistrationToken>,
+  pub source_app_id: String,
+  pub session: SharedSession,
+  pub tokens: Vec<EventToken>,
+  // 会话的最新完整状态，随属性变化事件更新；初次读取失败时为 None
+  pub info: Option<MediaInfo>,
+}
+
+impl InnerSession {
+  // 移除该会话上注册的所有监听器
+  fn detach(&self) {
+    for token in &self.tokens {
+      self.session.unsubscribe(*token);
+    }
+  }
+}
+
+// 回调统一包装成闭包，事件分发不依赖具体的 JS 线程安全函数
+pub type EventCallback<T> = Arc<dyn Fn(T) + Send + Sync>;
+
+pub fn dispatch<T: Clone>(callbacks: &[EventCallback<T>], data: T) {
+  for callback in callbacks {
+    callback(data.clone());
+  }
+}
+
+// 带订阅 id 的回调列表，可以单独移除某一个回调
+pub struct CallbackList<T> {
+  entries: Vec<(u32, EventCallback<T>)>,
+}
+
+impl<T> Default for CallbackList<T> {
+  fn default() -> Self {
+    Self {
+      entries: Vec::new(),
+    }
+  }
+}
+
+impl<T: Clone> CallbackList<T> {
+  pub fn push(&mut self, id: u32, callback: EventCallback<T>) {
+    self.entries.push((id, callback));
+  }
+
+  pub fn remove(&mut self, id: u32) -> bool {
+    let len = self.entries.len();
+    self.entries.retain(|(entry_id, _)| *entry_id != id);
+    self.entries.len() != len
+  }
+
+  pub fn clear(&mut self) {
+    self.entries.clear();
+  }
+
+  pub fn snapshot(&self) -> Vec<EventCallback<T>> {
+    self
+      .entries
+      .iter()
+      .map(|(_, callback)| callback.clone())
+      .collect()
+  }
 }
 
 pub struct SessionManager {
+  // 以会话键（而非 sourceAppId）索引，同一应用的多个会话互不覆盖
   pub sessions: HashMap<String, InnerSession>,
-  pub session_added_callbacks: Vec<ThreadsafeFunction<MediaInfo>>,
-  pub session_removed_callbacks: Vec<ThreadsafeFunction<String>>,
-  pub media_props_callbacks: Vec<ThreadsafeFunction<MediaPropsCallbackData>>,
-  pub playback_info_callbacks: Vec<ThreadsafeFunction<PlaybackInfoCallbackData>>,
-  pub timeline_props_callbacks: Vec<ThreadsafeFunction<TimelinePropsCallbackData>>,
-  pub current_session_changed_callbacks: Vec<ThreadsafeFunction<String>>,
+  pub session_added_callbacks: CallbackList<MediaInfo>,
+  pub session_removed_callbacks: CallbackList<SessionCallbackData>,
+  pub media_props_callbacks: CallbackList<MediaPropsCallbackData>,
+  pub playback_info_callbacks: CallbackList<PlaybackInfoCallbackData>,
+  pub timeline_props_callbacks: CallbackList<TimelinePropsCallbackData>,
+  pub current_session_changed_callbacks: CallbackList<SessionCallbackData>,
+  pub session_updated_callbacks: CallbackList<SessionUpdatedCallbackData>,
+  pub current_session_key: Option<String>,
+  pub thumbnail_settings: ThumbnailSettings,
+  // 读取会话状态时每个异步操作的超时
+  pub timeout: Duration,
+  // 缩略图未变化时，媒体属性事件中不再重复携带图片数据
+  pub omit_unchanged_thumbnail: bool,
+  next_subscription_id: u32,
 }
 
 impl SessionManager {
-  pub fn new() -> Self {
+  pub fn new(thumbnail_settings: ThumbnailSettings) -> Self {
     Self {
       sessions: HashMap::new(),
-      session_added_callbacks: Vec::new(),
-      session_removed_callbacks: Vec::new(),
-      media_props_callbacks: Vec::new(),
-      playback_info_callbacks: Vec::new(),
-      timeline_props_callbacks: Vec::new(),
-      current_session_changed_callbacks: Vec::new(),
+      session_added_callbacks: CallbackList::default(),
+      session_removed_callbacks: CallbackList::default(),
+      media_props_callbacks: CallbackList::default(),
+      playback_info_callbacks: CallbackList::default(),
+      timeline_props_callbacks: CallbackList::default(),
+      current_session_changed_callbacks: CallbackList::default(),
+      session_updated_callbacks: CallbackList::default(),
+      current_session_key: None,
+      thumbnail_settings,
+      timeout: DEFAULT_TIMEOUT,
+      omit_unchanged_thumbnail: false,
+      next_subscription_id: 0,
     }
   }
 
-  pub fn clear_all_sessions(&mut self) {
-    for session_data in self.sessions.values_mut() {
-      // 移除所有监听器
-      Self::remove_event_token(
-        &session_data.session,
-        session_data.media_props_token.take(),
-        |session, token| session.RemoveMediaPropertiesChanged(token),
-      );
+  // 注册回调并返回订阅 id，id 在所有事件类型之间唯一
+  pub fn subscribe<T: Clone>(
+    &mut self,
+    select: fn(&mut SessionManager) -> &mut CallbackList<T>,
+    callback: EventCallback<T>,
+  ) -> u32 {
+    self.next_subscription_id += 1;
+    let id = self.next_subscription_id;
+    select(self).push(id, callback);
+    id
+  }
 
-      Self::remove_event_token(
-        &session_data.session,
-        session_data.playback_info_token.take(),
-        |session, token| session.RemovePlaybackInfoChanged(token),
-      );
+  // 移除指定订阅，回调随之被释放；id 不存在时返回 false
+  pub fn unsubscribe(&mut self, id: u32) -> bool {
+    self.session_added_callbacks.remove(id)
+      || self.session_removed_callbacks.remove(id)
+      || self.media_props_callbacks.remove(id)
+      || self.playback_info_callbacks.remove(id)
+      || self.timeline_props_callbacks.remove(id)
+      || self.current_session_changed_callbacks.remove(id)
+      || self.session_updated_callbacks.remove(id)
+  }
 
-      Self::remove_event_token(
-        &session_data.session,
-        session_data.timeline_props_token.take(),
-        |session, token| session.RemoveTimelinePropertiesChanged(token),
-      );
+  pub fn clear_all_callbacks(&mut self) {
+    self.session_added_callbacks.clear();
+    self.session_removed_callbacks.clear();
+    self.media_props_callbacks.clear();
+    self.playback_info_callbacks.clear();
+    self.timeline_props_callbacks.clear();
+    self.current_session_changed_callbacks.clear();
+    self.session_updated_callbacks.clear();
+  }
+
+  pub fn clear_all_sessions(&mut self) {
+    for session_data in self.sessions.values() {
+      session_data.detach();
     }
 
     self.sessions.clear();
+    self.current_session_key = None;
   }
 
-  // 辅助方法，用于移除之前监听器注册出的令牌
-  fn remove_event_token<F>(
-    session: &GlobalSystemMediaTransportControlsSession,
-    token: Option<EventRegistrationToken>,
-    remove_fn: F,
-  ) where
-    F: FnOnce(
-      &GlobalSystemMediaTransportControlsSession,
-      EventRegistrationToken,
-    ) -> windows::core::Result<()>,
-  {
-    if let Some(token) = token {
-      let _ = remove_fn(session, token);
+  pub fn remove_session(&mut self, key: &str) -> Option<InnerSession> {
+    let session = self.sessions.remove(key)?;
+    session.detach();
+    if self.current_session_key.as_deref() == Some(key) {
+      self.current_session_key = None;
+    }
+    Some(session)
+  }
+
+  fn session_settings(&self) -> SessionSettings {
+    SessionSettings {
+      thumbnail: self.thumbnail_settings,
+      timeout: self.timeout,
+      omit_unchanged_thumbnail: self.omit_unchanged_thumbnail,
+    }
+  }
+
+  // 所有已登记会话的快照，按会话键排序
+  pub fn state(&self) -> MonitorState {
+    let mut sessions: Vec<MediaInfo> = self
+      .sessions
+      .values()
+      .filter_map(|session| session.info.clone())
+      .collect();
+    sessions.sort_by(|a, b| a.session_key.cmp(&b.session_key));
+
+    MonitorState {
+      sessions,
+      current_session_key: self.current_session_key.clone(),
     }
   }
 }
 
-pub fn register_session(
-  inner: &mut SessionManager,
-  id: String,
-  session: GlobalSystemMediaTransportControlsSession,
-) {
-  // 媒体属性变化
-  let media_props_token =
-    register_media_props_handler(&session, &inner.media_props_callbacks, id.clone());
+// 读取新会话状态时使用的设置，在释放锁之后读取
+#[derive(Clone, Copy)]
+struct SessionSettings {
+  thumbnail: ThumbnailSettings,
+  timeout: Duration,
+  omit_unchanged_thumbnail: bool,
+}
+
+// 读取会话的媒体信息并注册监听器，不需要持有管理器的锁；
+// 由调用方登记到管理器，并在释放锁之后分发 session-added 事件
+fn prepare_session(
+  shared: &Arc<Mutex<SessionManager>>,
+  settings: SessionSettings,
+  key: String,
+  source_app_id: String,
+  session: SharedSession,
+) -> InnerSession {
+  // 处理器只持有弱引用，避免会话与管理器之间循环引用
+  let manager = Arc::downgrade(shared);
+  let SessionSettings {
+    thumbnail: thumbnail_settings,
+    timeout,
+    ..
+  } = settings;
+
+  let media_info = session
+    .media_info(&thumbnail_settings, timeout)
+    .ok()
+    .flatten();
+
+  // 媒体属性变化，session-added 已携带的缩略图不再重复发送
+  let thumbnail = ThumbnailTracker {
+    enabled: settings.omit_unchanged_thumbnail,
+    last_hash: Mutex::new(
+      media_info
+        .as_ref()
+        .and_then(|info| info.media.thumbnail_hash.clone()),
+    ),
+  };
+  // 初次读取失败时，在之后的事件中补读快照
+  let fill = Arc::new({
+    let manager = manager.clone();
+    let key = key.clone();
+    move |session: &dyn MediaSession| {
+      fill_snapshot(&manager, &key, session, &thumbnail_settings, timeout)
+    }
+  });
+
+  let media_props = media_props_dispatcher(
+    manager.clone(),
+    key.clone(),
+    source_app_id.clone(),
+    thumbnail,
+  );
+  let fill_media = fill.clone();
+  let media_props_token = watch(&session, BackendEvent::MediaPropsChanged, move |session| {
+    fill_media(session);
+    if let Ok(Some(props)) = session.media_props(&thumbnail_settings, timeout) {
+      media_props(props);
+    }
+  });
 
   // 播放信息变化
-  let playback_token =
-    register_playback_info_handler(&session, &inner.playback_info_callbacks, id.clone());
+  let playback_info = playback_info_dispatcher(manager.clone(), key.clone(), source_app_id.clone());
+  let fill_playback = fill.clone();
+  let playback_token = watch(
+    &session,
+    BackendEvent::PlaybackInfoChanged,
+    move |session| {
+      fill_playback(session);
+      if let Ok(Some(info)) = session.playback_info(timeout) {
+        playback_info(info);
+      }
+    },
+  );
 
   // 时间线变化
-  let timeline_token =
-    register_timeline_props_handler(&session, &inner.timeline_props_callbacks, id.clone());
-
-  inner.sessions.insert(
-    id.clone(),
-    InnerSession {
-      session: session.clone(),
-      callbacks: Vec::new(),
-      media_props_token,
-      playback_info_token: playback_token,
-      timeline_props_token: timeline_token,
+  let timeline_props = timeline_props_dispatcher(manager, key, source_app_id.clone());
+  let timeline_token = watch(
+    &session,
+    BackendEvent::TimelinePropsChanged,
+    move |session| {
+      fill(session);
+      if let Ok(props) = session.timeline_props(timeout) {
+        timeline_props(props);
+      }
     },
   );
 
-  if let Ok(Some(media_info)) = utils::get_media_info_for_session(&session) {
-    for callback in &inner.session_added_callbacks {
-      callback.call(Ok(media_info.clone()), ThreadsafeFunctionCallMode::Blocking);
-    }
+  InnerSession {
+    source_app_id,
+    session,
+    tokens: [media_props_token, playback_token, timeline_token]
+      .into_iter()
+      .flatten()
+      .collect(),
+    info: media_info,
   }
 }
 
-fn register_media_props_handler(
-  session: &GlobalSystemMediaTransportControlsSession,
-  callbacks: &[ThreadsafeFunction<MediaPropsCallbackData>],
-  id: String,
-) -> Option<EventRegistrationToken> {
-  let media_session_clone = session.clone();
-  let media_props_callbacks = callbacks.to_vec();
+// 初次读取失败的会话没有快照，也没有分发 session-added；
+// 之后的事件中在锁外重新读取，成功后登记快照并补发 session-added
+fn fill_snapshot(
+  manager: &Weak<Mutex<SessionManager>>,
+  key: &str,
+  session: &dyn MediaSession,
+  thumbnail: &ThumbnailSettings,
+  timeout: Duration,
+) {
+  let manager = match manager.upgrade() {
+    Some(manager) => manager,
+    None => return,
+  };
+
+  let missing = match manager.lock() {
+    Ok(inner) => inner
+      .sessions
+      .get(key)
+      .is_some_and(|session| session.info.is_none()),
+    Err(_) => return,
+  };
+  if !missing {
+    return;
+  }
+
+  let media_info = match session.media_info(thumbnail, timeout) {
+    Ok(Some(info)) => info,
+    _ => return,
+  };
+
+  // 读取期间其它事件可能已补读了快照，此时不再重复分发
+  let callbacks = {
+    let mut inner = match manager.lock() {
+      Ok(inner) => inner,
+      Err(_) => return,
+    };
+    match inner.sessions.get_mut(key) {
+      Some(session) if session.info.is_none() => session.info = Some(media_info.clone()),
+      _ => return,
+    }
+    inner.session_added_callbacks.snapshot()
+  };
 
+  dispatch(&callbacks, media_info);
+}
+
+// 事件发生时重新读取会话状态；处理器只持有会话的弱引用，会话被移除后不再响应
+fn watch(
+  session: &SharedSession,
+  event: BackendEvent,
+  on_event: impl Fn(&dyn MediaSession) + Send + Sync + 'static,
+) -> Option<EventToken> {
+  let weak = Arc::downgrade(session);
   session
-    .MediaPropertiesChanged(&TypedEventHandler::new(move |_, _| {
-      if let Ok(Some(media_props)) = utils::get_media_props_for_session(&media_session_clone) {
-        for callback in &media_props_callbacks {
-          callback.call(
-            Ok(MediaPropsCallbackData {
-              source_app_id: id.clone(),
-              media_props: media_props.clone(),
-            }),
-            ThreadsafeFunctionCallMode::Blocking,
-          );
+    .subscribe(
+      event,
+      Arc::new(move || {
+        if let Some(session) = weak.upgrade() {
+          on_event(&*session);
         }
-      }
-      Ok(())
-    }))
+      }),
+    )
     .ok()
 }
 
-fn register_playback_info_handler(
-  session: &GlobalSystemMediaTransportControlsSession,
-  callbacks: &[ThreadsafeFunction<PlaybackInfoCallbackData>],
-  id: String,
-) -> Option<EventRegistrationToken> {
-  let playback_session_clone = session.clone();
-  let playback_info_callbacks = callbacks.to_vec();
+// 对比后端与已登记的会话：登记新出现的会话、注销已消失的会话并分发相应事件
+pub fn sync_sessions(backend: &dyn MediaBackend, shared: &Arc<Mutex<SessionManager>>) {
+  let sessions = backend.sessions();
 
-  session
-    .PlaybackInfoChanged(&TypedEventHandler::new(move |_, _| {
-      if let Ok(Some(playback_info)) = utils::get_playback_info_for_session(&playback_session_clone)
-      {
-        for callback in &playback_info_callbacks {
-          callback.call(
-            Ok(PlaybackInfoCallbackData {
-              source_app_id: id.clone(),
-              playback_info: playback_info.clone(),
-            }),
-            ThreadsafeFunctionCallMode::Blocking,
-          );
-        }
+  let (known_keys, settings) = match shared.lock() {
+    Ok(inner) => (
+      inner.sessions.keys().cloned().collect::<HashSet<_>>(),
+      inner.session_settings(),
+    ),
+    Err(_) => return,
+  };
+
+  // 在锁外读取新会话的状态，响应缓慢的播放器不会阻塞其它会话的事件
+  let mut current_keys = Vec::new();
+  let mut prepared = Vec::new();
+  for session in sessions {
+    let (source_app_id, key) = match (session.source_app_id(), session.session_key()) {
+      (Ok(id), Ok(key)) => (id, key),
+      _ => continue,
+    };
+    current_keys.push(key.clone());
+
+    if !known_keys.contains(&key) {
+      let session = prepare_session(shared, settings, key.clone(), source_app_id, session);
+      prepared.push((key, session));
+    }
+  }
+
+  let mut inner = match shared.lock() {
+    Ok(inner) => inner,
+    Err(_) => {
+      for (_, session) in prepared {
+        session.detach();
       }
-      Ok(())
-    }))
-    .ok()
+      return;
+    }
+  };
+
+  let mut added = Vec::new();
+  let mut removed = Vec::new();
+
+  // 读取期间其它同步可能已登记了同一会话，此时丢弃重复注册的监听器
+  for (key, session) in prepared {
+    if inner.sessions.contains_key(&key) {
+      session.detach();
+      continue;
+    }
+    added.extend(session.info.clone());
+    inner.sessions.insert(key, session);
+  }
+
+  // 处理已移除的会话
+  let removed_keys: Vec<_> = inner
+    .sessions
+    .keys()
+    .filter(|key| !current_keys.contains(key))
+    .cloned()
+    .collect();
+
+  for key in removed_keys {
+    if let Some(session) = inner.remove_session(&key) {
+      removed.push(SessionCallbackData {
+        source_app_id: session.source_app_id,
+        session_key: key,
+      });
+    }
+  }
+
+  // 释放锁之后再分发事件，避免回调阻塞其它后端事件
+  let added_callbacks = inner.session_added_callbacks.snapshot();
+  let removed_callbacks = inner.session_removed_callbacks.snapshot();
+  drop(inner);
+
+  for media_info in added {
+    dispatch(&added_callbacks, media_info);
+  }
+
+  for data in removed {
+    dispatch(&removed_callbacks, data);
+  }
 }
 
-fn register_timeline_props_handler(
-  session: &GlobalSystemMediaTransportControlsSession,
-  callbacks: &[ThreadsafeFunction<TimelinePropsCallbackData>],
-  id: String,
-) -> Option<EventRegistrationToken> {
-  let timeline_session_clone = session.clone();
-  let timeline_props_callbacks = callbacks.to_vec();
+pub fn current_session_changed(backend: &dyn MediaBackend, shared: &Arc<Mutex<SessionManager>>) {
+  let current_session = match backend.current_session() {
+    Some(session) => session,
+    None => {
+      if let Ok(mut inner) = shared.lock() {
+        inner.current_session_key = None;
+      }
+      return;
+    }
+  };
 
-  session
-    .TimelinePropertiesChanged(&TypedEventHandler::new(move |_, _| {
-      if let Ok(Some(timeline_props)) =
-        utils::get_timeline_props_for_session(&timeline_session_clone)
-      {
-        for callback in &timeline_props_callbacks {
-          callback.call(
-            Ok(TimelinePropsCallbackData {
-              source_app_id: id.clone(),
-              timeline_props: timeline_props.clone(),
-            }),
-            ThreadsafeFunctionCallMode::Blocking,
-          );
+  let (source_app_id, key) = match (
+    current_session.source_app_id(),
+    current_session.session_key(),
+  ) {
+    (Ok(id), Ok(key)) => (id, key),
+    _ => return,
+  };
+
+  let callbacks = match shared.lock() {
+    Ok(mut inner) => {
+      inner.current_session_key = Some(key.clone());
+      inner.current_session_changed_callbacks.snapshot()
+    }
+    Err(_) => return,
+  };
+
+  dispatch(
+    &callbacks,
+    SessionCallbackData {
+      source_app_id,
+      session_key: key,
+    },
+  );
+}
+
+type BackendHandler = fn(&dyn MediaBackend, &Arc<Mutex<SessionManager>>);
+
+// 登记已有会话并监听会话列表与当前会话的变化，返回的令牌在销毁时交给 detach
+pub fn attach(
+  backend: &SharedBackend,
+  shared: &Arc<Mutex<SessionManager>>,
+) -> napi::Result<Vec<EventToken>> {
+  sync_sessions(&**backend, shared);
+
+  let handlers: [(BackendEvent, BackendHandler); 2] = [
+    (BackendEvent::SessionsChanged, sync_sessions),
+    (BackendEvent::CurrentSessionChanged, current_session_changed),
+  ];
+
+  let mut tokens = Vec::new();
+  for (event, handle) in handlers {
+    let weak_backend = Arc::downgrade(backend);
+    let manager = Arc::downgrade(shared);
+    let token = backend.subscribe(
+      event,
+      Arc::new(move || {
+        if let (Some(backend), Some(manager)) = (weak_backend.upgrade(), manager.upgrade()) {
+          handle(&*backend, &manager);
         }
+      }),
+    );
+
+    match token {
+      Ok(token) => tokens.push(token),
+      Err(err) => {
+        detach(&**backend, tokens);
+        return Err(err);
       }
-      Ok(())
-    }))
-    .ok()
+    }
+  }
+
+  Ok(tokens)
+}
+
+pub fn detach(backend: &dyn MediaBackend, tokens: Vec<EventToken>) {
+  for token in tokens {
+    backend.unsubscribe(token);
+  }
+}
+
+// 记录上一次发送的缩略图哈希
+struct ThumbnailTracker {
+  enabled: bool,
+  last_hash: Mutex<Option<String>>,
+}
+
+impl ThumbnailTracker {
+  // 返回 true 表示本次事件可以省略缩略图数据
+  fn is_unchanged(&self, hash: Option<&str>) -> bool {
+    if !self.enabled {
+      return false;
+    }
+
+    let mut last_hash = self.last_hash.lock().unwrap_or_else(|e| e.into_inner());
+    if hash.is_some() && last_hash.as_deref() == hash {
+      return true;
+    }
+
+    *last_hash = hash.map(str::to_string);
+    false
+  }
+}
+
+// 在事件发生时才读取回调列表，initialize() 之后注册的回调同样能收到已有会话的事件
+fn emit<T: Clone>(
+  manager: &Weak<Mutex<SessionManager>>,
+  select: fn(&SessionManager) -> &CallbackList<T>,
+  data: T,
+) {
+  let manager = match manager.upgrade() {
+    Some(manager) => manager,
+    None => return,
+  };
+
+  // 先复制回调列表再释放锁，回调执行期间不阻塞注册与销毁
+  let callbacks = match manager.lock() {
+    Ok(inner) => select(&inner).snapshot(),
+    Err(_) => return,
+  };
+
+  dispatch(&callbacks, data);
+}
+
+// 逐个比较顶层字段，返回发生变化的字段路径，例如 "media.title"
+fn changed_fields<T: Serialize>(prefix: &str, old: &T, new: &T, ignored: &[&str]) -> Vec<String> {
+  let (Ok(Value::Object(old)), Ok(Value::Object(new))) =
+    (serde_json::to_value(old), serde_json::to_value(new))
+  else {
+    return Vec::new();
+  };
+
+  new
+    .iter()
+    .filter(|(name, value)| !ignored.contains(&name.as_str()) && old.get(*name) != Some(*value))
+    .map(|(name, _)| format!("{prefix}.{name}"))
+    .collect()
+}
+
+type SessionUpdate = (
+  Vec<EventCallback<SessionUpdatedCallbackData>>,
+  SessionUpdatedCallbackData,
+);
+
+// 在锁内更新会话快照；没有字段变化时返回 None，不分发 session-updated
+fn update_snapshot(
+  manager: &Weak<Mutex<SessionManager>>,
+  key: &str,
+  apply: impl FnOnce(&mut MediaInfo) -> Vec<String>,
+) -> Option<SessionUpdate> {
+  let manager = manager.upgrade()?;
+  let mut inner = manager.lock().ok()?;
+  let omit_unchanged_thumbnail = inner.omit_unchanged_thumbnail;
+  let info = inner.sessions.get_mut(key)?.info.as_mut()?;
+
+  let changed_fields = apply(info);
+  if changed_fields.is_empty() {
+    return None;
+  }
+  info.last_updated_time = utils::now_millis();
+
+  let mut media_info = info.clone();
+  if omit_unchanged_thumbnail && !changed_fields.iter().any(|f| f == "media.thumbnailHash") {
+    media_info.media.thumbnail = None;
+  }
+
+  let data = SessionUpdatedCallbackData {
+    source_app_id: media_info.source_app_id.clone(),
+    session_key: key.to_string(),
+    media_info,
+    changed_fields,
+  };
+  Some((inner.session_updated_callbacks.snapshot(), data))
+}
+
+fn media_props_dispatcher(
+  manager: Weak<Mutex<SessionManager>>,
+  key: String,
+  source_app_id: String,
+  thumbnail: ThumbnailTracker,
+) -> impl Fn(MediaProps) + Send + Sync + 'static {
+  move |mut media_props| {
+    let updated = update_snapshot(&manager, &key, |info| {
+      let changed = changed_fields("media", &info.media, &media_props, &[]);
+      info.media = media_props.clone();
+      changed
+    });
+
+    if thumbnail.is_unchanged(media_props.thumbnail_hash.as_deref()) {
+      media_props.thumbnail = None;
+    }
+
+    emit(
+      &manager,
+      |inner| &inner.media_props_callbacks,
+      MediaPropsCallbackData {
+        source_app_id: source_app_id.clone(),
+        session_key: key.clone(),
+        media_props,
+      },
+    );
+
+    if let Some((callbacks, data)) = updated {
+      dispatch(&callbacks, data);
+    }
+  }
+}
+
+fn playback_info_dispatcher(
+  manager: Weak<Mutex<SessionManager>>,
+  key: String,
+  source_app_id: String,
+) -> impl Fn(PlaybackInfo) + Send + Sync + 'static {
+  move |playback_info| {
+    let updated = update_snapshot(&manager, &key, |info| {
+      let changed = changed_fields("playback", &info.playback, &playback_info, &[]);
+      info.playback = playback_info.clone();
+      changed
+    });
+
+    emit(
+      &manager,
+      |inner| &inner.playback_info_callbacks,
+      PlaybackInfoCallbackData {
+        source_app_id: source_app_id.clone(),
+        session_key: key.clone(),
+        playback_info,
+      },
+    );
+
+    if let Some((callbacks, data)) = updated {
+      dispatch(&callbacks, data);
+    }
+  }
+}
+
+fn timeline_props_dispatcher(
+  manager: Weak<Mutex<SessionManager>>,
+  key: String,
+  source_app_id: String,
+) -> impl Fn(TimelineProps) + Send + Sync + 'static {
+  move |timeline_props| {
+    // 上报时间与推算位置随每次读取变化，不单独视为更新
+    let updated = update_snapshot(&manager, &key, |info| {
+      let changed = changed_fields(
+        "timeline",
+        &info.timeline,
+        &timeline_props,
+        &["lastUpdatedTime", "estimatedPosition"],
+      );
+      info.timeline = timeline_props.clone();
+      changed
+    });
+
+    emit(
+      &manager,
+      |inner| &inner.timeline_props_callbacks,
+      TimelinePropsCallbackData {
+        source_app_id: source_app_id.clone(),
+        session_key: key.clone(),
+        timeline_props,
+      },
+    );
+
+    if let Some((callbacks, data)) = updated {
+      dispatch(&callbacks, data);
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::backend::fake::FakeBackend;
+  use crate::session::fake::FakeSession;
+  use crate::types::{PlaybackStatus, PlaybackType};
+  use std::sync::atomic::Ordering;
+
+  // 模拟一个在 initialize() 时就已注册好处理器的会话，事件由测试手动触发
+  struct SimulatedSession {
+    playback_info_changed: Box<dyn Fn(PlaybackInfo)>,
+    timeline_props_changed: Box<dyn Fn(TimelineProps)>,
+  }
+
+  impl SimulatedSession {
+    fn register(shared: &Arc<Mutex<SessionManager>>, key: &str, source_app_id: &str) -> Self {
+      let manager = Arc::downgrade(shared);
+      let (key, id) = (key.to_string(), source_app_id.to_string());
+      Self {
+        playback_info_changed: Box::new(playback_info_dispatcher(
+          manager.clone(),
+          key.clone(),
+          id.clone(),
+        )),
+        timeline_props_changed: Box::new(timeline_props_dispatcher(manager, key, id)),
+      }
+    }
+  }
+
+  fn shared_manager() -> Arc<Mutex<SessionManager>> {
+    Arc::new(Mutex::new(
+      SessionManager::new(ThumbnailSettings::default()),
+    ))
+  }
+
+  fn recorder<T: Send + 'static>() -> (EventCallback<T>, Arc<Mutex<Vec<T>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let callback: EventCallback<T> = Arc::new(move |data| sink.lock().unwrap().push(data));
+    (callback, received)
+  }
+
+  fn playback_info(playback_status: PlaybackStatus) -> PlaybackInfo {
+    PlaybackInfo {
+      playback_status,
+      playback_type: PlaybackType::MUSIC,
+      is_shuffle_active: None,
+      auto_repeat_mode: None,
+      playback_rate: None,
+      controls: Default::default(),
+    }
+  }
+
+  fn timeline_props(position: f64) -> TimelineProps {
+    TimelineProps {
+      position,
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn listeners_added_after_registration_receive_events() {
+    let shared = shared_manager();
+    let session = SimulatedSession::register(&shared, "player.exe#1", "player.exe");
+
+    let (on_playback, playback) = recorder();
+    let (on_timeline, timeline) = recorder();
+    {
+      let mut inner = shared.lock().unwrap();
+      inner.subscribe(|inner| &mut inner.playback_info_callbacks, on_playback);
+      inner.subscribe(|inner| &mut inner.timeline_props_callbacks, on_timeline);
+    }
+
+    (session.playback_info_changed)(playback_info(PlaybackStatus::PLAYING));
+    (session.timeline_props_changed)(timeline_props(12.0));
+
+    let playback = playback.lock().unwrap();
+    assert_eq!(playback.len(), 1);
+    assert_eq!(playback[0].session_key, "player.exe#1");
+    assert_eq!(playback[0].source_app_id, "player.exe");
+    assert_eq!(
+      playback[0].playback_info.playback_status,
+      PlaybackStatus::PLAYING
+    );
+    assert_eq!(timeline.lock().unwrap()[0].timeline_props.position, 12.0);
+  }
+
+  #[test]
+  fn every_registered_listener_receives_events() {
+    let shared = shared_manager();
+    let (first, first_received) = recorder();
+    shared
+      .lock()
+      .unwrap()
+      .subscribe(|inner| &mut inner.timeline_props_callbacks, first);
+
+    let session = SimulatedSession::register(&shared, "player.exe#1", "player.exe");
+    (session.timeline_props_changed)(timeline_props(1.0));
+
+    let (second, second_received) = recorder();
+    shared
+      .lock()
+      .unwrap()
+      .subscribe(|inner| &mut inner.timeline_props_callbacks, second);
+    (session.timeline_props_changed)(timeline_props(2.0));
+
+    let positions = |received: &Arc<Mutex<Vec<TimelinePropsCallbackData>>>| -> Vec<f64> {
+      let received = received.lock().unwrap();
+      received
+        .iter()
+        .map(|data| data.timeline_props.position)
+        .collect()
+    };
+    assert_eq!(positions(&first_received), [1.0, 2.0]);
+    assert_eq!(positions(&second_received), [2.0]);
+  }
+
+  #[test]
+  fn events_after_manager_dropped_are_ignored() {
+    let shared = shared_manager();
+    let session = SimulatedSession::register(&shared, "player.exe#1", "player.exe");
+    drop(shared);
+
+    (session.timeline_props_changed)(timeline_props(1.0));
+  }
+
+  #[test]
+  fn unsubscribed_listener_stops_receiving_events() {
+    let shared = shared_manager();
+    let session = SimulatedSession::register(&shared, "player.exe#1", "player.exe");
+
+    let (kept, kept_received) = recorder();
+    let (removed, removed_received) = recorder();
+    let removed_id = {
+      let mut inner = shared.lock().unwrap();
+      inner.subscribe(|inner| &mut inner.timeline_props_callbacks, kept);
+      inner.subscribe(|inner| &mut inner.timeline_props_callbacks, removed)
+    };
+
+    (session.timeline_props_changed)(timeline_props(1.0));
+    assert!(shared.lock().unwrap().unsubscribe(removed_id));
+    (session.timeline_props_changed)(timeline_props(2.0));
+
+    assert_eq!(kept_received.lock().unwrap().len(), 2);
+    assert_eq!(removed_received.lock().unwrap().len(), 1);
+  }
+
+  #[test]
+  fn subscription_ids_are_unique_across_event_types() {
+    let mut inner = SessionManager::new(ThumbnailSettings::default());
+    let (playback, _) = recorder();
+    let (timeline, _) = recorder();
+    let playback_id = inner.subscribe(|inner| &mut inner.playback_info_callbacks, playback);
+    let timeline_id = inner.subscribe(|inner| &mut inner.timeline_props_callbacks, timeline);
+
+    assert_ne!(playback_id, timeline_id);
+    assert!(inner.unsubscribe(timeline_id));
+    assert!(!inner.unsubscribe(timeline_id));
+    assert!(inner.unsubscribe(playback_id));
+  }
+
+  #[test]
+  fn unsubscribe_drops_the_callback() {
+    let mut inner = SessionManager::new(ThumbnailSettings::default());
+    let (callback, _) = recorder::<TimelinePropsCallbackData>();
+    let weak = Arc::downgrade(&callback);
+    let id = inner.subscribe(|inner| &mut inner.timeline_props_callbacks, callback);
+
+    assert!(weak.upgrade().is_some());
+    inner.unsubscribe(id);
+    assert!(weak.upgrade().is_none());
+  }
+
+  #[test]
+  fn omits_only_unchanged_thumbnails() {
+    let tracker = ThumbnailTracker {
+      enabled: true,
+      last_hash: Mutex::new(Some("a".to_string())),
+    };
+
+    assert!(tracker.is_unchanged(Some("a")));
+    assert!(!tracker.is_unchanged(Some("b")));
+    assert!(tracker.is_unchanged(Some("b")));
+    assert!(!tracker.is_unchanged(None));
+    assert!(!tracker.is_unchanged(Some("b")));
+  }
+
+  #[test]
+  fn keeps_thumbnails_when_disabled() {
+    let tracker = ThumbnailTracker {
+      enabled: false,
+      last_hash: Mutex::new(Some("a".to_string())),
+    };
+
+    assert!(!tracker.is_unchanged(Some("a")));
+  }
+
+  fn attached() -> (
+    Arc<FakeBackend>,
+    Arc<Mutex<SessionManager>>,
+    Vec<EventToken>,
+  ) {
+    let backend = Arc::new(FakeBackend::default());
+    let shared = shared_manager();
+    let tokens = attach(&(backend.clone() as SharedBackend), &shared).unwrap();
+    (backend, shared, tokens)
+  }
+
+  #[test]
+  fn registers_existing_sessions_on_attach() {
+    let backend = Arc::new(FakeBackend::default());
+    backend.add(FakeSession::new("player.exe"));
+    let shared = shared_manager();
+
+    attach(&(backend.clone() as SharedBackend), &shared).unwrap();
+
+    let inner = shared.lock().unwrap();
+    assert!(inner.sessions.contains_key("player.exe#1"));
+    assert_eq!(inner.sessions["player.exe#1"].tokens.len(), 3);
+    assert_eq!(backend.handler_count(), 2);
+  }
+
+  #[test]
+  fn reads_new_sessions_without_holding_the_lock() {
+    let (backend, shared, _) = attached();
+    let locked_during_read = Arc::new(Mutex::new(None));
+
+    let mut session = FakeSession::new("player.exe");
+    let (manager, locked) = (Arc::downgrade(&shared), locked_during_read.clone());
+    session.on_read = Some(Arc::new(move || {
+      let manager = manager.upgrade().unwrap();
+      *locked.lock().unwrap() = Some(manager.try_lock().is_err());
+    }));
+    backend.add(session);
+    backend.emit(BackendEvent::SessionsChanged);
+
+    assert_eq!(*locked_during_read.lock().unwrap(), Some(false));
+    assert!(shared.lock().unwrap().sessions.contains_key("player.exe#1"));
+  }
+
+  #[test]
+  fn fills_the_snapshot_after_a_failed_first_read() {
+    let (backend, shared, _) = attached();
+    let (on_added, added) = recorder();
+    shared
+      .lock()
+      .unwrap()
+      .subscribe(|inner| &mut inner.session_added_callbacks, on_added);
+
+    let session = FakeSession::new("player.exe");
+    session.failed_reads.store(1, Ordering::SeqCst);
+    session.media.lock().unwrap().title = "Song".to_string();
+    backend.add(session.clone());
+
+    assert!(added.lock().unwrap().is_empty());
+    assert!(shared.lock().unwrap().state().sessions.is_empty());
+
+    // 下一次会话事件时补读快照，并补发 session-added
+    session.emit(BackendEvent::PlaybackInfoChanged);
+    session.emit(BackendEvent::TimelinePropsChanged);
+
+    let added = added.lock().unwrap();
+    assert_eq!(added.len(), 1);
+    assert_eq!(added[0].media.title, "Song");
+    let state = shared.lock().unwrap().state();
+    assert_eq!(state.sessions.len(), 1);
+    assert_eq!(state.sessions[0].session_key, "player.exe#1");
+  }
+
+  #[test]
+  fn dispatches_added_and_removed_sessions() {
+    let (backend, shared, _) = attached();
+    let (on_added, added) = recorder();
+    let (on_removed, removed) = recorder();
+    {
+      let mut inner = shared.lock().unwrap();
+      inner.subscribe(|inner| &mut inner.session_added_callbacks, on_added);
+      inner.subscribe(|inner| &mut inner.session_removed_callbacks, on_removed);
+    }
+
+    let session = FakeSession::new("player.exe");
+    session.media.lock().unwrap().title = "Song".to_string();
+    backend.add(session.clone());
+    // 会话列表未变化时不重复登记
+    backend.emit(BackendEvent::SessionsChanged);
+
+    {
+      let added = added.lock().unwrap();
+      assert_eq!(added.len(), 1);
+      assert_eq!(added[0].session_key, "player.exe#1");
+      assert_eq!(added[0].media.title, "Song");
+    }
+    assert_eq!(session.handler_count(), 3);
+
+    backend.remove("player.exe#1");
+
+    let removed = removed.lock().unwrap();
+    assert_eq!(removed.len(), 1);
+    assert_eq!(removed[0].session_key, "player.exe#1");
+    assert!(shared.lock().unwrap().sessions.is_empty());
+    assert_eq!(session.handler_count(), 0);
+  }
+
+  #[test]
+  fn reads_session_state_when_events_fire() {
+    let (backend, shared, _) = attached();
+    let session = FakeSession::new("player.exe");
+    backend.add(session.clone());
+
+    let (on_playback, playback) = recorder();
+    let (on_media, media) = recorder::<MediaPropsCallbackData>();
+    {
+      let mut inner = shared.lock().unwrap();
+      inner.subscribe(|inner| &mut inner.playback_info_callbacks, on_playback);
+      inner.subscribe(|inner| &mut inner.media_props_callbacks, on_media);
+    }
+
+    session.playback.lock().unwrap().playback_status = PlaybackStatus::PAUSED;
+    session.emit(BackendEvent::PlaybackInfoChanged);
+    session.media.lock().unwrap().artist = "Artist".to_string();
+    session.emit(BackendEvent::MediaPropsChanged);
+
+    let playback = playback.lock().unwrap();
+    assert_eq!(playback.len(), 1);
+    assert_eq!(playback[0].session_key, "player.exe#1");
+    assert_eq!(
+      playback[0].playback_info.playback_status,
+      PlaybackStatus::PAUSED
+    );
+    assert_eq!(media.lock().unwrap()[0].media_props.artist, "Artist");
+  }
+
+  #[test]
+  fn dispatches_current_session_changes() {
+    let (backend, shared, _) = attached();
+    backend.add(FakeSession::new("a.exe"));
+    backend.add(FakeSession::new("b.exe"));
+
+    let (on_current, current) = recorder();
+    shared.lock().unwrap().subscribe(
+      |inner| &mut inner.current_session_changed_callbacks,
+      on_current,
+    );
+
+    backend.set_current("b.exe#1");
+    // 当前会话不存在时不分发事件
+    backend.set_current("c.exe#1");
+
+    let current = current.lock().unwrap();
+    assert_eq!(current.len(), 1);
+    assert_eq!(current[0].source_app_id, "b.exe");
+  }
+
+  #[test]
+  fn detach_and_clear_remove_every_handler() {
+    let (backend, shared, tokens) = attached();
+    let session = FakeSession::new("player.exe");
+    backend.add(session.clone());
+
+    detach(&*backend, tokens);
+    shared.lock().unwrap().clear_all_sessions();
+
+    assert_eq!(backend.handler_count(), 0);
+    assert_eq!(session.handler_count(), 0);
+    backend.add(FakeSession::new("other.exe"));
+    assert!(shared.lock().unwrap().sessions.is_empty());
+  }
+
+  #[test]
+  fn reports_changed_top_level_fields() {
+    let old = timeline_props(1.0);
+    let mut new = timeline_props(1.0);
+    new.last_updated_time = Some(1_000.0);
+    let ignored = ["lastUpdatedTime", "estimatedPosition"];
+
+    assert!(changed_fields("timeline", &old, &new, &ignored).is_empty());
+
+    new.position = 2.0;
+    new.duration = 60.0;
+    assert_eq!(
+      changed_fields("timeline", &old, &new, &ignored),
+      ["timeline.duration", "timeline.position"]
+    );
+  }
+
+  #[test]
+  fn session_updated_carries_snapshot_and_skips_no_op_updates() {
+    let (backend, shared, _) = attached();
+    let session = FakeSession::new("player.exe");
+    backend.add(session.clone());
+
+    let (on_updated, updated) = recorder::<SessionUpdatedCallbackData>();
+    shared
+      .lock()
+      .unwrap()
+      .subscribe(|inner| &mut inner.session_updated_callbacks, on_updated);
+
+    // 状态未变化时不分发
+    session.emit(BackendEvent::PlaybackInfoChanged);
+    session.emit(BackendEvent::MediaPropsChanged);
+    assert!(updated.lock().unwrap().is_empty());
+
+    session.playback.lock().unwrap().playback_status = PlaybackStatus::PAUSED;
+    session.emit(BackendEvent::PlaybackInfoChanged);
+    session.media.lock().unwrap().title = "Song".to_string();
+    session.emit(BackendEvent::MediaPropsChanged);
+
+    {
+      let updated = updated.lock().unwrap();
+      assert_eq!(updated.len(), 2);
+      assert_eq!(updated[0].changed_fields, ["playback.playbackStatus"]);
+      assert_eq!(updated[1].changed_fields, ["media.title"]);
+      // 后一次事件的快照包含之前所有的变化
+      assert_eq!(
+        updated[1].media_info.playback.playback_status,
+        PlaybackStatus::PAUSED
+      );
+      assert_eq!(updated[1].media_info.media.title, "Song");
+    }
+
+    let state = shared.lock().unwrap().state();
+    assert_eq!(state.sessions.len(), 1);
+    assert_eq!(state.sessions[0].media.title, "Song");
+    assert_eq!(
+      state.sessions[0].playback.playback_status,
+      PlaybackStatus::PAUSED
+    );
+  }
+
+  #[test]
+  fn state_tracks_the_current_session() {
+    let (backend, shared, _) = attached();
+    backend.add(FakeSession::new("b.exe"));
+    backend.add(FakeSession::new("a.exe"));
+
+    backend.set_current("b.exe#1");
+    let state = shared.lock().unwrap().state();
+    let keys: Vec<_> = state
+      .sessions
+      .iter()
+      .map(|s| s.session_key.as_str())
+      .collect();
+    assert_eq!(keys, ["a.exe#1", "b.exe#1"]);
+    assert_eq!(state.current_session_key.as_deref(), Some("b.exe#1"));
+
+    backend.remove("b.exe#1");
+    let state = shared.lock().unwrap().state();
+    assert_eq!(state.sessions.len(), 1);
+    assert_eq!(state.current_session_key, None);
+  }
 }
@@ -0,0 +1,253 @@
+use napi::Result;
+use std::sync::{Mutex, OnceLock};
+#[cfg(windows)]
+use windows::Media::Control::GlobalSystemMediaTransportControlsSessionManager;
+
+#[cfg(windows)]
+use crate::media_control;
+#[cfg(target_os = "linux")]
+use crate::mpris::MprisBackend;
+#[cfg(any(windows, target_os = "linux"))]
+use crate::utils;
+#[cfg(target_os = "linux")]
+use std::sync::Arc;
+#[cfg(any(windows, target_os = "linux"))]
+use std::time::Duration;
+
+#[napi(object)]
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SessionManagerMetrics {
+  // 调用 RequestAsync 创建管理器的次数
+  pub created_count: u32,
+  // 复用已创建管理器的次数
+  pub reused_count: u32,
+  // 显式重置或因失效被丢弃的次数
+  pub reset_count: u32,
+  // 最近一次创建的时间（Unix 毫秒）
+  pub last_created_time: Option<f64>,
+}
+
+// 延迟创建、可重置的管理器缓存
+pub struct ManagerCache<M> {
+  manager: Option<M>,
+  metrics: SessionManagerMetrics,
+}
+
+impl<M> Default for ManagerCache<M> {
+  fn default() -> Self {
+    Self {
+      manager: None,
+      metrics: SessionManagerMetrics::default(),
+    }
+  }
+}
+
+impl<M: Clone + PartialEq> ManagerCache<M> {
+  fn cached(&mut self) -> Option<M> {
+    let manager = self.manager.clone()?;
+    self.metrics.reused_count += 1;
+    Some(manager)
+  }
+
+  // 创建期间其它调用可能已放入了管理器，此时复用已有的，丢弃新创建的
+  fn insert(&mut self, manager: M, now: f64) -> M {
+    if let Some(cached) = self.cached() {
+      return cached;
+    }
+
+    self.metrics.created_count += 1;
+    self.metrics.last_created_time = Some(now);
+    self.manager = Some(manager.clone());
+    manager
+  }
+
+  pub fn reset(&mut self) {
+    if self.manager.take().is_some() {
+      self.metrics.reset_count += 1;
+    }
+  }
+
+  // 只丢弃仍是当前缓存的管理器，已被替换时不做处理
+  pub fn invalidate(&mut self, manager: &M) {
+    if self.manager.as_ref() == Some(manager) {
+      self.reset();
+    }
+  }
+
+  pub fn metrics(&self) -> SessionManagerMetrics {
+    self.metrics.clone()
+  }
+}
+
+// 在锁外创建管理器，RequestAsync 等待期间不阻塞其它查询、重置与统计
+pub fn get_or_create<M: Clone + PartialEq>(
+  cache: &Mutex<ManagerCache<M>>,
+  create: impl FnOnce() -> Result<M>,
+  now: f64,
+) -> Result<M> {
+  let cached = cache.lock().unwrap_or_else(|e| e.into_inner()).cached();
+  if let Some(manager) = cached {
+    return Ok(manager);
+  }
+
+  let manager = create()?;
+  Ok(
+    cache
+      .lock()
+      .unwrap_or_else(|e| e.into_inner())
+      .insert(manager, now),
+  )
+}
+
+#[cfg(windows)]
+type Manager = GlobalSystemMediaTransportControlsSessionManager;
+#[cfg(target_os = "linux")]
+type Manager = Arc<MprisBackend>;
+// 其他平台不会创建管理器，缓存只用于保持重置与统计接口一致
+#[cfg(not(any(windows, target_os = "linux")))]
+type Manager = ();
+
+fn cache() -> &'static Mutex<ManagerCache<Manager>> {
+  static CACHE: OnceLock<Mutex<ManagerCache<Manager>>> = OnceLock::new();
+  CACHE.get_or_init(|| Mutex::new(ManagerCache::default()))
+}
+
+#[cfg(windows)]
+fn create_manager(timeout: Duration) -> Result<Manager> {
+  media_control::create_manager(timeout)
+}
+
+// Linux 上共享的是会话总线上的 MPRIS 后端，连接本地总线不需要等待播放器
+#[cfg(target_os = "linux")]
+fn create_manager(_timeout: Duration) -> Result<Manager> {
+  MprisBackend::connect()
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+// 获取进程内共享的会话管理器，首次调用时创建；timeout 只用于创建管理器
+pub fn get(timeout: Duration) -> Result<Manager> {
+  get_or_create(cache(), || create_manager(timeout), utils::now_millis())
+}
+
+#[cfg(any(windows, target_os = "linux"))]
+pub fn invalidate(manager: &Manager) {
+  cache()
+    .lock()
+    .unwrap_or_else(|e| e.into_inner())
+    .invalidate(manager);
+}
+
+// 丢弃共享的会话管理器，下次使用时重新创建
+#[napi]
+pub fn reset_session_manager() {
+  cache().lock().unwrap_or_else(|e| e.into_inner()).reset();
+}
+
+#[napi]
+pub fn get_session_manager_metrics() -> SessionManagerMetrics {
+  cache().lock().unwrap_or_else(|e| e.into_inner()).metrics()
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use napi::{Error, Status};
+
+  #[test]
+  fn creates_once_and_reuses() {
+    let cache = Mutex::new(ManagerCache::default());
+    let mut requests = 0;
+    for _ in 0..3 {
+      let manager = get_or_create(
+        &cache,
+        || {
+          requests += 1;
+          Ok(7)
+        },
+        100.0,
+      );
+      assert_eq!(manager.unwrap(), 7);
+    }
+
+    assert_eq!(requests, 1);
+    assert_eq!(
+      cache.lock().unwrap().metrics(),
+      SessionManagerMetrics {
+        created_count: 1,
+        reused_count: 2,
+        reset_count: 0,
+        last_created_time: Some(100.0),
+      }
+    );
+  }
+
+  #[test]
+  fn recreates_after_reset() {
+    let cache = Mutex::new(ManagerCache::default());
+    get_or_create(&cache, || Ok(1), 100.0).unwrap();
+    cache.lock().unwrap().reset();
+    // 没有缓存时重置不计数
+    cache.lock().unwrap().reset();
+
+    assert_eq!(get_or_create(&cache, || Ok(2), 200.0).unwrap(), 2);
+    let metrics = cache.lock().unwrap().metrics();
+    assert_eq!(metrics.created_count, 2);
+    assert_eq!(metrics.reset_count, 1);
+    assert_eq!(metrics.last_created_time, Some(200.0));
+  }
+
+  #[test]
+  fn invalidates_only_the_current_manager() {
+    let cache = Mutex::new(ManagerCache::default());
+    get_or_create(&cache, || Ok(1), 100.0).unwrap();
+
+    cache.lock().unwrap().invalidate(&2);
+    assert_eq!(cache.lock().unwrap().metrics().reset_count, 0);
+
+    cache.lock().unwrap().invalidate(&1);
+    assert_eq!(cache.lock().unwrap().metrics().reset_count, 1);
+  }
+
+  #[test]
+  fn does_not_cache_failures() {
+    let cache = Mutex::new(ManagerCache::<u32>::default());
+    let failed = get_or_create(
+      &cache,
+      || {
+        Err(Error::new(
+          Status::GenericFailure,
+          "unavailable".to_string(),
+        ))
+      },
+      100.0,
+    );
+
+    assert!(failed.is_err());
+    assert_eq!(
+      cache.lock().unwrap().metrics(),
+      SessionManagerMetrics::default()
+    );
+    assert_eq!(get_or_create(&cache, || Ok(3), 200.0).unwrap(), 3);
+  }
+
+  #[test]
+  fn creates_without_holding_the_lock() {
+    let cache = Mutex::new(ManagerCache::default());
+    let manager = get_or_create(
+      &cache,
+      || {
+        // 创建期间其它调用仍可读取统计，并先放入了自己创建的管理器
+        let mut inner = cache.try_lock().expect("cache locked during creation");
+        inner.insert(1, 50.0);
+        Ok(2)
+      },
+      100.0,
+    );
+
+    assert_eq!(manager.unwrap(), 1);
+    let metrics = cache.lock().unwrap().metrics();
+    assert_eq!(metrics.created_count, 1);
+    assert_eq!(metrics.reused_count, 1);
+    assert_eq!(metrics.last_created_time, Some(50.0));
+  }
+}
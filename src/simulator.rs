@@ -0,0 +1,824 @@
+use napi::{Error, Result, Status};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::backend::{
+  self, BackendEvent, EventHandler, EventToken, Handlers, MediaBackend, SharedBackend,
+  SharedSession,
+};
+use crate::image_info;
+use crate::position::estimate_position;
+use crate::query::SessionQuery;
+use crate::session::MediaSession;
+use crate::thumbnail::{ThumbnailOptions, ThumbnailSettings};
+use crate::thumbnail_cache;
+use crate::timeout::DEFAULT_TIMEOUT;
+use crate::types::{
+  MediaInfo, MediaProps, PlaybackControls, PlaybackInfo, PlaybackStatus, PlaybackType, RepeatMode,
+  ThumbnailBytes, TimelineProps,
+};
+use crate::utils;
+
+// 以下三个结构只包含需要修改的字段，未提供的字段保持原值
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SimulatedMediaProps {
+  pub title: Option<String>,
+  pub artist: Option<String>,
+  pub album_title: Option<String>,
+  pub album_artist: Option<String>,
+  pub genres: Option<Vec<String>>,
+  pub album_track_count: Option<u32>,
+  pub track_number: Option<u32>,
+  #[napi(ts_type = "Buffer | undefined")]
+  pub thumbnail: Option<ThumbnailBytes>,
+}
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SimulatedPlaybackInfo {
+  pub playback_status: Option<PlaybackStatus>,
+  pub playback_type: Option<PlaybackType>,
+  pub is_shuffle_active: Option<bool>,
+  pub auto_repeat_mode: Option<RepeatMode>,
+  pub playback_rate: Option<f64>,
+  pub controls: Option<PlaybackControls>,
+}
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SimulatedTimelineProps {
+  pub position: Option<f64>,
+  pub duration: Option<f64>,
+  pub start_time: Option<f64>,
+  pub min_seek_time: Option<f64>,
+  pub max_seek_time: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct SimulatedSessionInit {
+  pub media: Option<SimulatedMediaProps>,
+  pub playback: Option<SimulatedPlaybackInfo>,
+  pub timeline: Option<SimulatedTimelineProps>,
+}
+
+impl SimulatedMediaProps {
+  fn apply(self, media: &mut MediaProps) {
+    if let Some(title) = self.title {
+      media.title = title;
+    }
+    if let Some(artist) = self.artist {
+      media.artist = artist;
+    }
+    if let Some(album_title) = self.album_title {
+      media.album_title = album_title;
+    }
+    if let Some(album_artist) = self.album_artist {
+      media.album_artist = album_artist;
+    }
+    if let Some(genres) = self.genres {
+      media.genres = genres;
+    }
+    if let Some(album_track_count) = self.album_track_count {
+      media.album_track_count = album_track_count;
+    }
+    if let Some(track_number) = self.track_number {
+      media.track_number = track_number;
+    }
+    if let Some(thumbnail) = self.thumbnail {
+      // 与真实会话一样附带哈希与格式信息，getThumbnailByHash(hash) 可以取回图片
+      let info = image_info::detect_image(thumbnail.as_bytes(), None);
+      media.thumbnail_hash = Some(thumbnail_cache::cache_thumbnail(thumbnail.as_bytes()));
+      media.thumbnail_mime_type = info.as_ref().map(|info| info.mime_type.clone());
+      media.thumbnail_width = info.as_ref().and_then(|info| info.width);
+      media.thumbnail_height = info.as_ref().and_then(|info| info.height);
+      media.thumbnail = Some(thumbnail);
+    }
+  }
+}
+
+impl SimulatedPlaybackInfo {
+  fn apply(self, playback: &mut PlaybackInfo) {
+    if let Some(playback_status) = self.playback_status {
+      playback.playback_status = playback_status;
+    }
+    if let Some(playback_type) = self.playback_type {
+      playback.playback_type = playback_type;
+    }
+    if let Some(is_shuffle_active) = self.is_shuffle_active {
+      playback.is_shuffle_active = Some(is_shuffle_active);
+    }
+    if let Some(auto_repeat_mode) = self.auto_repeat_mode {
+      playback.auto_repeat_mode = Some(auto_repeat_mode);
+    }
+    if let Some(playback_rate) = self.playback_rate {
+      playback.playback_rate = Some(playback_rate);
+    }
+    if let Some(controls) = self.controls {
+      playback.controls = controls;
+    }
+  }
+}
+
+impl SimulatedTimelineProps {
+  fn apply(self, timeline: &mut TimelineProps) {
+    if let Some(position) = self.position {
+      timeline.position = position;
+    }
+    if let Some(duration) = self.duration {
+      timeline.duration = duration;
+    }
+    if let Some(start_time) = self.start_time {
+      timeline.start_time = start_time;
+    }
+    if let Some(min_seek_time) = self.min_seek_time {
+      timeline.min_seek_time = min_seek_time;
+    }
+    if let Some(max_seek_time) = self.max_seek_time {
+      timeline.max_seek_time = max_seek_time;
+    }
+  }
+}
+
+struct SessionState {
+  media: MediaProps,
+  playback: PlaybackInfo,
+  timeline: TimelineProps,
+}
+
+impl SessionState {
+  // 以当前推算位置作为新的上报位置，修改播放状态或速率后位置保持连续
+  fn rebase(&mut self, now: f64) {
+    self.timeline.position = estimate_position(&self.timeline, &self.playback, now);
+    self.timeline.last_updated_time = Some(now);
+  }
+}
+
+// 内存中的模拟会话，控制命令直接修改状态并触发与真实会话相同的事件
+pub struct SimulatedSession {
+  source_app_id: String,
+  key: String,
+  state: Mutex<SessionState>,
+  handlers: Handlers,
+}
+
+impl SimulatedSession {
+  fn new(source_app_id: String, key: String, init: SimulatedSessionInit) -> Self {
+    let mut state = SessionState {
+      media: MediaProps::default(),
+      playback: PlaybackInfo {
+        playback_status: PlaybackStatus::PLAYING,
+        playback_type: PlaybackType::MUSIC,
+        is_shuffle_active: Some(false),
+        auto_repeat_mode: Some(RepeatMode::NONE),
+        playback_rate: Some(1.0),
+        controls: PlaybackControls::from_fn(|_| true),
+      },
+      timeline: TimelineProps::default(),
+    };
+
+    init.media.unwrap_or_default().apply(&mut state.media);
+    init.playback.unwrap_or_default().apply(&mut state.playback);
+    init.timeline.unwrap_or_default().apply(&mut state.timeline);
+    state.timeline.last_updated_time = Some(utils::now_millis());
+
+    Self::with_state(source_app_id, key, state)
+  }
+
+  fn with_state(source_app_id: String, key: String, state: SessionState) -> Self {
+    Self {
+      source_app_id,
+      key,
+      state: Mutex::new(state),
+      handlers: Handlers::default(),
+    }
+  }
+
+  fn lock(&self) -> std::sync::MutexGuard<'_, SessionState> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  // 修改状态后在释放锁之后触发事件
+  fn update(&self, event: BackendEvent, apply: impl FnOnce(&mut SessionState)) {
+    apply(&mut self.lock());
+    self.handlers.emit(event);
+  }
+
+  // 对应的控制能力未开启时拒绝命令，与播放器返回 false 的行为一致
+  fn command(
+    &self,
+    enabled: fn(&PlaybackControls) -> bool,
+    event: Option<BackendEvent>,
+    apply: impl FnOnce(&mut SessionState),
+  ) -> Result<bool> {
+    {
+      let mut state = self.lock();
+      if !enabled(&state.playback.controls) {
+        return Ok(false);
+      }
+      state.rebase(utils::now_millis());
+      apply(&mut state);
+    }
+
+    if let Some(event) = event {
+      self.handlers.emit(event);
+    }
+    Ok(true)
+  }
+
+  fn set_status(
+    &self,
+    enabled: fn(&PlaybackControls) -> bool,
+    status: PlaybackStatus,
+  ) -> Result<bool> {
+    self.command(enabled, Some(BackendEvent::PlaybackInfoChanged), |state| {
+      state.playback.playback_status = status;
+    })
+  }
+}
+
+impl MediaSession for SimulatedSession {
+  fn source_app_id(&self) -> Result<String> {
+    Ok(self.source_app_id.clone())
+  }
+
+  fn session_key(&self) -> Result<String> {
+    Ok(self.key.clone())
+  }
+
+  fn media_info(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    timeout: Duration,
+  ) -> Result<Option<MediaInfo>> {
+    let media = self.media_props(thumbnail, timeout)?.unwrap_or_default();
+    let playback = self.lock().playback.clone();
+    let timeline = self.timeline_props(timeout)?;
+
+    Ok(Some(MediaInfo {
+      source_app_id: self.source_app_id.clone(),
+      session_key: self.key.clone(),
+      media,
+      playback,
+      timeline,
+      last_updated_time: utils::now_millis(),
+      error: None,
+    }))
+  }
+
+  fn media_props(
+    &self,
+    thumbnail: &ThumbnailSettings,
+    _timeout: Duration,
+  ) -> Result<Option<MediaProps>> {
+    let mut media = self.lock().media.clone();
+    if !thumbnail.include {
+      media.thumbnail = None;
+    }
+    Ok(Some(media))
+  }
+
+  fn playback_info(&self, _timeout: Duration) -> Result<Option<PlaybackInfo>> {
+    Ok(Some(self.lock().playback.clone()))
+  }
+
+  fn try_play(&self, _timeout: Duration) -> Result<bool> {
+    self.set_status(|controls| controls.play, PlaybackStatus::PLAYING)
+  }
+
+  fn try_pause(&self, _timeout: Duration) -> Result<bool> {
+    self.set_status(|controls| controls.pause, PlaybackStatus::PAUSED)
+  }
+
+  fn try_toggle_play_pause(&self, _timeout: Duration) -> Result<bool> {
+    let status = match self.lock().playback.playback_status {
+      PlaybackStatus::PLAYING => PlaybackStatus::PAUSED,
+      _ => PlaybackStatus::PLAYING,
+    };
+    self.set_status(|controls| controls.play_pause_toggle, status)
+  }
+
+  fn try_stop(&self, _timeout: Duration) -> Result<bool> {
+    self.set_status(|controls| controls.stop, PlaybackStatus::STOPPED)
+  }
+
+  // 模拟会话没有播放列表，切换曲目只确认命令，由测试自行更新媒体属性
+  fn try_skip_next(&self, _timeout: Duration) -> Result<bool> {
+    self.command(|controls| controls.next, None, |_| {})
+  }
+
+  fn try_skip_previous(&self, _timeout: Duration) -> Result<bool> {
+    self.command(|controls| controls.previous, None, |_| {})
+  }
+
+  fn is_seek_enabled(&self, _timeout: Duration) -> Result<bool> {
+    Ok(self.lock().playback.controls.seek)
+  }
+
+  fn timeline_props(&self, _timeout: Duration) -> Result<TimelineProps> {
+    let state = self.lock();
+    let mut timeline = state.timeline.clone();
+    timeline.estimated_position = Some(estimate_position(
+      &timeline,
+      &state.playback,
+      utils::now_millis(),
+    ));
+    Ok(timeline)
+  }
+
+  fn try_change_playback_position(&self, position: f64, _timeout: Duration) -> Result<bool> {
+    self.command(
+      |controls| controls.seek,
+      Some(BackendEvent::TimelinePropsChanged),
+      |state| state.timeline.position = position,
+    )
+  }
+
+  fn try_change_shuffle_active(&self, active: bool, _timeout: Duration) -> Result<bool> {
+    self.command(
+      |controls| controls.shuffle,
+      Some(BackendEvent::PlaybackInfoChanged),
+      |state| state.playback.is_shuffle_active = Some(active),
+    )
+  }
+
+  fn try_change_auto_repeat_mode(&self, mode: RepeatMode, _timeout: Duration) -> Result<bool> {
+    self.command(
+      |controls| controls.repeat,
+      Some(BackendEvent::PlaybackInfoChanged),
+      |state| state.playback.auto_repeat_mode = Some(mode),
+    )
+  }
+
+  fn try_change_playback_rate(&self, rate: f64, _timeout: Duration) -> Result<bool> {
+    self.command(
+      |controls| controls.playback_rate,
+      Some(BackendEvent::PlaybackInfoChanged),
+      |state| state.playback.playback_rate = Some(rate),
+    )
+  }
+
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+    match event {
+      BackendEvent::MediaPropsChanged
+      | BackendEvent::PlaybackInfoChanged
+      | BackendEvent::TimelinePropsChanged => Ok(self.handlers.add(event, handler)),
+      _ => Err(backend::unsupported_event(event)),
+    }
+  }
+
+  fn unsubscribe(&self, token: EventToken) {
+    self.handlers.remove(token);
+  }
+}
+
+#[derive(Default)]
+struct BackendState {
+  sessions: Vec<Arc<SimulatedSession>>,
+  current: Option<String>,
+  counters: HashMap<String, u32>,
+}
+
+#[derive(Default)]
+pub struct SimulatedBackend {
+  state: Mutex<BackendState>,
+  handlers: Handlers,
+}
+
+impl SimulatedBackend {
+  fn lock(&self) -> std::sync::MutexGuard<'_, BackendState> {
+    self.state.lock().unwrap_or_else(|e| e.into_inner())
+  }
+
+  // 会话键沿用 "<sourceAppId>#<序号>" 的格式
+  pub fn add_session(&self, source_app_id: &str, init: SimulatedSessionInit) -> String {
+    let key = {
+      let mut state = self.lock();
+      let counter = state.counters.entry(source_app_id.to_string()).or_insert(0);
+      *counter += 1;
+      let key = format!("{}#{}", source_app_id, counter);
+      state.sessions.push(Arc::new(SimulatedSession::new(
+        source_app_id.to_string(),
+        key.clone(),
+        init,
+      )));
+      key
+    };
+
+    self.handlers.emit(BackendEvent::SessionsChanged);
+    key
+  }
+
+  pub fn remove_session(&self, key: &str) -> bool {
+    let was_current = {
+      let mut state = self.lock();
+      let len = state.sessions.len();
+      state.sessions.retain(|session| session.key != key);
+      if state.sessions.len() == len {
+        return false;
+      }
+
+      let was_current = state.current.as_deref() == Some(key);
+      if was_current {
+        state.current = None;
+      }
+      was_current
+    };
+
+    self.handlers.emit(BackendEvent::SessionsChanged);
+    if was_current {
+      self.handlers.emit(BackendEvent::CurrentSessionChanged);
+    }
+    true
+  }
+
+  pub fn set_current_session(&self, key: Option<&str>) -> Result<()> {
+    if let Some(key) = key {
+      self.session(key)?;
+    }
+
+    self.lock().current = key.map(str::to_string);
+    self.handlers.emit(BackendEvent::CurrentSessionChanged);
+    Ok(())
+  }
+
+  pub fn update_media_props(&self, key: &str, media: SimulatedMediaProps) -> Result<()> {
+    self
+      .session(key)?
+      .update(BackendEvent::MediaPropsChanged, |state| {
+        media.apply(&mut state.media)
+      });
+    Ok(())
+  }
+
+  pub fn update_playback_info(&self, key: &str, playback: SimulatedPlaybackInfo) -> Result<()> {
+    self
+      .session(key)?
+      .update(BackendEvent::PlaybackInfoChanged, |state| {
+        state.rebase(utils::now_millis());
+        playback.apply(&mut state.playback);
+      });
+    Ok(())
+  }
+
+  pub fn update_timeline_props(&self, key: &str, timeline: SimulatedTimelineProps) -> Result<()> {
+    self
+      .session(key)?
+      .update(BackendEvent::TimelinePropsChanged, |state| {
+        state.rebase(utils::now_millis());
+        timeline.apply(&mut state.timeline);
+      });
+    Ok(())
+  }
+
+  // 以给定的会话键与完整状态登记会话，已存在同名会话时先移除；供回放录制的事件使用
+  pub fn restore_session(&self, info: MediaInfo) {
+    let session = SimulatedSession::with_state(
+      info.source_app_id,
+      info.session_key,
+      SessionState {
+        media: info.media,
+        playback: info.playback,
+        timeline: info.timeline,
+      },
+    );
+
+    {
+      let mut state = self.lock();
+      state
+        .sessions
+        .retain(|existing| existing.key != session.key);
+      state.sessions.push(Arc::new(session));
+    }
+    self.handlers.emit(BackendEvent::SessionsChanged);
+  }
+
+  // 以下三个方法整体替换会话状态，不按当前时间调整位置
+  pub fn replace_media_props(&self, key: &str, media: MediaProps) -> Result<()> {
+    self
+      .session(key)?
+      .update(BackendEvent::MediaPropsChanged, |state| state.media = media);
+    Ok(())
+  }
+
+  pub fn replace_playback_info(&self, key: &str, playback: PlaybackInfo) -> Result<()> {
+    self
+      .session(key)?
+      .update(BackendEvent::PlaybackInfoChanged, |state| {
+        state.playback = playback
+      });
+    Ok(())
+  }
+
+  pub fn replace_timeline_props(&self, key: &str, timeline: TimelineProps) -> Result<()> {
+    self
+      .session(key)?
+      .update(BackendEvent::TimelinePropsChanged, |state| {
+        state.timeline = timeline
+      });
+    Ok(())
+  }
+
+  fn session(&self, key: &str) -> Result<Arc<SimulatedSession>> {
+    self
+      .lock()
+      .sessions
+      .iter()
+      .find(|session| session.key == key)
+      .cloned()
+      .ok_or_else(|| {
+        Error::new(
+          Status::InvalidArg,
+          format!("No simulated session found for sessionKey \"{}\"", key),
+        )
+      })
+  }
+}
+
+impl MediaBackend for SimulatedBackend {
+  fn sessions(&self) -> Vec<SharedSession> {
+    self
+      .lock()
+      .sessions
+      .iter()
+      .map(|session| session.clone() as SharedSession)
+      .collect()
+  }
+
+  fn current_session(&self) -> Option<SharedSession> {
+    let key = self.lock().current.clone()?;
+    self
+      .session(&key)
+      .ok()
+      .map(|session| session as SharedSession)
+  }
+
+  fn subscribe(&self, event: BackendEvent, handler: EventHandler) -> Result<EventToken> {
+    match event {
+      BackendEvent::SessionsChanged | BackendEvent::CurrentSessionChanged => {
+        Ok(self.handlers.add(event, handler))
+      }
+      _ => Err(backend::unsupported_event(event)),
+    }
+  }
+
+  fn unsubscribe(&self, token: EventToken) {
+    self.handlers.remove(token);
+  }
+}
+
+// 可编程的模拟后端，在没有 SMTC 的系统上驱动 SMTCMonitor
+#[napi]
+#[derive(Clone, Default)]
+pub struct MediaSimulator {
+  backend: Arc<SimulatedBackend>,
+}
+
+#[napi]
+impl MediaSimulator {
+  #[napi(constructor)]
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  #[napi]
+  pub fn add_session(&self, source_app_id: String, init: Option<SimulatedSessionInit>) -> String {
+    self
+      .backend
+      .add_session(&source_app_id, init.unwrap_or_default())
+  }
+
+  #[napi]
+  pub fn remove_session(&self, session_key: String) -> bool {
+    self.backend.remove_session(&session_key)
+  }
+
+  #[napi]
+  pub fn set_current_session(&self, session_key: Option<String>) -> Result<()> {
+    self.backend.set_current_session(session_key.as_deref())
+  }
+
+  #[napi]
+  pub fn update_media_props(&self, session_key: String, media: SimulatedMediaProps) -> Result<()> {
+    self.backend.update_media_props(&session_key, media)
+  }
+
+  #[napi]
+  pub fn update_playback_info(
+    &self,
+    session_key: String,
+    playback: SimulatedPlaybackInfo,
+  ) -> Result<()> {
+    self.backend.update_playback_info(&session_key, playback)
+  }
+
+  #[napi]
+  pub fn update_timeline_props(
+    &self,
+    session_key: String,
+    timeline: SimulatedTimelineProps,
+  ) -> Result<()> {
+    self.backend.update_timeline_props(&session_key, timeline)
+  }
+
+  #[napi]
+  pub fn get_sessions(&self, options: Option<ThumbnailOptions>) -> Result<Vec<MediaInfo>> {
+    let settings = ThumbnailSettings::from_options(options.as_ref())?;
+    SessionQuery::All.run(&*self.backend, &settings, DEFAULT_TIMEOUT)
+  }
+
+  #[napi]
+  pub fn get_current_session(
+    &self,
+    options: Option<ThumbnailOptions>,
+  ) -> Result<Option<MediaInfo>> {
+    let settings = ThumbnailSettings::from_options(options.as_ref())?;
+    Ok(
+      SessionQuery::Current
+        .run(&*self.backend, &settings, DEFAULT_TIMEOUT)?
+        .pop(),
+    )
+  }
+
+  pub fn backend(&self) -> SharedBackend {
+    self.backend.clone()
+  }
+
+  pub fn simulated_backend(&self) -> Arc<SimulatedBackend> {
+    self.backend.clone()
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::monitor::{PlaybackInfoCallbackData, SessionCallbackData};
+  use crate::session_manager::{self, EventCallback, SessionManager};
+
+  fn recorder<T: Send + 'static>() -> (EventCallback<T>, Arc<Mutex<Vec<T>>>) {
+    let received = Arc::new(Mutex::new(Vec::new()));
+    let sink = received.clone();
+    let callback: EventCallback<T> = Arc::new(move |data| sink.lock().unwrap().push(data));
+    (callback, received)
+  }
+
+  fn attached() -> (Arc<SimulatedBackend>, Arc<Mutex<SessionManager>>) {
+    let backend = Arc::new(SimulatedBackend::default());
+    let shared = Arc::new(Mutex::new(
+      SessionManager::new(ThumbnailSettings::default()),
+    ));
+    session_manager::attach(&(backend.clone() as SharedBackend), &shared).unwrap();
+    (backend, shared)
+  }
+
+  fn song(title: &str) -> SimulatedSessionInit {
+    SimulatedSessionInit {
+      media: Some(SimulatedMediaProps {
+        title: Some(title.to_string()),
+        ..Default::default()
+      }),
+      ..Default::default()
+    }
+  }
+
+  #[test]
+  fn emits_the_same_events_as_a_real_session() {
+    let (backend, shared) = attached();
+    let (on_added, added) = recorder::<MediaInfo>();
+    let (on_removed, removed) = recorder::<SessionCallbackData>();
+    let (on_current, current) = recorder::<SessionCallbackData>();
+    let (on_playback, playback) = recorder::<PlaybackInfoCallbackData>();
+    {
+      let mut inner = shared.lock().unwrap();
+      inner.subscribe(|inner| &mut inner.session_added_callbacks, on_added);
+      inner.subscribe(|inner| &mut inner.session_removed_callbacks, on_removed);
+      inner.subscribe(
+        |inner| &mut inner.current_session_changed_callbacks,
+        on_current,
+      );
+      inner.subscribe(|inner| &mut inner.playback_info_callbacks, on_playback);
+    }
+
+    let first = backend.add_session("player.exe", song("First"));
+    let second = backend.add_session("player.exe", song("Second"));
+    backend.set_current_session(Some(&second)).unwrap();
+    backend
+      .update_playback_info(
+        &first,
+        SimulatedPlaybackInfo {
+          playback_status: Some(PlaybackStatus::PAUSED),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    assert!(backend.remove_session(&second));
+    assert!(!backend.remove_session(&second));
+
+    let added = added.lock().unwrap();
+    assert_eq!(first, "player.exe#1");
+    assert_eq!(second, "player.exe#2");
+    assert_eq!(added.len(), 2);
+    assert_eq!(added[1].media.title, "Second");
+    assert_eq!(current.lock().unwrap()[0].session_key, second);
+    assert_eq!(
+      playback.lock().unwrap()[0].playback_info.playback_status,
+      PlaybackStatus::PAUSED
+    );
+    assert_eq!(removed.lock().unwrap()[0].session_key, second);
+    assert!(backend.current_session().is_none());
+  }
+
+  #[test]
+  fn commands_update_state_when_the_control_is_enabled() {
+    let backend = SimulatedBackend::default();
+    let key = backend.add_session(
+      "player.exe",
+      SimulatedSessionInit {
+        timeline: Some(SimulatedTimelineProps {
+          duration: Some(200.0),
+          ..Default::default()
+        }),
+        ..Default::default()
+      },
+    );
+    let session = backend.session(&key).unwrap();
+
+    assert!(session.try_pause(DEFAULT_TIMEOUT).unwrap());
+    assert!(session
+      .try_change_playback_position(42.0, DEFAULT_TIMEOUT)
+      .unwrap());
+    assert!(session
+      .try_change_auto_repeat_mode(RepeatMode::LIST, DEFAULT_TIMEOUT)
+      .unwrap());
+    let playback = session.playback_info(DEFAULT_TIMEOUT).unwrap().unwrap();
+    assert_eq!(playback.playback_status, PlaybackStatus::PAUSED);
+    assert_eq!(playback.auto_repeat_mode, Some(RepeatMode::LIST));
+    assert_eq!(
+      session
+        .timeline_props(DEFAULT_TIMEOUT)
+        .unwrap()
+        .estimated_position,
+      Some(42.0)
+    );
+
+    let mut controls = playback.controls.clone();
+    controls.play = false;
+    backend
+      .update_playback_info(
+        &key,
+        SimulatedPlaybackInfo {
+          controls: Some(controls),
+          ..Default::default()
+        },
+      )
+      .unwrap();
+    assert!(!session.try_play(DEFAULT_TIMEOUT).unwrap());
+    assert_eq!(
+      session
+        .playback_info(DEFAULT_TIMEOUT)
+        .unwrap()
+        .unwrap()
+        .playback_status,
+      PlaybackStatus::PAUSED
+    );
+  }
+
+  #[test]
+  fn rebases_the_position_before_state_changes() {
+    let mut state = SessionState {
+      media: MediaProps::default(),
+      playback: PlaybackInfo {
+        playback_status: PlaybackStatus::PLAYING,
+        playback_type: PlaybackType::MUSIC,
+        is_shuffle_active: None,
+        auto_repeat_mode: None,
+        playback_rate: Some(2.0),
+        controls: Default::default(),
+      },
+      timeline: TimelineProps {
+        position: 10.0,
+        duration: 100.0,
+        last_updated_time: Some(1_000.0),
+        ..Default::default()
+      },
+    };
+
+    state.rebase(3_000.0);
+
+    assert_eq!(state.timeline.position, 14.0);
+    assert_eq!(state.timeline.last_updated_time, Some(3_000.0));
+  }
+
+  #[test]
+  fn rejects_unknown_session_keys() {
+    let backend = SimulatedBackend::default();
+    let err = backend.set_current_session(Some("missing#1")).unwrap_err();
+
+    assert_eq!(err.status, Status::InvalidArg);
+    assert!(backend
+      .update_media_props("missing#1", SimulatedMediaProps::default())
+      .is_err());
+  }
+}
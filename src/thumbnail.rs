@@ -0,0 +1,494 @@
+use napi::{bindgen_prelude::*, Result};
+use std::time::Duration;
+#[cfg(windows)]
+use windows::{
+  Media::Control::GlobalSystemMediaTransportControlsSessionMediaProperties,
+  Storage::Streams::{
+    Buffer as WinBuffer, DataReader, IBuffer, IRandomAccessStreamWithContentType,
+    InputStreamOptions,
+  },
+};
+
+#[cfg(target_os = "linux")]
+use crate::backend;
+#[cfg(windows)]
+use crate::media_control;
+#[cfg(target_os = "linux")]
+use crate::session::MediaSession;
+#[cfg(windows)]
+use crate::shared_manager;
+use crate::thumbnail_cache;
+#[cfg(windows)]
+use crate::timeout;
+use crate::transcode::{self, Transcode};
+#[cfg(windows)]
+use crate::utils::{self, try_win_api, win_to_napi_err};
+
+const DEFAULT_MAX_BYTES: usize = 16 * 1024 * 1024;
+const CHUNK_SIZE: u32 = 1024 * 1024;
+
+#[napi(object)]
+#[derive(Default)]
+pub struct ThumbnailOptions {
+  pub include_thumbnail: Option<bool>,
+  pub max_bytes: Option<u32>,
+  #[napi(ts_type = "'png' | 'jpeg' | 'webp'")]
+  pub format: Option<String>,
+  pub max_dimension: Option<u32>,
+  pub quality: Option<u32>,
+  pub palette: Option<bool>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ThumbnailSettings {
+  // 为 false 时只读取元数据，封面通过 getThumbnail 按需获取
+  pub include: bool,
+  // 缩略图最多读取的字节数，超出部分会被截断
+  pub max_bytes: usize,
+  // 未设置格式与尺寸时不转码
+  pub transcode: Option<Transcode>,
+  // 读取缩略图时顺带提取主色调与 BlurHash
+  pub palette: bool,
+}
+
+impl Default for ThumbnailSettings {
+  fn default() -> Self {
+    Self {
+      include: true,
+      max_bytes: DEFAULT_MAX_BYTES,
+      transcode: None,
+      palette: false,
+    }
+  }
+}
+
+impl ThumbnailSettings {
+  pub fn from_options(options: Option<&ThumbnailOptions>) -> Result<Self> {
+    let mut settings = Self::default();
+    let options = match options {
+      Some(options) => options,
+      None => return Ok(settings),
+    };
+
+    if let Some(include) = options.include_thumbnail {
+      settings.include = include;
+    }
+
+    if let Some(max_bytes) = options.max_bytes {
+      if max_bytes == 0 {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "Invalid thumbnail maxBytes: 0. Expected a positive integer".to_string(),
+        ));
+      }
+      settings.max_bytes = max_bytes as usize;
+    }
+
+    settings.palette = options.palette.unwrap_or(false);
+
+    if options.format.is_some() || options.max_dimension.is_some() {
+      if options.max_dimension == Some(0) {
+        return Err(Error::new(
+          Status::InvalidArg,
+          "Invalid thumbnail maxDimension: 0. Expected a positive integer".to_string(),
+        ));
+      }
+
+      settings.transcode = Some(Transcode {
+        format: options
+          .format
+          .as_deref()
+          .map(transcode::parse_format)
+          .transpose()?,
+        max_dimension: options.max_dimension,
+        quality: match options.quality {
+          Some(quality) => transcode::parse_quality(quality)?,
+          None => transcode::DEFAULT_QUALITY,
+        },
+      });
+    }
+
+    Ok(settings)
+  }
+}
+
+pub struct ThumbnailData {
+  pub bytes: Vec<u8>,
+  pub truncated: bool,
+  pub content_type: Option<String>,
+}
+
+// 按块读取的数据源，读到末尾时返回空数据
+pub trait ChunkSource {
+  fn read_chunk(&mut self, len: u32) -> Result<Vec<u8>>;
+}
+
+// 读取到流结束或达到上限为止；reported_size 为 0 表示流没有报告大小
+pub fn read_to_limit<S: ChunkSource>(
+  source: &mut S,
+  reported_size: u64,
+  max_bytes: usize,
+) -> Result<ThumbnailData> {
+  let limit = max_bytes as u64;
+  let expected = if reported_size > 0 {
+    reported_size.min(limit)
+  } else {
+    limit
+  };
+
+  let mut bytes = Vec::with_capacity(expected.min(CHUNK_SIZE as u64) as usize);
+  while (bytes.len() as u64) < expected {
+    let len = (expected - bytes.len() as u64).min(CHUNK_SIZE as u64) as u32;
+    let chunk = source.read_chunk(len)?;
+    if chunk.is_empty() {
+      break;
+    }
+    bytes.extend_from_slice(&chunk);
+  }
+  bytes.truncate(expected as usize);
+
+  let truncated = bytes.len() as u64 == limit
+    && if reported_size > 0 {
+      reported_size > limit
+    } else {
+      !source.read_chunk(1)?.is_empty()
+    };
+
+  Ok(ThumbnailData {
+    bytes,
+    truncated,
+    content_type: None,
+  })
+}
+
+#[cfg(windows)]
+struct StreamSource<'a> {
+  stream: &'a IRandomAccessStreamWithContentType,
+  timeout: Duration,
+}
+
+#[cfg(windows)]
+impl ChunkSource for StreamSource<'_> {
+  fn read_chunk(&mut self, len: u32) -> Result<Vec<u8>> {
+    let buffer = win_to_napi_err(WinBuffer::Create(len))?;
+    let operation = win_to_napi_err(
+      self
+        .stream
+        .ReadAsync(&buffer, len, InputStreamOptions::None),
+    )?;
+    let result = timeout::wait(&operation, "Reading the thumbnail", self.timeout)?;
+    read_buffer(&result)
+  }
+}
+
+#[cfg(windows)]
+fn read_buffer(buffer: &IBuffer) -> Result<Vec<u8>> {
+  let length = win_to_napi_err(buffer.Length())?;
+  let mut bytes = vec![0u8; length as usize];
+  if length > 0 {
+    let data_reader = win_to_napi_err(DataReader::FromBuffer(buffer))?;
+    win_to_napi_err(data_reader.ReadBytes(&mut bytes))?;
+  }
+  Ok(bytes)
+}
+
+#[cfg(windows)]
+// 读取超时时返回错误，其它读取失败视为没有缩略图
+pub fn read_thumbnail(
+  media_props: &GlobalSystemMediaTransportControlsSessionMediaProperties,
+  settings: &ThumbnailSettings,
+  timeout: Duration,
+) -> Result<Option<ThumbnailData>> {
+  let operation = match try_win_api(|| media_props.Thumbnail().and_then(|t| t.OpenReadAsync())) {
+    Some(operation) => operation,
+    None => return Ok(None),
+  };
+  let stream = match timeout::wait(&operation, "Opening the thumbnail", timeout) {
+    Ok(stream) => stream,
+    Err(err) if timeout::is_timeout(&err) => return Err(err),
+    Err(_) => return Ok(None),
+  };
+  let reported_size = try_win_api(|| stream.Size()).unwrap_or(0);
+  let content_type = try_win_api(|| stream.ContentType())
+    .map(|content_type| content_type.to_string())
+    .filter(|content_type| !content_type.is_empty());
+
+  let mut source = StreamSource {
+    stream: &stream,
+    timeout,
+  };
+  match read_to_limit(&mut source, reported_size, settings.max_bytes) {
+    Ok(data) if !data.bytes.is_empty() => Ok(Some(ThumbnailData {
+      content_type,
+      ..data
+    })),
+    Ok(_) => Ok(None),
+    Err(err) if timeout::is_timeout(&err) => Err(err),
+    Err(_) => Ok(None),
+  }
+}
+
+#[cfg(windows)]
+// 读取并按设置转码缩略图，未启用时不读取
+pub fn load_thumbnail(
+  media_props: &GlobalSystemMediaTransportControlsSessionMediaProperties,
+  settings: &ThumbnailSettings,
+  timeout: Duration,
+) -> Result<Option<ThumbnailData>> {
+  if !settings.include {
+    return Ok(None);
+  }
+
+  Ok(read_thumbnail(media_props, settings, timeout)?.map(|data| normalize(data, settings)))
+}
+
+// 按设置转码；截断或无法解码的图片保持原样
+pub fn normalize(data: ThumbnailData, settings: &ThumbnailSettings) -> ThumbnailData {
+  let options = match &settings.transcode {
+    Some(options) if !data.truncated => options,
+    _ => return data,
+  };
+
+  match transcode::transcode(&data.bytes, options) {
+    Ok((bytes, format)) => ThumbnailData {
+      bytes,
+      truncated: false,
+      content_type: Some(format.mime_type().to_string()),
+    },
+    Err(_) => data,
+  }
+}
+
+// 按会话键读取缩略图，按设置转码后写入缓存
+pub struct ThumbnailTask {
+  session_key: String,
+  settings: ThumbnailSettings,
+  timeout: Duration,
+}
+
+impl ThumbnailTask {
+  pub fn new(session_key: String, settings: ThumbnailSettings, timeout: Duration) -> Self {
+    Self {
+      session_key,
+      settings: ThumbnailSettings {
+        include: true,
+        ..settings
+      },
+      timeout,
+    }
+  }
+}
+
+impl Task for ThumbnailTask {
+  type Output = Option<Vec<u8>>;
+  type JsValue = Option<Buffer>;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    Ok(
+      read_session_thumbnail(&self.session_key, &self.settings, self.timeout)?.map(|data| {
+        thumbnail_cache::cache_thumbnail(&data.bytes);
+        data.bytes
+      }),
+    )
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output.map(Buffer::from))
+  }
+}
+
+#[cfg(windows)]
+fn read_session_thumbnail(
+  key: &str,
+  settings: &ThumbnailSettings,
+  timeout: Duration,
+) -> Result<Option<ThumbnailData>> {
+  let manager = shared_manager::get(timeout)?;
+  let session = match media_control::find_session(&manager, Some(key)) {
+    Some(session) => session,
+    None => return Ok(None),
+  };
+
+  let media_props = match utils::request_media_props(&session, timeout)? {
+    Some(props) => props,
+    None => return Ok(None),
+  };
+
+  load_thumbnail(&media_props, settings, timeout)
+}
+
+// MPRIS 会话的缩略图在读取媒体属性时一并加载
+#[cfg(target_os = "linux")]
+fn read_session_thumbnail(
+  key: &str,
+  settings: &ThumbnailSettings,
+  timeout: Duration,
+) -> Result<Option<ThumbnailData>> {
+  let backend = backend::resolve(None, timeout)?;
+  let media_props = match backend::find_session(&*backend, Some(key)) {
+    Some(session) => session.media_props(settings, timeout)?,
+    None => return Ok(None),
+  };
+
+  Ok(media_props.and_then(|media| {
+    let bytes = media.thumbnail?.as_bytes().to_vec();
+    Some(ThumbnailData {
+      bytes,
+      truncated: media.thumbnail_truncated,
+      content_type: media.thumbnail_mime_type,
+    })
+  }))
+}
+
+// 其他平台没有系统媒体会话，只能返回缓存中已有的缩略图
+#[cfg(not(any(windows, target_os = "linux")))]
+fn read_session_thumbnail(
+  _key: &str,
+  _settings: &ThumbnailSettings,
+  _timeout: Duration,
+) -> Result<Option<ThumbnailData>> {
+  Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::image_info::ImageFormat;
+  use crate::timeout::DEFAULT_TIMEOUT;
+
+  // 每次最多返回 step 字节，模拟分段返回的流
+  struct FakeStream {
+    data: Vec<u8>,
+    offset: usize,
+    step: usize,
+  }
+
+  impl FakeStream {
+    fn new(len: usize, step: usize) -> Self {
+      Self {
+        data: (0..len).map(|i| i as u8).collect(),
+        offset: 0,
+        step,
+      }
+    }
+  }
+
+  impl ChunkSource for FakeStream {
+    fn read_chunk(&mut self, len: u32) -> Result<Vec<u8>> {
+      let end = (self.offset + self.step.min(len as usize)).min(self.data.len());
+      let chunk = self.data[self.offset..end].to_vec();
+      self.offset = end;
+      Ok(chunk)
+    }
+  }
+
+  #[test]
+  fn reads_the_whole_stream_across_partial_reads() {
+    let mut stream = FakeStream::new(3000, 700);
+    let data = read_to_limit(&mut stream, 3000, DEFAULT_MAX_BYTES).unwrap();
+
+    assert_eq!(data.bytes, stream.data);
+    assert!(!data.truncated);
+  }
+
+  #[test]
+  fn truncates_at_the_limit() {
+    let mut stream = FakeStream::new(3000, 700);
+    let data = read_to_limit(&mut stream, 3000, 1000).unwrap();
+
+    assert_eq!(data.bytes, &stream.data[..1000]);
+    assert!(data.truncated);
+  }
+
+  #[test]
+  fn detects_truncation_without_reported_size() {
+    let mut larger = FakeStream::new(3000, 700);
+    let mut exact = FakeStream::new(1000, 700);
+
+    assert!(read_to_limit(&mut larger, 0, 1000).unwrap().truncated);
+    assert!(!read_to_limit(&mut exact, 0, 1000).unwrap().truncated);
+  }
+
+  #[test]
+  fn stops_at_end_of_a_shorter_stream() {
+    let mut stream = FakeStream::new(500, 700);
+    let data = read_to_limit(&mut stream, 3000, DEFAULT_MAX_BYTES).unwrap();
+
+    assert_eq!(data.bytes.len(), 500);
+    assert!(!data.truncated);
+  }
+
+  #[test]
+  fn rejects_zero_max_bytes() {
+    let options = ThumbnailOptions {
+      max_bytes: Some(0),
+      ..Default::default()
+    };
+    let error = ThumbnailSettings::from_options(Some(&options)).unwrap_err();
+
+    assert_eq!(error.status, Status::InvalidArg);
+    assert_eq!(
+      ThumbnailSettings::from_options(None).unwrap(),
+      ThumbnailSettings::default()
+    );
+  }
+
+  #[test]
+  fn enables_transcoding_when_format_or_size_is_set() {
+    let options = ThumbnailOptions {
+      format: Some("webp".to_string()),
+      max_dimension: Some(256),
+      ..Default::default()
+    };
+    let settings = ThumbnailSettings::from_options(Some(&options)).unwrap();
+
+    assert_eq!(
+      settings.transcode,
+      Some(Transcode {
+        format: Some(ImageFormat::WebP),
+        max_dimension: Some(256),
+        quality: transcode::DEFAULT_QUALITY,
+      })
+    );
+    assert_eq!(ThumbnailSettings::default().transcode, None);
+  }
+
+  #[test]
+  fn keeps_truncated_thumbnails_untouched() {
+    let settings = ThumbnailSettings {
+      transcode: Some(Transcode {
+        format: Some(ImageFormat::Png),
+        max_dimension: None,
+        quality: transcode::DEFAULT_QUALITY,
+      }),
+      ..Default::default()
+    };
+    let data = ThumbnailData {
+      bytes: include_bytes!("../__test__/fixtures/cover.bmp")[..40].to_vec(),
+      truncated: true,
+      content_type: Some("image/bmp".to_string()),
+    };
+
+    let normalized = normalize(data, &settings);
+    assert!(normalized.truncated);
+    assert_eq!(normalized.content_type.as_deref(), Some("image/bmp"));
+  }
+
+  #[test]
+  fn includes_thumbnails_unless_disabled() {
+    let options = ThumbnailOptions {
+      include_thumbnail: Some(false),
+      ..Default::default()
+    };
+    let settings = ThumbnailSettings::from_options(Some(&options)).unwrap();
+
+    assert!(!settings.include);
+    assert!(ThumbnailSettings::default().include);
+    // 按需读取时忽略 includeThumbnail
+    assert!(
+      ThumbnailTask::new("a#1".to_string(), settings, DEFAULT_TIMEOUT)
+        .settings
+        .include
+    );
+  }
+}
@@ -0,0 +1,128 @@
+use std::collections::hash_map::DefaultHasher;
+use std::hash::Hasher;
+use std::sync::{Arc, Mutex, OnceLock};
+
+const MAX_ENTRIES: usize = 32;
+const MAX_BYTES: usize = 64 * 1024 * 1024;
+
+// 按内容哈希索引的 LRU 缓存，同一张封面只保存一份
+pub struct ThumbnailCache {
+  // 越靠后越是最近使用的
+  entries: Vec<(String, Arc<[u8]>)>,
+  max_entries: usize,
+  max_bytes: usize,
+  total_bytes: usize,
+}
+
+impl ThumbnailCache {
+  pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+    Self {
+      entries: Vec::new(),
+      max_entries,
+      max_bytes,
+      total_bytes: 0,
+    }
+  }
+
+  pub fn get(&mut self, hash: &str) -> Option<Arc<[u8]>> {
+    let index = self.entries.iter().position(|(key, _)| key == hash)?;
+    let entry = self.entries.remove(index);
+    let bytes = entry.1.clone();
+    self.entries.push(entry);
+    Some(bytes)
+  }
+
+  pub fn insert(&mut self, hash: String, bytes: &[u8]) {
+    if self.get(&hash).is_some() {
+      return;
+    }
+
+    self.total_bytes += bytes.len();
+    self.entries.push((hash, Arc::from(bytes)));
+
+    // 至少保留刚插入的一项
+    while self.entries.len() > 1
+      && (self.entries.len() > self.max_entries || self.total_bytes > self.max_bytes)
+    {
+      let (_, evicted) = self.entries.remove(0);
+      self.total_bytes -= evicted.len();
+    }
+  }
+}
+
+pub fn content_hash(bytes: &[u8]) -> String {
+  let mut hasher = DefaultHasher::new();
+  hasher.write(bytes);
+  format!("{:016x}", hasher.finish())
+}
+
+fn cache() -> &'static Mutex<ThumbnailCache> {
+  static CACHE: OnceLock<Mutex<ThumbnailCache>> = OnceLock::new();
+  CACHE.get_or_init(|| Mutex::new(ThumbnailCache::new(MAX_ENTRIES, MAX_BYTES)))
+}
+
+// 缓存缩略图并返回其内容哈希
+pub fn cache_thumbnail(bytes: &[u8]) -> String {
+  let hash = content_hash(bytes);
+  if let Ok(mut cache) = cache().lock() {
+    cache.insert(hash.clone(), bytes);
+  }
+  hash
+}
+
+pub fn cached_thumbnail(hash: &str) -> Option<Arc<[u8]>> {
+  cache().lock().ok()?.get(hash)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn keys(cache: &ThumbnailCache) -> Vec<&str> {
+    cache.entries.iter().map(|(key, _)| key.as_str()).collect()
+  }
+
+  #[test]
+  fn evicts_least_recently_used_entries() {
+    let mut cache = ThumbnailCache::new(2, usize::MAX);
+    cache.insert("a".to_string(), b"1");
+    cache.insert("b".to_string(), b"2");
+    cache.get("a");
+    cache.insert("c".to_string(), b"3");
+
+    assert_eq!(keys(&cache), ["a", "c"]);
+    assert!(cache.get("b").is_none());
+  }
+
+  #[test]
+  fn evicts_to_stay_within_the_byte_budget() {
+    let mut cache = ThumbnailCache::new(8, 10);
+    cache.insert("a".to_string(), &[0; 6]);
+    cache.insert("b".to_string(), &[0; 6]);
+
+    assert_eq!(keys(&cache), ["b"]);
+    assert_eq!(cache.total_bytes, 6);
+
+    // 单张超过预算的图片仍会被缓存
+    cache.insert("c".to_string(), &[0; 20]);
+    assert_eq!(keys(&cache), ["c"]);
+  }
+
+  #[test]
+  fn stores_identical_content_once() {
+    let mut cache = ThumbnailCache::new(8, usize::MAX);
+    let hash = content_hash(b"cover");
+    cache.insert(hash.clone(), b"cover");
+    cache.insert(hash.clone(), b"cover");
+
+    assert_eq!(cache.entries.len(), 1);
+    assert_eq!(&*cache.get(&hash).unwrap(), b"cover");
+  }
+
+  #[test]
+  fn hashes_by_content() {
+    assert_eq!(content_hash(b"cover"), content_hash(b"cover"));
+    assert_ne!(content_hash(b"cover"), content_hash(b"other"));
+    assert_eq!(content_hash(b"cover").len(), 16);
+  }
+}
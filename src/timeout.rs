@@ -0,0 +1,208 @@
+use napi::{Error, Result, Status};
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::time::Duration;
+#[cfg(windows)]
+use windows::Foundation::{
+  AsyncOperationCompletedHandler, AsyncOperationWithProgressCompletedHandler, IAsyncOperation,
+  IAsyncOperationWithProgress,
+};
+
+#[cfg(windows)]
+use crate::utils::win_to_napi_err;
+
+// 未配置时每个 WinRT 异步操作最多等待 5 秒
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// 可以注册完成回调、取消的异步操作，便于用假的慢操作测试超时逻辑
+pub trait PendingOperation {
+  type Output;
+
+  // 操作完成（包括失败或取消）时发送通知，已完成时立即通知
+  fn on_completed(&self, done: Sender<()>) -> Result<()>;
+  fn results(&self) -> Result<Self::Output>;
+  fn cancel(&self);
+}
+
+#[cfg(windows)]
+impl<T: windows::core::RuntimeType + 'static> PendingOperation for IAsyncOperation<T> {
+  type Output = T;
+
+  fn on_completed(&self, done: Sender<()>) -> Result<()> {
+    win_to_napi_err(
+      self.SetCompleted(&AsyncOperationCompletedHandler::new(move |_, _| {
+        let _ = done.send(());
+        Ok(())
+      })),
+    )
+  }
+
+  fn results(&self) -> Result<T> {
+    win_to_napi_err(self.GetResults())
+  }
+
+  fn cancel(&self) {
+    let _ = self.Cancel();
+  }
+}
+
+#[cfg(windows)]
+impl<T: windows::core::RuntimeType + 'static, P: windows::core::RuntimeType + 'static>
+  PendingOperation for IAsyncOperationWithProgress<T, P>
+{
+  type Output = T;
+
+  fn on_completed(&self, done: Sender<()>) -> Result<()> {
+    win_to_napi_err(
+      self.SetCompleted(&AsyncOperationWithProgressCompletedHandler::new(
+        move |_, _| {
+          let _ = done.send(());
+          Ok(())
+        },
+      )),
+    )
+  }
+
+  fn results(&self) -> Result<T> {
+    win_to_napi_err(self.GetResults())
+  }
+
+  fn cancel(&self) {
+    let _ = self.Cancel();
+  }
+}
+
+// 等待操作完成，超时后取消操作并返回 Cancelled 错误
+pub fn wait<O: PendingOperation>(
+  operation: &O,
+  name: &str,
+  timeout: Duration,
+) -> Result<O::Output> {
+  let (done, completed) = mpsc::channel();
+  operation.on_completed(done)?;
+
+  match completed.recv_timeout(timeout) {
+    Ok(()) => operation.results(),
+    Err(RecvTimeoutError::Timeout) => {
+      operation.cancel();
+      Err(timeout_error(name, timeout))
+    }
+    Err(RecvTimeoutError::Disconnected) => Err(Error::new(
+      Status::GenericFailure,
+      format!("{} was dropped before completing", name),
+    )),
+  }
+}
+
+fn timeout_error(name: &str, timeout: Duration) -> Error {
+  Error::new(
+    Status::Cancelled,
+    format!("{} timed out after {} ms", name, timeout.as_millis()),
+  )
+}
+
+#[cfg(any(windows, test))]
+pub fn is_timeout(err: &Error) -> bool {
+  err.status == Status::Cancelled
+}
+
+pub fn parse_timeout(timeout_ms: u32) -> Result<Duration> {
+  if timeout_ms == 0 {
+    return Err(Error::new(
+      Status::InvalidArg,
+      "Invalid timeoutMs: 0. Expected a positive integer".to_string(),
+    ));
+  }
+
+  Ok(Duration::from_millis(timeout_ms as u64))
+}
+
+// 未指定 timeoutMs 时使用默认超时
+pub fn timeout_or_default(timeout_ms: Option<u32>) -> Result<Duration> {
+  timeout_ms.map_or(Ok(DEFAULT_TIMEOUT), parse_timeout)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use std::sync::atomic::{AtomicBool, Ordering};
+  use std::sync::Arc;
+  use std::thread;
+  use std::time::Instant;
+
+  // 模拟响应缓慢的会话：delay 之后才完成，为 None 时永远不会完成
+  struct SlowOperation {
+    delay: Option<Duration>,
+    cancelled: Arc<AtomicBool>,
+  }
+
+  impl SlowOperation {
+    fn new(delay: Option<Duration>) -> Self {
+      Self {
+        delay,
+        cancelled: Arc::new(AtomicBool::new(false)),
+      }
+    }
+  }
+
+  impl PendingOperation for SlowOperation {
+    type Output = &'static str;
+
+    fn on_completed(&self, done: Sender<()>) -> Result<()> {
+      if let Some(delay) = self.delay {
+        thread::spawn(move || {
+          thread::sleep(delay);
+          let _ = done.send(());
+        });
+      } else {
+        // 保留发送端，模拟一直挂起的操作
+        thread::spawn(move || {
+          thread::sleep(Duration::from_secs(60));
+          drop(done);
+        });
+      }
+      Ok(())
+    }
+
+    fn results(&self) -> Result<&'static str> {
+      Ok("media properties")
+    }
+
+    fn cancel(&self) {
+      self.cancelled.store(true, Ordering::SeqCst);
+    }
+  }
+
+  #[test]
+  fn returns_results_that_complete_in_time() {
+    let operation = SlowOperation::new(Some(Duration::from_millis(10)));
+    let result = wait(&operation, "Reading", Duration::from_secs(5));
+
+    assert_eq!(result.unwrap(), "media properties");
+    assert!(!operation.cancelled.load(Ordering::SeqCst));
+  }
+
+  #[test]
+  fn cancels_hung_operations_after_the_timeout() {
+    let operation = SlowOperation::new(None);
+    let started = Instant::now();
+    let err = wait(
+      &operation,
+      "Reading media properties",
+      Duration::from_millis(50),
+    )
+    .unwrap_err();
+
+    assert!(started.elapsed() < Duration::from_secs(5));
+    assert!(is_timeout(&err));
+    assert_eq!(err.reason, "Reading media properties timed out after 50 ms");
+    assert!(operation.cancelled.load(Ordering::SeqCst));
+  }
+
+  #[test]
+  fn rejects_zero_timeout() {
+    assert_eq!(parse_timeout(0).unwrap_err().status, Status::InvalidArg);
+    assert_eq!(parse_timeout(250).unwrap(), Duration::from_millis(250));
+    assert_eq!(timeout_or_default(None).unwrap(), DEFAULT_TIMEOUT);
+    assert!(timeout_or_default(Some(0)).is_err());
+  }
+}
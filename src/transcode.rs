@@ -0,0 +1,191 @@
+use image::codecs::jpeg::JpegEncoder;
+use image::codecs::png::PngEncoder;
+use image::codecs::webp::WebPEncoder;
+use image::imageops::FilterType;
+use image::DynamicImage;
+use napi::{Error, Result, Status};
+
+use crate::image_info::{self, ImageFormat};
+
+pub const DEFAULT_QUALITY: u8 = 85;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Transcode {
+  // 为空时保持原格式（原格式无法编码时转为 PNG）
+  pub format: Option<ImageFormat>,
+  // 宽高中较大一边的上限，按比例缩放
+  pub max_dimension: Option<u32>,
+  // 仅对 JPEG 生效，WebP 始终为无损编码
+  pub quality: u8,
+}
+
+pub fn parse_format(format: &str) -> Result<ImageFormat> {
+  match format {
+    "png" => Ok(ImageFormat::Png),
+    "jpeg" | "jpg" => Ok(ImageFormat::Jpeg),
+    "webp" => Ok(ImageFormat::WebP),
+    _ => Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "Invalid thumbnail format: {}. Expected 'png', 'jpeg' or 'webp'",
+        format
+      ),
+    )),
+  }
+}
+
+pub fn parse_quality(quality: u32) -> Result<u8> {
+  match quality {
+    1..=100 => Ok(quality as u8),
+    _ => Err(Error::new(
+      Status::InvalidArg,
+      format!(
+        "Invalid thumbnail quality: {}. Expected an integer between 1 and 100",
+        quality
+      ),
+    )),
+  }
+}
+
+fn output_format(source: Option<ImageFormat>, transcode: &Transcode) -> ImageFormat {
+  match (transcode.format, source) {
+    (Some(format), _) => format,
+    (None, Some(format @ (ImageFormat::Png | ImageFormat::Jpeg | ImageFormat::WebP))) => format,
+    (None, _) => ImageFormat::Png,
+  }
+}
+
+// 转换为目标格式与尺寸，返回新的图片数据与其格式；无需转换时原样返回
+pub fn transcode(bytes: &[u8], transcode: &Transcode) -> Result<(Vec<u8>, ImageFormat)> {
+  let source = image_info::detect_format(bytes);
+  let format = output_format(source, transcode);
+  let size = source.and_then(|source| image_info::image_size(source, bytes));
+  let fits = match (size, transcode.max_dimension) {
+    (_, None) => true,
+    (Some((width, height)), Some(max)) => width.max(height) <= max,
+    (None, Some(_)) => false,
+  };
+
+  if source == Some(format) && fits {
+    return Ok((bytes.to_vec(), format));
+  }
+
+  let mut image = image::load_from_memory(bytes).map_err(image_error)?;
+  if let Some(max) = transcode.max_dimension {
+    if image.width().max(image.height()) > max {
+      image = image.resize(max, max, FilterType::Triangle);
+    }
+  }
+
+  Ok((encode(image, format, transcode.quality)?, format))
+}
+
+fn encode(image: DynamicImage, format: ImageFormat, quality: u8) -> Result<Vec<u8>> {
+  let mut bytes = Vec::new();
+  let result = match format {
+    // JPEG 不支持透明通道
+    ImageFormat::Jpeg => DynamicImage::ImageRgb8(image.to_rgb8())
+      .write_with_encoder(JpegEncoder::new_with_quality(&mut bytes, quality)),
+    ImageFormat::WebP => DynamicImage::ImageRgba8(image.to_rgba8())
+      .write_with_encoder(WebPEncoder::new_lossless(&mut bytes)),
+    _ => image.write_with_encoder(PngEncoder::new(&mut bytes)),
+  };
+
+  result.map_err(image_error)?;
+  Ok(bytes)
+}
+
+fn image_error(error: image::ImageError) -> Error {
+  Error::new(
+    Status::GenericFailure,
+    format!("Failed to transcode thumbnail: {}", error),
+  )
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use image::{ImageFormat as Codec, Rgb, RgbImage};
+  use std::io::Cursor;
+
+  const BMP: &[u8] = include_bytes!("../__test__/fixtures/cover.bmp");
+  const PNG: &[u8] = include_bytes!("../__test__/fixtures/cover.png");
+
+  fn options(format: Option<ImageFormat>, max_dimension: Option<u32>) -> Transcode {
+    Transcode {
+      format,
+      max_dimension,
+      quality: DEFAULT_QUALITY,
+    }
+  }
+
+  // 生成带噪点的图片，便于比较不同质量下的体积
+  fn noisy_bmp(width: u32, height: u32) -> Vec<u8> {
+    let image = RgbImage::from_fn(width, height, |x, y| {
+      let n = (x * 7919 + y * 104_729) % 251;
+      Rgb([n as u8, (n * 3 % 251) as u8, (n * 7 % 251) as u8])
+    });
+    let mut bytes = Cursor::new(Vec::new());
+    image.write_to(&mut bytes, Codec::Bmp).unwrap();
+    bytes.into_inner()
+  }
+
+  fn detect(bytes: &[u8]) -> (ImageFormat, (u32, u32)) {
+    let format = image_info::detect_format(bytes).unwrap();
+    (format, image_info::image_size(format, bytes).unwrap())
+  }
+
+  #[test]
+  fn converts_bmp_to_each_output_format() {
+    for format in [ImageFormat::Png, ImageFormat::Jpeg, ImageFormat::WebP] {
+      let (bytes, output) = transcode(BMP, &options(Some(format), None)).unwrap();
+
+      assert_eq!(output, format);
+      assert_eq!(detect(&bytes), (format, (3, 2)));
+    }
+  }
+
+  #[test]
+  fn resizes_to_the_maximum_dimension_keeping_aspect_ratio() {
+    let (bytes, format) = transcode(&noisy_bmp(64, 32), &options(None, Some(16))).unwrap();
+
+    // BMP 无法输出，默认转为 PNG
+    assert_eq!(format, ImageFormat::Png);
+    assert_eq!(detect(&bytes), (ImageFormat::Png, (16, 8)));
+  }
+
+  #[test]
+  fn keeps_images_that_already_match() {
+    let (bytes, format) = transcode(PNG, &options(Some(ImageFormat::Png), Some(16))).unwrap();
+
+    assert_eq!(format, ImageFormat::Png);
+    assert_eq!(bytes, PNG);
+  }
+
+  #[test]
+  fn lower_jpeg_quality_produces_smaller_output() {
+    let source = noisy_bmp(64, 64);
+    let encode = |quality| {
+      let transcode_options = Transcode {
+        quality,
+        ..options(Some(ImageFormat::Jpeg), None)
+      };
+      transcode(&source, &transcode_options).unwrap().0.len()
+    };
+
+    assert!(encode(10) < encode(95));
+  }
+
+  #[test]
+  fn fails_on_truncated_images() {
+    assert!(transcode(&BMP[..40], &options(Some(ImageFormat::Png), None)).is_err());
+  }
+
+  #[test]
+  fn validates_format_and_quality() {
+    assert_eq!(parse_format("jpg").unwrap(), ImageFormat::Jpeg);
+    assert_eq!(parse_format("bmp").unwrap_err().status, Status::InvalidArg);
+    assert_eq!(parse_quality(100).unwrap(), 100);
+    assert_eq!(parse_quality(0).unwrap_err().status, Status::InvalidArg);
+  }
+}
@@ -0,0 +1,279 @@
+use napi::{bindgen_prelude::*, Result};
+use std::time::Duration;
+
+use crate::backend::{self, SharedBackend};
+use crate::session::MediaSession;
+use crate::types::RepeatMode;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TransportCommand {
+  Play,
+  Pause,
+  TogglePlayPause,
+  Stop,
+  SkipNext,
+  SkipPrevious,
+  SetShuffle(bool),
+  SetRepeatMode(RepeatMode),
+  SetPlaybackRate(f64),
+}
+
+impl TransportCommand {
+  pub fn set_repeat_mode(mode: RepeatMode) -> Result<Self> {
+    if mode == RepeatMode::UNRECOGNIZED {
+      return Err(Error::new(
+        Status::InvalidArg,
+        "Invalid repeat mode: UNRECOGNIZED, expected NONE, TRACK or LIST".to_string(),
+      ));
+    }
+
+    Ok(TransportCommand::SetRepeatMode(mode))
+  }
+
+  pub fn set_playback_rate(rate: f64) -> Result<Self> {
+    if !rate.is_finite() || rate <= 0.0 {
+      return Err(Error::new(
+        Status::InvalidArg,
+        format!(
+          "Invalid playback rate: {}, expected a positive number",
+          rate
+        ),
+      ));
+    }
+
+    Ok(TransportCommand::SetPlaybackRate(rate))
+  }
+
+  pub fn name(&self) -> &'static str {
+    match self {
+      TransportCommand::Play => "play",
+      TransportCommand::Pause => "pause",
+      TransportCommand::TogglePlayPause => "toggle play/pause",
+      TransportCommand::Stop => "stop",
+      TransportCommand::SkipNext => "skip next",
+      TransportCommand::SkipPrevious => "skip previous",
+      TransportCommand::SetShuffle(_) => "set shuffle on",
+      TransportCommand::SetRepeatMode(_) => "set repeat mode on",
+      TransportCommand::SetPlaybackRate(_) => "set playback rate on",
+    }
+  }
+}
+
+// 将命令路由到会话对应的 Try*Async 方法，并为错误附带命令上下文
+pub fn send_command<S: MediaSession>(
+  session: &S,
+  command: TransportCommand,
+  timeout: Duration,
+) -> Result<bool> {
+  let result = match command {
+    TransportCommand::Play => session.try_play(timeout),
+    TransportCommand::Pause => session.try_pause(timeout),
+    TransportCommand::TogglePlayPause => session.try_toggle_play_pause(timeout),
+    TransportCommand::Stop => session.try_stop(timeout),
+    TransportCommand::SkipNext => session.try_skip_next(timeout),
+    TransportCommand::SkipPrevious => session.try_skip_previous(timeout),
+    TransportCommand::SetShuffle(active) => session.try_change_shuffle_active(active, timeout),
+    TransportCommand::SetRepeatMode(mode) => session.try_change_auto_repeat_mode(mode, timeout),
+    TransportCommand::SetPlaybackRate(rate) => session.try_change_playback_rate(rate, timeout),
+  };
+
+  result.map_err(|e| {
+    Error::new(
+      Status::GenericFailure,
+      format!("Failed to {} media session: {}", command.name(), e.reason),
+    )
+  })
+}
+
+// 按会话键或 sourceAppId 选择会话（会话键优先），未指定时使用当前会话
+pub fn select_session<S, I>(
+  sessions: I,
+  current: Option<S>,
+  source_app_id: Option<&str>,
+) -> Option<S>
+where
+  S: MediaSession,
+  I: IntoIterator<Item = S>,
+{
+  let target = match source_app_id {
+    Some(target) => target,
+    None => return current,
+  };
+
+  let mut sessions: Vec<S> = sessions.into_iter().collect();
+  let index = sessions
+    .iter()
+    .position(|session| matches!(session.session_key(), Ok(key) if key == target))
+    .or_else(|| {
+      sessions
+        .iter()
+        .position(|session| matches!(session.source_app_id(), Ok(id) if id == target))
+    })?;
+
+  Some(sessions.swap_remove(index))
+}
+
+pub fn session_not_found(source_app_id: Option<&str>) -> Error {
+  match source_app_id {
+    Some(id) => Error::new(
+      Status::InvalidArg,
+      format!("No media session found for sourceAppId \"{}\"", id),
+    ),
+    None => Error::new(Status::InvalidArg, "No current media session".to_string()),
+  }
+}
+
+pub struct TransportTask {
+  backend: Option<SharedBackend>,
+  source_app_id: Option<String>,
+  command: TransportCommand,
+  timeout: Duration,
+}
+
+impl TransportTask {
+  pub fn new(
+    backend: Option<SharedBackend>,
+    source_app_id: Option<String>,
+    command: TransportCommand,
+    timeout: Duration,
+  ) -> Self {
+    Self {
+      backend,
+      source_app_id,
+      command,
+      timeout,
+    }
+  }
+}
+
+impl Task for TransportTask {
+  type Output = bool;
+  type JsValue = bool;
+
+  fn compute(&mut self) -> Result<Self::Output> {
+    let backend = backend::resolve(self.backend.as_ref(), self.timeout)?;
+
+    let source_app_id = self.source_app_id.as_deref();
+    let session = backend::find_session(&*backend, source_app_id)
+      .ok_or_else(|| session_not_found(source_app_id))?;
+
+    send_command(&session, self.command, self.timeout)
+  }
+
+  fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+    Ok(output)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::session::fake::FakeSession;
+  use crate::timeout::DEFAULT_TIMEOUT;
+
+  #[test]
+  fn routes_each_command_to_matching_session_method() {
+    let session = FakeSession::new("player.exe");
+    let commands = [
+      (TransportCommand::Play, "play"),
+      (TransportCommand::Pause, "pause"),
+      (TransportCommand::TogglePlayPause, "toggle"),
+      (TransportCommand::Stop, "stop"),
+      (TransportCommand::SkipNext, "next"),
+      (TransportCommand::SkipPrevious, "previous"),
+      (TransportCommand::SetShuffle(true), "shuffle:true"),
+      (
+        TransportCommand::SetRepeatMode(RepeatMode::LIST),
+        "repeat:LIST",
+      ),
+      (TransportCommand::SetPlaybackRate(1.5), "rate:1.5"),
+    ];
+
+    for (command, expected) in commands {
+      assert!(send_command(&session, command, DEFAULT_TIMEOUT).unwrap());
+      assert_eq!(session.last_received().as_deref(), Some(expected));
+    }
+  }
+
+  #[test]
+  fn reports_when_player_rejects_command() {
+    let mut session = FakeSession::new("player.exe");
+    session.accept = false;
+
+    assert!(!send_command(&session, TransportCommand::SkipNext, DEFAULT_TIMEOUT).unwrap());
+  }
+
+  #[test]
+  fn maps_session_errors_with_command_context() {
+    let mut session = FakeSession::new("player.exe");
+    session.fail = true;
+
+    let err = send_command(&session, TransportCommand::Pause, DEFAULT_TIMEOUT).unwrap_err();
+    assert_eq!(err.status, Status::GenericFailure);
+    assert_eq!(err.reason, "Failed to pause media session: 0x80004005");
+  }
+
+  #[test]
+  fn validates_repeat_mode_and_playback_rate() {
+    assert_eq!(
+      TransportCommand::set_repeat_mode(RepeatMode::TRACK).unwrap(),
+      TransportCommand::SetRepeatMode(RepeatMode::TRACK)
+    );
+    assert_eq!(
+      TransportCommand::set_repeat_mode(RepeatMode::UNRECOGNIZED)
+        .unwrap_err()
+        .status,
+      Status::InvalidArg
+    );
+
+    assert_eq!(
+      TransportCommand::set_playback_rate(0.5).unwrap(),
+      TransportCommand::SetPlaybackRate(0.5)
+    );
+    for rate in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+      assert!(TransportCommand::set_playback_rate(rate).is_err());
+    }
+  }
+
+  #[test]
+  fn selects_session_by_source_app_id_or_current() {
+    let a = FakeSession::new("a.exe");
+    let b = FakeSession::new("b.exe");
+    let sessions = || vec![a.clone(), b.clone()];
+
+    let selected = select_session(sessions(), Some(a.clone()), Some("b.exe")).unwrap();
+    assert_eq!(selected.id, "b.exe");
+
+    let selected = select_session(sessions(), Some(a.clone()), None).unwrap();
+    assert_eq!(selected.id, "a.exe");
+
+    assert!(select_session(sessions(), Some(a.clone()), Some("c.exe")).is_none());
+    assert!(select_session(Vec::<FakeSession>::new(), None, None).is_none());
+  }
+
+  #[test]
+  fn selects_between_sessions_of_the_same_app_by_key() {
+    let first = FakeSession::new("chrome.exe");
+    let mut second = FakeSession::new("chrome.exe");
+    second.key = "chrome.exe#2".to_string();
+    let sessions = || vec![first.clone(), second.clone()];
+
+    let selected = select_session(sessions(), None, Some("chrome.exe#2")).unwrap();
+    assert_eq!(selected.key, "chrome.exe#2");
+
+    // 仅给出 sourceAppId 时选中该应用的第一个会话
+    let selected = select_session(sessions(), None, Some("chrome.exe")).unwrap();
+    assert_eq!(selected.key, "chrome.exe#1");
+  }
+
+  #[test]
+  fn missing_session_is_invalid_arg() {
+    let err = session_not_found(Some("c.exe"));
+    assert_eq!(err.status, Status::InvalidArg);
+    assert_eq!(
+      err.reason,
+      "No media session found for sourceAppId \"c.exe\""
+    );
+    assert_eq!(session_not_found(None).reason, "No current media session");
+  }
+}
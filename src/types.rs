@@ -1,22 +1,197 @@
+use napi::bindgen_prelude::{Buffer, FromNapiValue, ToNapiValue, TypeName, ValueType};
+use napi::sys;
 use napi_derive::napi;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
 use std::fmt;
+use std::sync::Arc;
+
+use crate::palette::ThumbnailPalette;
 
 #[napi(object)]
-#[derive(Clone)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct TimelineProps {
   pub position: f64,
   pub duration: f64,
+  pub start_time: f64,
+  pub min_seek_time: f64,
+  pub max_seek_time: f64,
+  // 播放器上报此时间线的时间（Unix 毫秒），position 仅在该时刻准确
+  pub last_updated_time: Option<f64>,
+  // 按播放状态和速率推算出的读取时刻的位置
+  pub estimated_position: Option<f64>,
+}
+
+// 与 GlobalSystemMediaTransportControlsSessionPlaybackStatus 的取值一致，
+// 变体名沿用 JS 常量的写法，生成的 TypeScript 定义保持不变
+#[napi]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlaybackStatus {
+  CLOSED = 0,
+  OPENED = 1,
+  CHANGING = 2,
+  STOPPED = 3,
+  PLAYING = 4,
+  PAUSED = 5,
+  // 当前版本无法识别的新取值
+  UNRECOGNIZED = -1,
+}
+
+// 与 MediaPlaybackType 的取值一致，播放器未提供时为 UNKNOWN
+#[napi]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum PlaybackType {
+  UNKNOWN = 0,
+  MUSIC = 1,
+  VIDEO = 2,
+  IMAGE = 3,
+  UNRECOGNIZED = -1,
+}
+
+// 与 MediaPlaybackAutoRepeatMode 的取值一致
+#[napi]
+#[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum RepeatMode {
+  NONE = 0,
+  TRACK = 1,
+  LIST = 2,
+  UNRECOGNIZED = -1,
+}
+
+// 枚举按数值序列化，与 JS 中看到的取值一致；无法识别的数值读回为 UNRECOGNIZED
+impl Serialize for PlaybackStatus {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i32(*self as i32)
+  }
+}
+
+impl<'de> Deserialize<'de> for PlaybackStatus {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    Ok(match i32::deserialize(deserializer)? {
+      0 => Self::CLOSED,
+      1 => Self::OPENED,
+      2 => Self::CHANGING,
+      3 => Self::STOPPED,
+      4 => Self::PLAYING,
+      5 => Self::PAUSED,
+      _ => Self::UNRECOGNIZED,
+    })
+  }
+}
+
+impl Serialize for PlaybackType {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i32(*self as i32)
+  }
+}
+
+impl<'de> Deserialize<'de> for PlaybackType {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    Ok(match i32::deserialize(deserializer)? {
+      0 => Self::UNKNOWN,
+      1 => Self::MUSIC,
+      2 => Self::VIDEO,
+      3 => Self::IMAGE,
+      _ => Self::UNRECOGNIZED,
+    })
+  }
+}
+
+impl Serialize for RepeatMode {
+  fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_i32(*self as i32)
+  }
+}
+
+impl<'de> Deserialize<'de> for RepeatMode {
+  fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+    Ok(match i32::deserialize(deserializer)? {
+      0 => Self::NONE,
+      1 => Self::TRACK,
+      2 => Self::LIST,
+      _ => Self::UNRECOGNIZED,
+    })
+  }
 }
 
 #[napi(object)]
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct PlaybackInfo {
-  pub playback_status: u8,
-  pub playback_type: u8,
+  pub playback_status: PlaybackStatus,
+  pub playback_type: PlaybackType,
+  pub is_shuffle_active: Option<bool>,
+  pub auto_repeat_mode: Option<RepeatMode>,
+  pub playback_rate: Option<f64>,
+  pub controls: PlaybackControls,
+}
+
+// 播放器声明为可用的控制项
+#[napi(object)]
+#[derive(Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaybackControls {
+  pub play: bool,
+  pub pause: bool,
+  pub stop: bool,
+  pub record: bool,
+  pub fast_forward: bool,
+  pub rewind: bool,
+  pub next: bool,
+  pub previous: bool,
+  pub channel_up: bool,
+  pub channel_down: bool,
+  pub play_pause_toggle: bool,
+  pub shuffle: bool,
+  pub repeat: bool,
+  pub playback_rate: bool,
+  pub seek: bool,
+}
+
+// 缩略图数据，转换为 JS 值时才创建 Buffer，Rust 侧的克隆与释放不依赖 Node 运行时
+#[derive(Clone, PartialEq)]
+pub struct ThumbnailBytes(Arc<[u8]>);
+
+impl ThumbnailBytes {
+  pub fn as_bytes(&self) -> &[u8] {
+    &self.0
+  }
+}
+
+impl From<Vec<u8>> for ThumbnailBytes {
+  fn from(bytes: Vec<u8>) -> Self {
+    Self(bytes.into())
+  }
+}
+
+impl TypeName for ThumbnailBytes {
+  fn type_name() -> &'static str {
+    "Buffer"
+  }
+
+  fn value_type() -> ValueType {
+    ValueType::Object
+  }
+}
+
+impl ToNapiValue for ThumbnailBytes {
+  unsafe fn to_napi_value(env: sys::napi_env, val: Self) -> napi::Result<sys::napi_value> {
+    Buffer::to_napi_value(env, Buffer::from(val.0.to_vec()))
+  }
+}
+
+impl FromNapiValue for ThumbnailBytes {
+  unsafe fn from_napi_value(env: sys::napi_env, val: sys::napi_value) -> napi::Result<Self> {
+    Buffer::from_napi_value(env, val).map(|buffer| Self(buffer.to_vec().into()))
+  }
 }
 
 #[napi(object)]
-#[derive(Clone)]
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MediaProps {
   pub title: String,
   pub artist: String,
@@ -25,28 +200,46 @@ pub struct MediaProps {
   pub genres: Vec<String>,
   pub album_track_count: u32,
   pub track_number: u32,
+  // 序列化时不写入图片数据，只保留下面的哈希与格式信息
   #[napi(ts_type = "Buffer | undefined")]
-  pub thumbnail: Option<napi::bindgen_prelude::Buffer>,
+  #[serde(skip)]
+  pub thumbnail: Option<ThumbnailBytes>,
+  // 缩略图超过读取上限时只返回前面的部分
+  pub thumbnail_truncated: bool,
+  // 缩略图内容哈希，可通过 getThumbnailByHash(hash) 取回图片
+  pub thumbnail_hash: Option<String>,
+  // 根据文件头识别出的格式与尺寸
+  pub thumbnail_mime_type: Option<String>,
+  pub thumbnail_width: Option<u32>,
+  pub thumbnail_height: Option<u32>,
+  // 仅在 palette 选项开启时提取
+  pub thumbnail_palette: Option<ThumbnailPalette>,
 }
 
 #[napi(object)]
-#[derive(Clone)]
+#[derive(Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct MediaInfo {
   pub source_app_id: String,
+  pub session_key: String,
   pub media: MediaProps,
   pub playback: PlaybackInfo,
   pub timeline: TimelineProps,
   pub last_updated_time: f64,
+  // 读取超时时只返回部分信息，此处为超时原因
+  pub error: Option<String>,
 }
 
 impl fmt::Debug for MediaInfo {
   fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
     f.debug_struct("MediaInfo")
       .field("source_app_id", &self.source_app_id)
+      .field("session_key", &self.session_key)
       .field("media", &self.media)
       .field("playback", &self.playback)
       .field("timeline", &self.timeline)
       .field("last_updated_time", &self.last_updated_time)
+      .field("error", &self.error)
       .finish()
   }
 }
@@ -62,6 +255,12 @@ impl fmt::Debug for MediaProps {
       .field("album_track_count", &self.album_track_count)
       .field("track_number", &self.track_number)
       .field("thumbnail", &"[Buffer]")
+      .field("thumbnail_truncated", &self.thumbnail_truncated)
+      .field("thumbnail_hash", &self.thumbnail_hash)
+      .field("thumbnail_mime_type", &self.thumbnail_mime_type)
+      .field("thumbnail_width", &self.thumbnail_width)
+      .field("thumbnail_height", &self.thumbnail_height)
+      .field("thumbnail_palette", &self.thumbnail_palette)
       .finish()
   }
 }
@@ -71,6 +270,32 @@ impl fmt::Debug for PlaybackInfo {
     f.debug_struct("PlaybackInfo")
       .field("playback_status", &self.playback_status)
       .field("playback_type", &self.playback_type)
+      .field("is_shuffle_active", &self.is_shuffle_active)
+      .field("auto_repeat_mode", &self.auto_repeat_mode)
+      .field("playback_rate", &self.playback_rate)
+      .field("controls", &self.controls)
+      .finish()
+  }
+}
+
+impl fmt::Debug for PlaybackControls {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("PlaybackControls")
+      .field("play", &self.play)
+      .field("pause", &self.pause)
+      .field("stop", &self.stop)
+      .field("record", &self.record)
+      .field("fast_forward", &self.fast_forward)
+      .field("rewind", &self.rewind)
+      .field("next", &self.next)
+      .field("previous", &self.previous)
+      .field("channel_up", &self.channel_up)
+      .field("channel_down", &self.channel_down)
+      .field("play_pause_toggle", &self.play_pause_toggle)
+      .field("shuffle", &self.shuffle)
+      .field("repeat", &self.repeat)
+      .field("playback_rate", &self.playback_rate)
+      .field("seek", &self.seek)
       .finish()
   }
 }
@@ -80,6 +305,11 @@ impl fmt::Debug for TimelineProps {
     f.debug_struct("TimelineProps")
       .field("position", &self.position)
       .field("duration", &self.duration)
+      .field("start_time", &self.start_time)
+      .field("min_seek_time", &self.min_seek_time)
+      .field("max_seek_time", &self.max_seek_time)
+      .field("last_updated_time", &self.last_updated_time)
+      .field("estimated_position", &self.estimated_position)
       .finish()
   }
 }
@@ -1,41 +1,78 @@
-use napi::{bindgen_prelude::Buffer, Error, Result, Status};
+#[cfg(windows)]
+use napi::{Error, Result, Status};
+#[cfg(windows)]
+use std::time::Duration;
 use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(windows)]
 use windows::{
   core,
-  Foundation::TimeSpan,
+  Foundation::{DateTime, TimeSpan},
   Media::{
     Control::{
       GlobalSystemMediaTransportControlsSession,
+      GlobalSystemMediaTransportControlsSessionMediaProperties,
       GlobalSystemMediaTransportControlsSessionPlaybackStatus,
     },
-    MediaPlaybackType,
+    MediaPlaybackAutoRepeatMode, MediaPlaybackType,
   },
-  Storage::Streams::{Buffer as WinBuffer, DataReader, InputStreamOptions},
 };
 
-use crate::{types::MediaInfo, MediaProps, PlaybackInfo, TimelineProps};
+#[cfg(windows)]
+use crate::controls::read_playback_controls;
+use crate::image_info;
+use crate::palette;
+#[cfg(windows)]
+use crate::position::estimate_position;
+#[cfg(windows)]
+use crate::session_key;
+#[cfg(windows)]
+use crate::thumbnail;
+use crate::thumbnail::{ThumbnailData, ThumbnailSettings};
+use crate::thumbnail_cache;
+#[cfg(windows)]
+use crate::timeout;
+use crate::MediaProps;
+#[cfg(windows)]
+use crate::{MediaInfo, PlaybackInfo, PlaybackStatus, PlaybackType, RepeatMode, TimelineProps};
 
+#[cfg(windows)]
 pub fn win_to_napi_err<T>(result: core::Result<T>) -> Result<T> {
   result.map_err(|e| Error::new(Status::GenericFailure, e.to_string()))
 }
 
+#[cfg(windows)]
 pub fn timespan_to_seconds(ts: TimeSpan) -> f64 {
   ts.Duration as f64 / 10_000_000.0
 }
 
-pub fn buffer_to_napi_buffer(win_buffer: &WinBuffer) -> Result<Option<Buffer>> {
-  let length = win_to_napi_err(win_buffer.Length())?;
-  if length == 0 {
-    return Ok(None);
+pub fn now_millis() -> f64 {
+  SystemTime::now()
+    .duration_since(UNIX_EPOCH)
+    .unwrap_or_default()
+    .as_millis() as f64
+}
+
+#[cfg(windows)]
+pub fn seconds_to_timespan(seconds: f64) -> TimeSpan {
+  TimeSpan {
+    Duration: (seconds * 10_000_000.0).round() as i64,
   }
+}
+
+// DateTime 以 1601-01-01 起的 100 纳秒为单位，0 表示未设置
+#[cfg(windows)]
+const UNIX_EPOCH_TICKS: i64 = 116_444_736_000_000_000;
 
-  let mut bytes = vec![0u8; length as usize];
-  let data_reader = win_to_napi_err(DataReader::FromBuffer(win_buffer))?;
-  win_to_napi_err(data_reader.ReadBytes(&mut bytes))?;
+#[cfg(windows)]
+pub fn datetime_to_unix_millis(dt: DateTime) -> Option<f64> {
+  if dt.UniversalTime <= 0 {
+    return None;
+  }
 
-  Ok(Some(bytes.into()))
+  Some((dt.UniversalTime - UNIX_EPOCH_TICKS) as f64 / 10_000.0)
 }
 
+#[cfg(windows)]
 // 安全地运行Windows API调用并处理可能的错误
 pub fn try_win_api<T, F>(op: F) -> Option<T>
 where
@@ -44,15 +81,42 @@ where
   op().ok()
 }
 
+#[cfg(windows)]
+// 请求媒体属性，超时时返回错误，其它失败返回 None
+pub fn request_media_props(
+  session: &GlobalSystemMediaTransportControlsSession,
+  timeout: Duration,
+) -> Result<Option<GlobalSystemMediaTransportControlsSessionMediaProperties>> {
+  let operation = match try_win_api(|| session.TryGetMediaPropertiesAsync()) {
+    Some(operation) => operation,
+    None => return Ok(None),
+  };
+
+  match timeout::wait(&operation, "Reading media properties", timeout) {
+    Ok(props) => Ok(Some(props)),
+    Err(err) if timeout::is_timeout(&err) => Err(err),
+    Err(_) => Ok(None),
+  }
+}
+
+#[cfg(windows)]
 pub fn get_media_props_for_session(
   session: &GlobalSystemMediaTransportControlsSession,
+  thumbnail_settings: &ThumbnailSettings,
+  timeout: Duration,
 ) -> Result<Option<MediaProps>> {
+  Ok(read_media_props(session, thumbnail_settings, timeout)?.map(|(media, _)| media))
+}
+
+#[cfg(windows)]
+// 缩略图读取超时时返回不含缩略图的属性，并附带超时错误
+fn read_media_props(
+  session: &GlobalSystemMediaTransportControlsSession,
+  thumbnail_settings: &ThumbnailSettings,
+  timeout: Duration,
+) -> Result<Option<(MediaProps, Option<Error>)>> {
   // 尝试获取媒体属性
-  let media_props = match try_win_api(|| {
-    session
-      .TryGetMediaPropertiesAsync()
-      .and_then(|props_async| props_async.get())
-  }) {
+  let media_props = match request_media_props(session, timeout)? {
     Some(props) => props,
     None => return Ok(None),
   };
@@ -84,22 +148,13 @@ pub fn get_media_props_for_session(
   let track_number = win_to_napi_err(media_props.TrackNumber())?;
 
   // 提取缩略图
-  let thumbnail = try_win_api(|| media_props.Thumbnail())
-    .and_then(|thumbnail| try_win_api(|| thumbnail.OpenReadAsync().and_then(|op| op.get())))
-    .and_then(|stream| {
-      try_win_api(|| WinBuffer::Create(1024 * 1024)).and_then(|buffer| {
-        try_win_api(|| buffer.Capacity()).and_then(|capacity| {
-          try_win_api(|| {
-            stream
-              .ReadAsync(&buffer, capacity, InputStreamOptions::None)
-              .and_then(|read_op| read_op.get())
-          })
-          .and_then(|_| buffer_to_napi_buffer(&buffer).ok().flatten())
-        })
-      })
-    });
+  let (thumbnail, error) =
+    match thumbnail::load_thumbnail(&media_props, thumbnail_settings, timeout) {
+      Ok(thumbnail) => (thumbnail, None),
+      Err(err) => (None, Some(err)),
+    };
 
-  Ok(Some(MediaProps {
+  let mut media = MediaProps {
     title,
     artist,
     album_title,
@@ -107,53 +162,138 @@ pub fn get_media_props_for_session(
     genres,
     album_track_count: album_track_count.try_into().unwrap_or(0),
     track_number: track_number.try_into().unwrap_or(0),
-    thumbnail,
-  }))
+    ..MediaProps::default()
+  };
+  apply_thumbnail(&mut media, thumbnail, thumbnail_settings);
+
+  Ok(Some((media, error)))
+}
+
+// 写入缩略图，并附带哈希、格式、尺寸与调色板
+pub fn apply_thumbnail(
+  media: &mut MediaProps,
+  thumbnail: Option<ThumbnailData>,
+  thumbnail_settings: &ThumbnailSettings,
+) {
+  let thumbnail = match thumbnail {
+    Some(thumbnail) => thumbnail,
+    None => return,
+  };
+
+  let info = image_info::detect_image(&thumbnail.bytes, thumbnail.content_type.as_deref());
+  media.thumbnail_truncated = thumbnail.truncated;
+  media.thumbnail_hash = Some(thumbnail_cache::cache_thumbnail(&thumbnail.bytes));
+  media.thumbnail_mime_type = info.as_ref().map(|info| info.mime_type.clone());
+  media.thumbnail_width = info.as_ref().and_then(|info| info.width);
+  media.thumbnail_height = info.as_ref().and_then(|info| info.height);
+  media.thumbnail_palette = Some(&thumbnail)
+    .filter(|data| thumbnail_settings.palette && !data.truncated)
+    .and_then(|data| palette::extract_palette(&data.bytes));
+  media.thumbnail = Some(thumbnail.bytes.into());
+}
+
+#[cfg(windows)]
+fn to_playback_status(
+  status: GlobalSystemMediaTransportControlsSessionPlaybackStatus,
+) -> PlaybackStatus {
+  match status {
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Closed => PlaybackStatus::CLOSED,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Opened => PlaybackStatus::OPENED,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Changing => PlaybackStatus::CHANGING,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Stopped => PlaybackStatus::STOPPED,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Playing => PlaybackStatus::PLAYING,
+    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Paused => PlaybackStatus::PAUSED,
+    _ => PlaybackStatus::UNRECOGNIZED,
+  }
+}
+
+#[cfg(windows)]
+fn to_playback_type(playback_type: MediaPlaybackType) -> PlaybackType {
+  match playback_type {
+    MediaPlaybackType::Unknown => PlaybackType::UNKNOWN,
+    MediaPlaybackType::Music => PlaybackType::MUSIC,
+    MediaPlaybackType::Video => PlaybackType::VIDEO,
+    MediaPlaybackType::Image => PlaybackType::IMAGE,
+    _ => PlaybackType::UNRECOGNIZED,
+  }
 }
 
+#[cfg(windows)]
 pub fn get_playback_info_for_session(
   session: &GlobalSystemMediaTransportControlsSession,
 ) -> Result<Option<PlaybackInfo>> {
   let playback_info = win_to_napi_err(session.GetPlaybackInfo())?;
 
-  let playback_status = match win_to_napi_err(playback_info.PlaybackStatus())? {
-    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Closed => 0,
-    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Opened => 1,
-    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Changing => 2,
-    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Stopped => 3,
-    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Playing => 4,
-    GlobalSystemMediaTransportControlsSessionPlaybackStatus::Paused => 5,
-    _ => 0,
-  };
+  let playback_status = to_playback_status(win_to_napi_err(playback_info.PlaybackStatus())?);
 
   let playback_type = try_win_api(|| playback_info.PlaybackType().and_then(|pt| pt.Value()))
-    .map(|pt| match pt {
-      MediaPlaybackType::Unknown => 0,
-      MediaPlaybackType::Music => 1,
-      MediaPlaybackType::Video => 2,
-      MediaPlaybackType::Image => 3,
-      _ => 0,
-    })
-    .unwrap_or(0);
+    .map(to_playback_type)
+    .unwrap_or(PlaybackType::UNKNOWN);
+
+  // 以下属性由播放器选择性提供，未提供时为 None
+  let is_shuffle_active = try_win_api(|| playback_info.IsShuffleActive().and_then(|v| v.Value()));
+
+  let auto_repeat_mode = try_win_api(|| playback_info.AutoRepeatMode().and_then(|m| m.Value()))
+    .map(|mode| match mode {
+      MediaPlaybackAutoRepeatMode::None => RepeatMode::NONE,
+      MediaPlaybackAutoRepeatMode::Track => RepeatMode::TRACK,
+      MediaPlaybackAutoRepeatMode::List => RepeatMode::LIST,
+      _ => RepeatMode::UNRECOGNIZED,
+    });
+
+  let playback_rate = try_win_api(|| playback_info.PlaybackRate().and_then(|r| r.Value()));
+
+  let controls = try_win_api(|| playback_info.Controls())
+    .map(|controls| read_playback_controls(&controls))
+    .unwrap_or_default();
 
   Ok(Some(PlaybackInfo {
     playback_status,
     playback_type,
+    is_shuffle_active,
+    auto_repeat_mode,
+    playback_rate,
+    controls,
   }))
 }
 
+// 推算位置需要播放状态与速率，由调用方传入已读取的播放信息，避免重复读取
+#[cfg(windows)]
 pub fn get_timeline_props_for_session(
   session: &GlobalSystemMediaTransportControlsSession,
+  playback: Option<&PlaybackInfo>,
 ) -> Result<Option<TimelineProps>> {
   let timeline_props = win_to_napi_err(session.GetTimelineProperties())?;
   let position = timespan_to_seconds(win_to_napi_err(timeline_props.Position())?);
   let duration = timespan_to_seconds(win_to_napi_err(timeline_props.EndTime())?);
+  let start_time = timespan_to_seconds(win_to_napi_err(timeline_props.StartTime())?);
+  let min_seek_time = timespan_to_seconds(win_to_napi_err(timeline_props.MinSeekTime())?);
+  let max_seek_time = timespan_to_seconds(win_to_napi_err(timeline_props.MaxSeekTime())?);
+  let last_updated_time =
+    try_win_api(|| timeline_props.LastUpdatedTime()).and_then(datetime_to_unix_millis);
 
-  Ok(Some(TimelineProps { position, duration }))
+  let mut timeline = TimelineProps {
+    position,
+    duration,
+    start_time,
+    min_seek_time,
+    max_seek_time,
+    last_updated_time,
+    estimated_position: None,
+  };
+
+  if let Some(playback) = playback {
+    timeline.estimated_position = Some(estimate_position(&timeline, playback, now_millis()));
+  }
+
+  Ok(Some(timeline))
 }
 
+#[cfg(windows)]
 pub fn get_media_info_for_session(
   session: &GlobalSystemMediaTransportControlsSession,
+  thumbnail_settings: &ThumbnailSettings,
+  timeout: Duration,
 ) -> Result<Option<MediaInfo>> {
   // 获取应用ID
   let source_app_id = match win_to_napi_err(session.SourceAppUserModelId()) {
@@ -161,26 +301,101 @@ pub fn get_media_info_for_session(
     Err(_) => return Ok(None),
   };
 
-  // 使用提取操作符简化代码结构
-  let media = get_media_props_for_session(session)?
-    .ok_or_else(|| Error::new(Status::GenericFailure, "Failed to get media properties"))?;
+  let session_key = session_key::session_key(session, &source_app_id);
+
+  // 读取超时的会话作为部分结果返回，不影响其它会话
+  let (media, error) = match read_media_props(session, thumbnail_settings, timeout) {
+    Ok(Some((media, error))) => (media, error),
+    Ok(None) => {
+      return Err(Error::new(
+        Status::GenericFailure,
+        "Failed to get media properties",
+      ))
+    }
+    Err(err) if timeout::is_timeout(&err) => (MediaProps::default(), Some(err)),
+    Err(err) => return Err(err),
+  };
 
   let playback = get_playback_info_for_session(session)?
     .ok_or_else(|| Error::new(Status::GenericFailure, "Failed to get playback info"))?;
 
-  let timeline = get_timeline_props_for_session(session)?
+  let timeline = get_timeline_props_for_session(session, Some(&playback))?
     .ok_or_else(|| Error::new(Status::GenericFailure, "Failed to get timeline properties"))?;
 
-  let last_updated_time = SystemTime::now()
-    .duration_since(UNIX_EPOCH)
-    .unwrap_or_default()
-    .as_millis() as f64;
+  let last_updated_time = now_millis();
 
   Ok(Some(MediaInfo {
     source_app_id,
+    session_key,
     media,
     playback,
     timeline,
     last_updated_time,
+    error: error.map(|err| err.reason),
   }))
 }
+
+// 这些转换只在 Windows 上编译
+#[cfg(all(test, windows))]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn converts_between_seconds_and_timespan() {
+    assert_eq!(seconds_to_timespan(1.0).Duration, 10_000_000);
+    assert_eq!(seconds_to_timespan(217.228).Duration, 2_172_280_000);
+    assert_eq!(
+      timespan_to_seconds(TimeSpan {
+        Duration: 2_590_000_000
+      }),
+      259.0
+    );
+
+    for seconds in [0.0, 0.5, 12.345_678_9, 3600.25] {
+      assert_eq!(timespan_to_seconds(seconds_to_timespan(seconds)), seconds);
+    }
+  }
+
+  #[test]
+  fn converts_datetime_to_unix_millis() {
+    let epoch = DateTime {
+      UniversalTime: UNIX_EPOCH_TICKS,
+    };
+    assert_eq!(datetime_to_unix_millis(epoch), Some(0.0));
+
+    // 2025-02-19T21:20:00.123Z
+    let dt = DateTime {
+      UniversalTime: UNIX_EPOCH_TICKS + 1_740_000_000_123 * 10_000,
+    };
+    assert_eq!(datetime_to_unix_millis(dt), Some(1_740_000_000_123.0));
+
+    assert_eq!(datetime_to_unix_millis(DateTime::default()), None);
+  }
+
+  #[test]
+  fn rounds_to_nearest_tick() {
+    assert_eq!(seconds_to_timespan(0.000_000_04).Duration, 0);
+    assert_eq!(seconds_to_timespan(0.000_000_06).Duration, 1);
+    assert_eq!(seconds_to_timespan(-1.5).Duration, -15_000_000);
+  }
+
+  #[test]
+  fn maps_unrecognized_playback_values_explicitly() {
+    assert_eq!(
+      to_playback_status(GlobalSystemMediaTransportControlsSessionPlaybackStatus::Paused),
+      PlaybackStatus::PAUSED
+    );
+    assert_eq!(
+      to_playback_status(GlobalSystemMediaTransportControlsSessionPlaybackStatus(42)),
+      PlaybackStatus::UNRECOGNIZED
+    );
+    assert_eq!(
+      to_playback_type(MediaPlaybackType::Unknown),
+      PlaybackType::UNKNOWN
+    );
+    assert_eq!(
+      to_playback_type(MediaPlaybackType(42)),
+      PlaybackType::UNRECOGNIZED
+    );
+  }
+}